@@ -0,0 +1,98 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::database::models::MergeAudit;
+
+/// Which master data table a merge was done on.
+#[derive(Debug, Clone, Copy)]
+pub enum MergeEntity {
+    Product,
+    Line,
+    Client,
+}
+
+impl MergeEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MergeEntity::Product => "product",
+            MergeEntity::Line => "line",
+            MergeEntity::Client => "client",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergeResponse<T> {
+    pub record: T, // The surviving record
+    pub reports_moved: u64,
+}
+
+/// One side of a merge, labels are kept so the entry stays readable after renames.
+pub struct MergeSide<'a> {
+    pub id: Uuid,
+    pub label: &'a str,
+}
+
+pub(crate) async fn record_merge<'e, E>(
+    executor: E,
+    entity: MergeEntity,
+    source: MergeSide<'_>,
+    target: MergeSide<'_>,
+    reports_moved: u64,
+    merged_by: Option<Uuid>,
+) -> Result<()>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO merge_audit (id, entity, source_id, source_label, target_id, target_label, reports_moved, merged_by, merged_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(entity.as_str())
+    .bind(source.id)
+    .bind(source.label)
+    .bind(target.id)
+    .bind(target.label)
+    .bind(reports_moved as i64)
+    .bind(merged_by)
+    .bind(Utc::now())
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub struct AuditService {
+    pool: PgPool,
+}
+
+impl AuditService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Merges, newest first, optionally limited to one entity ("product", "line" or "client").
+    pub async fn get_merge_history(&self, entity: Option<String>) -> Result<Vec<MergeAudit>> {
+        let entries = sqlx::query_as::<_, MergeAudit>(
+            r#"
+            SELECT m.id, m.entity, m.source_id, m.source_label, m.target_id, m.target_label,
+                   m.reports_moved, m.merged_by, u.username as merged_by_name, m.merged_at
+            FROM merge_audit m
+            LEFT JOIN users u ON m.merged_by = u.id
+            WHERE $1::TEXT IS NULL OR m.entity = $1
+            ORDER BY m.merged_at DESC
+            "#,
+        )
+        .bind(entity)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+}
@@ -1,18 +1,26 @@
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
 use anyhow::{Result, anyhow};
 use bcrypt::{hash, verify, DEFAULT_COST};
 use uuid::Uuid;
-use chrono::Utc;
-use std::str::FromStr;
+use chrono::{DateTime, Duration, Local, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-use super::models::{User, CreateUser, UserRole};
+use super::bulk::{delete_response, deletable_ids, BulkDeleteResponse};
+use super::concurrency::update_miss;
+use super::models::{LoginAttempt, User, CreateUser, UserActivity, UserSummary};
+use super::ldap::{DirectoryUser, LdapConfig};
+use super::roles::{ensure_user_manager_remains, set_user_roles, user_access};
+use super::security::{LockoutPolicy, PasswordPolicy};
+use super::validation::Validator;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PaginationParams {
     pub page: i64,
     pub limit: i64,
     pub search: Option<String>,
+    #[serde(default)]
+    pub include_archived: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -37,14 +45,41 @@ pub struct LoginResponse {
     pub success: bool,
     pub user: Option<UserInfo>,
     pub message: String,
+    pub must_change_password: bool, // Set after an admin reset or once the password expired
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserInfo {
     pub id: uuid::Uuid,
     pub username: String,
-    pub role: String, // Keep for backward compatibility - will be primary role
-    pub roles: Vec<String>, // New multiple roles field
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>, // Granted by any of the roles
+    pub origins: Vec<String>, // Claim origins the user can see and report on
+    pub auth_source: String, // 'local' or 'ldap', directory users change their password in the directory
+    pub updated_at: DateTime<Utc>, // Guard of the profile page's own updates
+}
+
+const AUTH_SOURCE_LDAP: &str = "ldap";
+
+/// Columns of `User`, its role names come from user_roles.
+const USER_COLUMNS: &str = "u.id, u.username, u.password_hash, u.created_at, u.updated_at, u.archived_at, u.last_login_at, u.login_count, u.is_active, \
+    ARRAY(SELECT r.name FROM user_roles ur JOIN roles r ON r.id = ur.role_id WHERE ur.user_id = u.id ORDER BY r.name)::TEXT[] AS roles";
+
+/// Where passwords are checked, picked with `AUTH_PROVIDER` (`local` by default, or `ldap`).
+#[derive(Debug, Clone)]
+pub enum AuthProvider {
+    Local,
+    Ldap(Box<LdapConfig>),
+}
+
+impl AuthProvider {
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("AUTH_PROVIDER").unwrap_or_default().trim().to_lowercase().as_str() {
+            "" | "local" => Ok(AuthProvider::Local),
+            "ldap" => Ok(AuthProvider::Ldap(Box::new(LdapConfig::from_env()?))),
+            other => Err(anyhow!("Unknown AUTH_PROVIDER {}, expected local or ldap", other)),
+        }
+    }
 }
 
 pub struct AuthService {
@@ -57,87 +92,417 @@ impl AuthService {
     }
 
     pub async fn login(&self, request: LoginRequest) -> Result<LoginResponse> {
-        // Find user by username
-        let user_result = sqlx::query_as::<_, User>(
-            "SELECT id, username, password_hash, role, roles, created_at, updated_at FROM users WHERE username = $1"
+        let now = Utc::now();
+        let provider = AuthProvider::from_env()?;
+
+        // Directory usernames are case-insensitive and stored as the directory spells them
+        let account = sqlx::query_as::<_, LoginAccount>(
+            r#"
+            SELECT id, password_hash, auth_source, locked_until, must_change_password, password_changed_at
+            FROM users
+            WHERE (username = $1 OR (auth_source = $2 AND LOWER(username) = LOWER($1))) AND archived_at IS NULL
+            ORDER BY username = $1 DESC
+            LIMIT 1
+            "#
         )
-        .bind(&request.username)
+        .bind(request.username.trim())
+        .bind(AUTH_SOURCE_LDAP)
         .fetch_optional(&self.pool)
         .await?;
 
-        match user_result {
-            Some(user) => {
-                if verify(&request.password, &user.password_hash)? {
-                    // Use roles array if available, otherwise fall back to single role
-                    let roles = user.roles.unwrap_or_else(|| vec![user.role.clone()]);
-                    let primary_role = roles.first().unwrap_or(&user.role).clone();
-                    
-                    Ok(LoginResponse {
-                        success: true,
-                        user: Some(UserInfo {
-                            id: user.id,
-                            username: user.username,
-                            role: primary_role,
-                            roles,
-                        }),
-                        message: "Login successful".to_string(),
-                    })
-                } else {
-                    Ok(LoginResponse {
-                        success: false,
-                        user: None,
-                        message: "Invalid credentials".to_string(),
-                    })
+        if let Some(account) = &account {
+            if let Some(locked_until) = account.locked_until.filter(|until| *until > now) {
+                self.record_login_attempt(&request.username, Some(account.id), LoginOutcome::Locked).await?;
+                return Ok(LoginResponse::failed(&locked_message(locked_until)));
+            }
+        }
+
+        // Local accounts, such as the initial admin, keep their password even with a directory
+        let directory = match (&provider, &account) {
+            (AuthProvider::Ldap(config), None) => Some(config),
+            (AuthProvider::Ldap(config), Some(account)) if account.auth_source == AUTH_SOURCE_LDAP => Some(config),
+            _ => None,
+        };
+
+        let signed_in = match (directory, &account) {
+            (Some(config), _) => match config.authenticate(&request.username, &request.password).await? {
+                Some(directory_user) if directory_user.roles.is_empty() => {
+                    self.record_login_attempt(&request.username, account.as_ref().map(|a| a.id), LoginOutcome::NoRole).await?;
+                    return Ok(LoginResponse::failed("None of your directory groups gives access to this application"));
                 }
+                Some(directory_user) => self.provision_directory_user(directory_user, now).await?
+                    .map(|user| (user, false)),
+                None => None,
+            },
+            (None, Some(account)) if verify(&request.password, &account.password_hash)? => {
+                let password_expired = account.password_changed_at
+                    .is_some_and(|changed_at| PasswordPolicy::from_env().is_expired(changed_at, now));
+
+                let user = self.user_info(account.id).await?;
+                Some((user, account.must_change_password || password_expired))
             }
-            None => Ok(LoginResponse {
-                success: false,
-                user: None,
-                message: "Invalid credentials".to_string(),
-            }),
+            (None, _) => None,
+        };
+
+        let Some((user, must_change_password)) = signed_in else {
+            let Some(account) = account else {
+                self.record_login_attempt(&request.username, None, LoginOutcome::UnknownUser).await?;
+                return Ok(LoginResponse::failed("Invalid credentials"));
+            };
+
+            let lockout = LockoutPolicy::from_env();
+
+            // The counter starts over once the account gets locked
+            let locked_until: Option<DateTime<Utc>> = sqlx::query_scalar(
+                r#"
+                UPDATE users SET
+                    failed_login_attempts = CASE WHEN failed_login_attempts + 1 >= $2 THEN 0 ELSE failed_login_attempts + 1 END,
+                    locked_until = CASE WHEN failed_login_attempts + 1 >= $2 THEN $3 ELSE NULL END
+                WHERE id = $1
+                RETURNING locked_until
+                "#
+            )
+            .bind(account.id)
+            .bind(lockout.max_failed_attempts)
+            .bind(now + Duration::minutes(lockout.lockout_minutes))
+            .fetch_one(&self.pool)
+            .await?;
+
+            self.record_login_attempt(&request.username, Some(account.id), LoginOutcome::InvalidPassword).await?;
+
+            return Ok(match locked_until {
+                Some(locked_until) => LoginResponse::failed(&locked_message(locked_until)),
+                None => LoginResponse::failed("Invalid credentials"),
+            });
+        };
+
+        // Checked once the password is known to be right, so it doesn't reveal which accounts exist
+        let is_active: bool = sqlx::query_scalar("SELECT is_active FROM users WHERE id = $1")
+            .bind(user.id)
+            .fetch_one(&self.pool)
+            .await?;
+        if !is_active {
+            self.record_login_attempt(&request.username, Some(user.id), LoginOutcome::Disabled).await?;
+            return Ok(LoginResponse::failed("This account is disabled"));
+        }
+        if user.roles.is_empty() {
+            self.record_login_attempt(&request.username, Some(user.id), LoginOutcome::NoRole).await?;
+            return Ok(LoginResponse::failed("This account has no role, ask an administrator to give it one"));
         }
+
+        sqlx::query(
+            "UPDATE users SET failed_login_attempts = 0, locked_until = NULL, last_login_at = $2, login_count = login_count + 1 WHERE id = $1"
+        )
+        .bind(user.id)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        self.record_login_attempt(&request.username, Some(user.id), LoginOutcome::Success).await?;
+
+        Ok(LoginResponse {
+            success: true,
+            user: Some(user),
+            message: "Login successful".to_string(),
+            must_change_password,
+        })
+    }
+
+    /// Creates the local account of a directory user at their first login and
+    /// keeps its roles in line with their groups afterwards. Returns `None` when
+    /// the username belongs to an archived or a local account.
+    async fn provision_directory_user(&self, directory_user: DirectoryUser, now: DateTime<Utc>) -> Result<Option<UserInfo>> {
+        let existing: Option<(Uuid, String, Option<DateTime<Utc>>)> = sqlx::query_as(
+            r#"
+            SELECT id, auth_source, archived_at FROM users
+            WHERE username = $1 OR (auth_source = $2 AND LOWER(username) = LOWER($1))
+            ORDER BY username = $1 DESC, archived_at IS NULL DESC
+            LIMIT 1
+            "#
+        )
+        .bind(&directory_user.username)
+        .bind(AUTH_SOURCE_LDAP)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let mut tx = self.pool.begin().await?;
+
+        let user_id = match existing {
+            Some((id, auth_source, None)) if auth_source == AUTH_SOURCE_LDAP => id,
+            Some(_) => return Ok(None),
+            None => {
+                // Nobody knows this password, directory accounts can only sign in through the directory
+                let password_hash = hash(Uuid::new_v4().to_string(), DEFAULT_COST)?;
+                let id = Uuid::new_v4();
+
+                sqlx::query(
+                    r#"
+                    INSERT INTO users (id, username, password_hash, auth_source, password_changed_at, created_at, updated_at)
+                    VALUES ($1, $2, $3, $4, $5, $5, $5)
+                    "#
+                )
+                .bind(id)
+                .bind(&directory_user.username)
+                .bind(&password_hash)
+                .bind(AUTH_SOURCE_LDAP)
+                .bind(now)
+                .execute(&mut *tx)
+                .await?;
+                id
+            }
+        };
+
+        // Group mappings naming a role that no longer exists are ignored
+        sqlx::query("DELETE FROM user_roles WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("INSERT INTO user_roles (user_id, role_id) SELECT $1, id FROM roles WHERE name = ANY($2)")
+            .bind(user_id)
+            .bind(&directory_user.roles)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(self.user_info(user_id).await?))
+    }
+
+    async fn user_info(&self, id: Uuid) -> Result<UserInfo> {
+        let (username, auth_source, updated_at): (String, String, DateTime<Utc>) =
+            sqlx::query_as("SELECT username, auth_source, updated_at FROM users WHERE id = $1")
+                .bind(id)
+                .fetch_one(&self.pool)
+                .await?;
+        let access = user_access(&self.pool, id).await?;
+
+        Ok(UserInfo {
+            id,
+            username,
+            roles: access.roles,
+            permissions: access.permissions,
+            origins: access.origins,
+            auth_source,
+            updated_at,
+        })
+    }
+
+    async fn record_login_attempt(&self, username: &str, user_id: Option<Uuid>, outcome: LoginOutcome) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO login_attempts (id, username, user_id, outcome, attempted_at) VALUES ($1, $2, $3, $4, $5)"
+        )
+        .bind(Uuid::new_v4())
+        .bind(username)
+        .bind(user_id)
+        .bind(outcome.as_str())
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Most recent login attempts first, optionally for a single username.
+    pub async fn get_login_attempts(&self, username: Option<String>, limit: i64) -> Result<Vec<LoginAttempt>> {
+        let attempts = sqlx::query_as::<_, LoginAttempt>(
+            r#"
+            SELECT id, username, user_id, outcome, attempted_at
+            FROM login_attempts
+            WHERE $1::TEXT IS NULL OR username = $1
+            ORDER BY attempted_at DESC
+            LIMIT $2
+            "#
+        )
+        .bind(username)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(attempts)
     }
 
-    pub async fn create_user(&self, create_user: CreateUser) -> Result<User> {
+    /// Reports the user created, edited and moved between statuses, most recent first.
+    pub async fn get_user_activity(&self, user_id: &Uuid, limit: i64) -> Result<Vec<UserActivity>> {
+        let activity = sqlx::query_as::<_, UserActivity>(
+            r#"
+            SELECT kind, report_id, report_number, detail, occurred_at FROM (
+                SELECT 'report_created' AS kind, ncr.id AS report_id, ncr.report_number, NULL::TEXT AS detail, ncr.created_at AS occurred_at
+                FROM non_conformity_reports ncr
+                WHERE ncr.reported_by = $1
+
+                UNION ALL
+
+                -- One entry per save, listing the fields it changed
+                SELECT 'report_edited', h.report_id, ncr.report_number, STRING_AGG(h.field, ', ' ORDER BY h.field), h.changed_at
+                FROM report_field_history h
+                JOIN non_conformity_reports ncr ON ncr.id = h.report_id
+                WHERE h.changed_by = $1
+                GROUP BY h.report_id, ncr.report_number, h.changed_at
+
+                UNION ALL
+
+                -- The initial status of a new report is already covered by report_created
+                SELECT 'status_changed', s.report_id, ncr.report_number, s.old_status || ' → ' || s.new_status, s.changed_at
+                FROM report_status_history s
+                JOIN non_conformity_reports ncr ON ncr.id = s.report_id
+                WHERE s.changed_by = $1 AND s.old_status IS NOT NULL
+            ) activity
+            ORDER BY occurred_at DESC
+            LIMIT $2
+            "#
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(activity)
+    }
+
+    pub async fn unlock_user(&self, user_id: &Uuid) -> Result<()> {
+        let result = sqlx::query("UPDATE users SET failed_login_attempts = 0, locked_until = NULL WHERE id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow!("User not found"));
+        }
+
+        Ok(())
+    }
+
+    /// Checks `new_password` against the password policy and the user's recent
+    /// passwords, then stores it. Every broken rule is reported at once.
+    /// `must_change` makes the user pick a new password at their next login.
+    /// `expected_updated_at` guards resets by an admin. Users changing their own
+    /// password go without, they just proved they know the current one.
+    async fn set_password(
+        &self,
+        user_id: &Uuid,
+        new_password: &str,
+        must_change: bool,
+        expected_updated_at: Option<DateTime<Utc>>,
+    ) -> Result<User> {
+        let policy = PasswordPolicy::from_env();
+        let user = self.get_user_by_id(user_id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+
+        let auth_source: String = sqlx::query_scalar("SELECT auth_source FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await?;
+        if auth_source == AUTH_SOURCE_LDAP {
+            return Err(anyhow!("This account signs in through the directory, its password is changed there"));
+        }
+
+        let mut v = Validator::new();
+        policy.check(&mut v, &user.username, new_password);
+
+        if v.is_valid() && policy.history > 0 {
+            let mut recent: Vec<String> = sqlx::query_scalar(
+                "SELECT password_hash FROM password_history WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2"
+            )
+            .bind(user_id)
+            .bind(policy.history as i64)
+            .fetch_all(&self.pool)
+            .await?;
+            // Accounts created before the history existed only have their current hash
+            recent.push(user.password_hash);
+
+            for previous in &recent {
+                if verify(new_password, previous)? {
+                    v.error("password", format!("Must differ from the last {} passwords", policy.history));
+                    break;
+                }
+            }
+        }
+
+        v.finish()?;
+
+        let password_hash = hash(new_password, DEFAULT_COST)?;
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+
+        let user = sqlx::query_as::<_, User>(&format!(
+            r#"
+            UPDATE users u SET
+                password_hash = $1, must_change_password = $2, password_changed_at = $3,
+                failed_login_attempts = 0, locked_until = NULL, updated_at = $3
+            WHERE u.id = $4 AND ($5::TIMESTAMPTZ IS NULL OR u.updated_at = $5)
+            RETURNING {}
+            "#,
+            USER_COLUMNS
+        ))
+        .bind(&password_hash)
+        .bind(must_change)
+        .bind(now)
+        .bind(user_id)
+        .bind(expected_updated_at)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(user) = user else {
+            return Err(update_miss(&mut *tx, "users", "User", *user_id, expected_updated_at).await?);
+        };
+
+        record_password(&mut tx, user_id, &password_hash, now, policy.history).await?;
+
+        tx.commit().await?;
+
+        Ok(user)
+    }
+
+    pub async fn create_user(&self, create_user: CreateUser) -> Result<UserSummary> {
+        let policy = PasswordPolicy::from_env();
+        let mut v = Validator::new();
+        policy.check(&mut v, &create_user.username, &create_user.password);
+        v.finish()?;
+
         let user_id = Uuid::new_v4();
         let password_hash = hash(&create_user.password, DEFAULT_COST)?;
         let now = Utc::now();
 
-        let roles = create_user.roles.unwrap_or_else(|| vec![create_user.role.clone()]);
+        let mut tx = self.pool.begin().await?;
+
+        // Like an admin reset, the first password is known to whoever created the account
 
         sqlx::query(
             "
-            INSERT INTO users (id, username, password_hash, role, roles, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            INSERT INTO users (id, username, password_hash, must_change_password, password_changed_at, created_at, updated_at)
+            VALUES ($1, $2, $3, TRUE, $4, $4, $4)
             "
         )
         .bind(&user_id)
         .bind(&create_user.username)
         .bind(&password_hash)
-        .bind(&create_user.role)
-        .bind(&roles)
         .bind(now)
-        .bind(now)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        set_user_roles(&mut tx, user_id, &create_user.roles).await?;
+
+        record_password(&mut tx, &user_id, &password_hash, now, policy.history).await?;
+
+        tx.commit().await?;
+
         let user = User {
             id: user_id,
             username: create_user.username,
             password_hash,
-            role: create_user.role,
-            roles: Some(roles),
+            roles: create_user.roles,
             created_at: now,
             updated_at: now,
+            archived_at: None,
+            last_login_at: None,
+            login_count: 0,
+            is_active: true,
         };
 
-        Ok(user)
+        Ok(UserSummary::from(user))
     }
 
-    pub async fn get_user_by_id(&self, user_id: &Uuid) -> Result<Option<User>> {
-        let user = sqlx::query_as::<_, User>(
-            "SELECT id, username, password_hash, role, roles, created_at, updated_at FROM users WHERE id = $1"
-        )
+    async fn get_user_by_id(&self, user_id: &Uuid) -> Result<Option<User>> {
+        let user = sqlx::query_as::<_, User>(&format!("SELECT {} FROM users u WHERE u.id = $1", USER_COLUMNS))
         .bind(user_id)
         .fetch_optional(&self.pool)
         .await?;
@@ -145,30 +510,41 @@ impl AuthService {
         Ok(user)
     }
 
-    pub async fn get_all_users(&self) -> Result<Vec<User>> {
-        let users = sqlx::query_as::<_, User>(
-            "SELECT id, username, password_hash, role, roles, created_at, updated_at FROM users ORDER BY created_at DESC"
-        )
+    pub async fn get_all_users(&self) -> Result<Vec<UserSummary>> {
+        let users = sqlx::query_as::<_, User>(&format!(
+            "SELECT {} FROM users u WHERE u.archived_at IS NULL ORDER BY u.created_at DESC",
+            USER_COLUMNS
+        ))
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(users)
+        Ok(users.into_iter().map(UserSummary::from).collect())
     }
 
-    pub async fn get_paginated_users(&self, params: PaginationParams) -> Result<PaginatedResponse<User>> {
+    pub async fn get_paginated_users(&self, params: PaginationParams) -> Result<PaginatedResponse<UserSummary>> {
         let offset = (params.page - 1) * params.limit;
         
-        let mut query = "SELECT id, username, password_hash, role, roles, created_at, updated_at FROM users".to_string();
-        let mut count_query = "SELECT COUNT(*) as count FROM users".to_string();
+        let mut query = format!("SELECT {} FROM users u", USER_COLUMNS);
+        let mut count_query = "SELECT COUNT(*) as count FROM users u".to_string();
         
+        let mut conditions = Vec::new();
+
+        if !params.include_archived {
+            conditions.push("u.archived_at IS NULL".to_string());
+        }
+
         if let Some(search) = &params.search {
-            let search_condition = format!(" WHERE username ILIKE '%{}%' OR role ILIKE '%{}%'", 
-                search.replace("'", "''"), search.replace("'", "''"));
-            query.push_str(&search_condition);
-            count_query.push_str(&search_condition);
+            conditions.push(format!("(u.username ILIKE '%{}%' OR EXISTS (SELECT 1 FROM user_roles ur JOIN roles r ON r.id = ur.role_id WHERE ur.user_id = u.id AND r.name ILIKE '%{}%'))", 
+                search.replace("'", "''"), search.replace("'", "''")));
+        }
+
+        if !conditions.is_empty() {
+            let where_clause = format!(" WHERE {}", conditions.join(" AND "));
+            query.push_str(&where_clause);
+            count_query.push_str(&where_clause);
         }
         
-        query.push_str(" ORDER BY created_at DESC LIMIT $1 OFFSET $2");
+        query.push_str(" ORDER BY u.created_at DESC LIMIT $1 OFFSET $2");
         
         let users = sqlx::query_as::<_, User>(&query)
             .bind(params.limit)
@@ -183,7 +559,7 @@ impl AuthService {
         let total_pages = (total.0 + params.limit - 1) / params.limit;
         
         Ok(PaginatedResponse {
-            data: users,
+            data: users.into_iter().map(UserSummary::from).collect(),
             total: total.0,
             page: params.page,
             limit: params.limit,
@@ -198,7 +574,7 @@ impl AuthService {
         user_id: &Uuid,
         current_password: &str,
         new_password: &str,
-    ) -> Result<()> {
+    ) -> Result<UserSummary> {
         // Get the user
         let user = self.get_user_by_id(user_id).await?
             .ok_or_else(|| anyhow!("User not found"))?;
@@ -208,88 +584,52 @@ impl AuthService {
             return Err(anyhow!("Current password is incorrect"));
         }
 
-        // Hash new password
-        let new_password_hash = hash(new_password, DEFAULT_COST)?;
-        let now = Utc::now();
-
-        // Update password
-        sqlx::query(
-            "UPDATE users SET password_hash = $1, updated_at = $2 WHERE id = $3"
-        )
-        .bind(&new_password_hash)
-        .bind(now)
-        .bind(user_id)
-        .execute(&self.pool)
-        .await?;
-
-        Ok(())
-    }
-
-    pub async fn update_user_role(
-        &self,
-        user_id: &Uuid,
-        new_role: &str,
-    ) -> Result<()> {
-        // Validate role using FromStr
-        if let Err(_) = UserRole::from_str(new_role) {
-            return Err(anyhow!("Invalid role specified"));
-        }
-
-        let now = Utc::now();
-
-        sqlx::query(
-            "UPDATE users SET role = $1, updated_at = $2 WHERE id = $3"
-        )
-        .bind(new_role)
-        .bind(now)
-        .bind(user_id)
-        .execute(&self.pool)
-        .await?;
-
-        Ok(())
+        self.set_password(user_id, new_password, false, None).await.map(UserSummary::from)
     }
 
+    /// Replaces the user's roles. At least one active user has to keep the
+    /// permission to manage users.
     pub async fn update_user_roles(
         &self,
         user_id: &Uuid,
         new_roles: Vec<String>,
-    ) -> Result<()> {
-        // Validate all roles
-        for role in &new_roles {
-            if let Err(_) = UserRole::from_str(role) {
-                return Err(anyhow!("Invalid role specified: {}", role));
-            }
-        }
+        expected_updated_at: DateTime<Utc>,
+    ) -> Result<UserSummary> {
+        let mut tx = self.pool.begin().await?;
 
-        if new_roles.is_empty() {
-            return Err(anyhow!("At least one role must be specified"));
+        // Locks the user before its roles change
+        let found: Option<Uuid> = sqlx::query_scalar("SELECT id FROM users WHERE id = $1 AND updated_at = $2 FOR UPDATE")
+            .bind(user_id)
+            .bind(expected_updated_at)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        if found.is_none() {
+            return Err(update_miss(&mut *tx, "users", "User", *user_id, Some(expected_updated_at)).await?);
         }
 
-        let now = Utc::now();
-        let primary_role = new_roles.first().unwrap();
+        set_user_roles(&mut tx, *user_id, &new_roles).await?;
+        ensure_user_manager_remains(&mut tx).await?;
 
-        sqlx::query(
-            "UPDATE users SET role = $1, roles = $2, updated_at = $3 WHERE id = $4"
-        )
-        .bind(primary_role)
-        .bind(&new_roles)
-        .bind(now)
-        .bind(user_id)
-        .execute(&self.pool)
-        .await?;
+        let user = sqlx::query_as::<_, User>(&format!("UPDATE users u SET updated_at = $1 WHERE u.id = $2 RETURNING {}", USER_COLUMNS))
+            .bind(Utc::now())
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
 
-        Ok(())
+        tx.commit().await?;
+
+        Ok(UserSummary::from(user))
     }
 
     pub async fn update_username(
         &self,
         user_id: &Uuid,
         new_username: &str,
-    ) -> Result<()> {
+        expected_updated_at: DateTime<Utc>,
+    ) -> Result<UserSummary> {
         // Check if username already exists
-        let existing_user = sqlx::query_as::<_, User>(
-            "SELECT id, username, password_hash, role, roles, created_at, updated_at FROM users WHERE username = $1 AND id != $2"
-        )
+        let existing_user = sqlx::query_as::<_, User>(&format!("SELECT {} FROM users u WHERE u.username = $1 AND u.id != $2", USER_COLUMNS))
         .bind(new_username)
         .bind(user_id)
         .fetch_optional(&self.pool)
@@ -301,44 +641,276 @@ impl AuthService {
 
         let now = Utc::now();
 
-        sqlx::query(
-            "UPDATE users SET username = $1, updated_at = $2 WHERE id = $3"
-        )
+        let user = sqlx::query_as::<_, User>(&format!(
+            "UPDATE users u SET username = $1, updated_at = $2 WHERE u.id = $3 AND u.updated_at = $4 RETURNING {}",
+            USER_COLUMNS
+        ))
         .bind(new_username)
         .bind(now)
         .bind(user_id)
-        .execute(&self.pool)
+        .bind(expected_updated_at)
+        .fetch_optional(&self.pool)
         .await?;
 
-        Ok(())
+        match user {
+            Some(user) => Ok(UserSummary::from(user)),
+            None => Err(update_miss(&self.pool, "users", "User", *user_id, Some(expected_updated_at)).await?),
+        }
     }
 
+    /// Archives a user. Authors of reports are refused, they have to be
+    /// disabled so their name stays on their reports.
     pub async fn delete_user(&self, user_id: &Uuid) -> Result<()> {
-        sqlx::query("DELETE FROM users WHERE id = $1")
+        let report_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM non_conformity_reports WHERE reported_by = $1")
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        if report_count > 0 {
+            return Err(anyhow!(
+                "User authored {} report(s) and cannot be deleted, disable the account instead",
+                report_count
+            ));
+        }
+
+        sqlx::query("UPDATE users SET archived_at = NOW() WHERE id = $1 AND archived_at IS NULL")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Stops a user from logging in. The last active user able to manage
+    /// users cannot be disabled.
+    pub async fn disable_user(&self, user_id: &Uuid) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query("UPDATE users SET is_active = FALSE, updated_at = $1 WHERE id = $2 AND archived_at IS NULL")
+            .bind(Utc::now())
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow!("User not found"));
+        }
+
+        ensure_user_manager_remains(&mut tx).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn enable_user(&self, user_id: &Uuid) -> Result<()> {
+        let result = sqlx::query("UPDATE users SET is_active = TRUE, updated_at = $1 WHERE id = $2 AND archived_at IS NULL")
+            .bind(Utc::now())
             .bind(user_id)
             .execute(&self.pool)
             .await?;
 
+        if result.rows_affected() == 0 {
+            return Err(anyhow!("User not found"));
+        }
+
         Ok(())
     }
 
+    pub async fn restore_user(&self, user_id: &Uuid) -> Result<bool> {
+        let result = sqlx::query("UPDATE users SET archived_at = NULL WHERE id = $1 AND archived_at IS NOT NULL")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Archives several users in one transaction. Users who authored reports
+    /// are left in place unless `force` is set.
+    pub async fn delete_multiple_users(&self, ids: &[Uuid], force: bool) -> Result<BulkDeleteResponse> {
+        let mut tx = self.pool.begin().await?;
+
+        let report_counts: HashMap<Uuid, i64> = sqlx::query_as::<_, (Uuid, i64)>(
+            "SELECT reported_by, COUNT(*) FROM non_conformity_reports WHERE reported_by = ANY($1) GROUP BY reported_by"
+        )
+        .bind(ids)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+
+        let deleted: Vec<Uuid> = sqlx::query_scalar("UPDATE users SET archived_at = NOW() WHERE id = ANY($1) AND archived_at IS NULL RETURNING id")
+            .bind(deletable_ids(ids, &report_counts, force))
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(delete_response(ids, &report_counts, &deleted))
+    }
+
     pub async fn update_user_password(
         &self,
         user_id: &Uuid,
         new_password: &str,
-    ) -> Result<()> {
-        let password_hash = hash(new_password, DEFAULT_COST)?;
-        let now = Utc::now();
+        expected_updated_at: DateTime<Utc>,
+    ) -> Result<UserSummary> {
+        // The admin knows this password, so the user has to replace it
+        self.set_password(user_id, new_password, true, Some(expected_updated_at))
+            .await
+            .map(UserSummary::from)
+    }
+}
 
-        sqlx::query(
-            "UPDATE users SET password_hash = $1, updated_at = $2 WHERE id = $3"
-        )
-        .bind(&password_hash)
-        .bind(now)
+#[derive(Debug, Clone, Copy)]
+enum LoginOutcome {
+    Success,
+    UnknownUser,
+    InvalidPassword,
+    Locked,
+    NoRole,
+    Disabled,
+}
+
+impl LoginOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LoginOutcome::Success => "success",
+            LoginOutcome::UnknownUser => "unknown_user",
+            LoginOutcome::InvalidPassword => "invalid_password",
+            LoginOutcome::Locked => "locked",
+            LoginOutcome::NoRole => "no_role",
+            LoginOutcome::Disabled => "disabled",
+        }
+    }
+}
+
+/// The columns login needs, read in one query.
+#[derive(sqlx::FromRow)]
+struct LoginAccount {
+    id: Uuid,
+    password_hash: String,
+    auth_source: String,
+    locked_until: Option<DateTime<Utc>>,
+    must_change_password: bool,
+    password_changed_at: Option<DateTime<Utc>>,
+}
+
+impl LoginResponse {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            user: None,
+            message: message.to_string(),
+            must_change_password: false,
+        }
+    }
+}
+
+fn locked_message(locked_until: DateTime<Utc>) -> String {
+    format!(
+        "Too many failed attempts, the account is locked until {}",
+        locked_until.with_timezone(&Local).format("%H:%M")
+    )
+}
+
+/// Remembers a newly set password hash and forgets those beyond the policy's history.
+async fn record_password(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: &Uuid,
+    password_hash: &str,
+    at: DateTime<Utc>,
+    history: usize,
+) -> Result<()> {
+    sqlx::query("INSERT INTO password_history (id, user_id, password_hash, created_at) VALUES ($1, $2, $3, $4)")
+        .bind(Uuid::new_v4())
         .bind(user_id)
-        .execute(&self.pool)
+        .bind(password_hash)
+        .bind(at)
+        .execute(&mut **tx)
         .await?;
 
-        Ok(())
+    sqlx::query(
+        r#"
+        DELETE FROM password_history
+        WHERE user_id = $1
+        AND id NOT IN (
+            SELECT id FROM password_history WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2
+        )
+        "#
+    )
+    .bind(user_id)
+    .bind(history.max(1) as i64)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::UserStatus;
+
+    fn user(password_hash: &str) -> User {
+        let now = Utc::now();
+        User {
+            id: Uuid::new_v4(),
+            username: "operator".to_string(),
+            password_hash: password_hash.to_string(),
+            roles: vec!["site01".to_string()],
+            created_at: now,
+            updated_at: now,
+            archived_at: None,
+            last_login_at: Some(now),
+            login_count: 3,
+            is_active: false,
+        }
+    }
+
+    fn fields<T: Serialize>(response: &T) -> Vec<String> {
+        let json = serde_json::to_value(response).unwrap();
+        json.as_object().unwrap().keys().cloned().collect()
+    }
+
+    #[test]
+    fn user_summary_status_follows_archive_and_active_flags() {
+        let mut user = user("hash");
+        assert_eq!(UserSummary::from(user.clone()).status, UserStatus::Disabled);
+
+        user.is_active = true;
+        assert_eq!(UserSummary::from(user.clone()).status, UserStatus::Active);
+
+        user.archived_at = Some(Utc::now());
+        assert_eq!(UserSummary::from(user).status, UserStatus::Archived);
+    }
+
+    /// Every user command answers with `UserSummary::from` since `User` is not
+    /// serializable, and login answers with `UserInfo`.
+    #[test]
+    fn user_responses_do_not_serialize_password_hashes() {
+        let password_hash = hash("Secret123", 4).unwrap();
+        let summary = UserSummary::from(user(&password_hash));
+
+        assert!(!serde_json::to_string(&summary).unwrap().contains(&password_hash));
+        assert_eq!(
+            fields(&summary),
+            ["created_at", "id", "last_login_at", "login_count", "roles", "status", "updated_at", "username"]
+        );
+
+        let info = UserInfo {
+            id: summary.id,
+            username: summary.username,
+            roles: summary.roles,
+            permissions: Vec::new(),
+            origins: Vec::new(),
+            auth_source: "local".to_string(),
+            updated_at: summary.updated_at,
+        };
+        assert_eq!(
+            fields(&info),
+            ["auth_source", "id", "origins", "permissions", "roles", "updated_at", "username"]
+        );
     }
 }
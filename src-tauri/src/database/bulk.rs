@@ -0,0 +1,233 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// What to do when a bulk row matches an existing record (or an earlier row
+/// of the same request) on its natural key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    #[default]
+    Fail,
+    Skip,
+    Update,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkCreateResponse<T> {
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub data: Vec<T>, // Inserted and updated rows as stored
+}
+
+pub struct BulkPlan<T> {
+    pub inserts: Vec<T>,
+    pub updates: Vec<(Uuid, T)>,
+    pub skipped: usize,
+}
+
+/// Splits bulk rows into inserts and updates against `existing`, which maps
+/// normalized keys to record IDs.
+pub fn plan_bulk<T>(
+    rows: Vec<T>,
+    existing: &HashMap<String, Uuid>,
+    key: impl Fn(&T) -> String,
+    on_conflict: OnConflict,
+    entity: &str,
+) -> Result<BulkPlan<T>> {
+    let mut inserts: Vec<T> = Vec::new();
+    let mut updates: Vec<(Uuid, T)> = Vec::new();
+    let mut insert_index: HashMap<String, usize> = HashMap::new();
+    let mut update_index: HashMap<Uuid, usize> = HashMap::new();
+    let mut skipped = 0;
+
+    for row in rows {
+        let row_key = key(&row);
+
+        if row_key.is_empty() {
+            return Err(anyhow!("{} key cannot be empty", entity));
+        }
+
+        let existing_id = existing.get(&row_key).copied();
+        let pending_insert = insert_index.get(&row_key).copied();
+
+        if existing_id.is_none() && pending_insert.is_none() {
+            insert_index.insert(row_key, inserts.len());
+            inserts.push(row);
+            continue;
+        }
+
+        match on_conflict {
+            OnConflict::Fail => return Err(anyhow!("{} {} already exists", entity, row_key)),
+            OnConflict::Skip => skipped += 1,
+            // Later rows win over earlier ones with the same key
+            OnConflict::Update => {
+                if let Some(index) = pending_insert {
+                    inserts[index] = row;
+                } else if let Some(id) = existing_id {
+                    match update_index.get(&id) {
+                        Some(index) => updates[*index].1 = row,
+                        None => {
+                            update_index.insert(id, updates.len());
+                            updates.push((id, row));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(BulkPlan {
+        inserts,
+        updates,
+        skipped,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeleteOutcome {
+    Deleted,
+    NotFound,
+    Referenced, // Still used by reports and not forced
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkDeleteResult {
+    pub id: Uuid,
+    pub outcome: DeleteOutcome,
+    pub report_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkDeleteResponse {
+    pub deleted: usize,
+    pub results: Vec<BulkDeleteResult>,
+}
+
+/// IDs that may be deleted, given how many reports still reference each one.
+pub fn deletable_ids(ids: &[Uuid], report_counts: &HashMap<Uuid, i64>, force: bool) -> Vec<Uuid> {
+    ids.iter()
+        .filter(|id| force || report_counts.get(id).copied().unwrap_or(0) == 0)
+        .copied()
+        .collect()
+}
+
+/// Builds the per-ID outcome of a bulk delete, in the order the IDs were requested.
+pub fn delete_response(ids: &[Uuid], report_counts: &HashMap<Uuid, i64>, deleted: &[Uuid]) -> BulkDeleteResponse {
+    let mut seen = HashSet::new();
+    let results: Vec<BulkDeleteResult> = ids
+        .iter()
+        .filter(|id| seen.insert(**id))
+        .map(|id| {
+            let report_count = report_counts.get(id).copied().unwrap_or(0);
+            let outcome = if deleted.contains(id) {
+                DeleteOutcome::Deleted
+            } else if report_count > 0 {
+                DeleteOutcome::Referenced
+            } else {
+                DeleteOutcome::NotFound
+            };
+
+            BulkDeleteResult {
+                id: *id,
+                outcome,
+                report_count,
+            }
+        })
+        .collect();
+
+    BulkDeleteResponse {
+        deleted: deleted.len(),
+        results,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(rows: &[&str], existing: &HashMap<String, Uuid>, on_conflict: OnConflict) -> Result<BulkPlan<String>> {
+        let rows = rows.iter().map(|row| row.to_string()).collect();
+        plan_bulk(rows, existing, |row: &String| row.trim().to_lowercase(), on_conflict, "Client")
+    }
+
+    #[test]
+    fn plan_bulk_inserts_new_keys() {
+        let plan = plan(&["Alpha", "Beta"], &HashMap::new(), OnConflict::Fail).unwrap();
+
+        assert_eq!(plan.inserts, ["Alpha", "Beta"]);
+        assert!(plan.updates.is_empty());
+        assert_eq!(plan.skipped, 0);
+    }
+
+    #[test]
+    fn plan_bulk_refuses_conflicts_and_empty_keys() {
+        let existing = HashMap::from([("alpha".to_string(), Uuid::new_v4())]);
+
+        let error = plan(&["ALPHA"], &existing, OnConflict::Fail).err().unwrap();
+        assert_eq!(error.to_string(), "Client alpha already exists");
+        assert!(plan(&["Beta", "beta "], &HashMap::new(), OnConflict::Fail).is_err());
+        assert_eq!(plan(&["  "], &HashMap::new(), OnConflict::Skip).err().unwrap().to_string(), "Client key cannot be empty");
+    }
+
+    #[test]
+    fn plan_bulk_skips_existing_and_repeated_keys() {
+        let existing = HashMap::from([("alpha".to_string(), Uuid::new_v4())]);
+        let plan = plan(&["Alpha", "Beta", "BETA"], &existing, OnConflict::Skip).unwrap();
+
+        assert_eq!(plan.inserts, ["Beta"]);
+        assert!(plan.updates.is_empty());
+        assert_eq!(plan.skipped, 2);
+    }
+
+    #[test]
+    fn plan_bulk_updates_with_the_last_row_of_each_key() {
+        let alpha = Uuid::new_v4();
+        let existing = HashMap::from([("alpha".to_string(), alpha)]);
+        let plan = plan(&["alpha", "Beta", "ALPHA", "beta"], &existing, OnConflict::Update).unwrap();
+
+        assert_eq!(plan.inserts, ["beta"]);
+        assert_eq!(plan.updates, [(alpha, "ALPHA".to_string())]);
+        assert_eq!(plan.skipped, 0);
+    }
+
+    #[test]
+    fn deletable_ids_keep_referenced_records_unless_forced() {
+        let (free, used) = (Uuid::new_v4(), Uuid::new_v4());
+        let report_counts = HashMap::from([(used, 3)]);
+
+        assert_eq!(deletable_ids(&[free, used], &report_counts, false), [free]);
+        assert_eq!(deletable_ids(&[free, used], &report_counts, true), [free, used]);
+    }
+
+    #[test]
+    fn delete_response_reports_each_requested_id_once() {
+        let (deleted, used, missing) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let report_counts = HashMap::from([(used, 2)]);
+        let response = delete_response(&[used, deleted, missing, deleted], &report_counts, &[deleted]);
+
+        assert_eq!(response.deleted, 1);
+        let results: Vec<(Uuid, DeleteOutcome, i64)> =
+            response.results.iter().map(|r| (r.id, r.outcome, r.report_count)).collect();
+        assert_eq!(
+            results,
+            [
+                (used, DeleteOutcome::Referenced, 2),
+                (deleted, DeleteOutcome::Deleted, 0),
+                (missing, DeleteOutcome::NotFound, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn delete_response_counts_forced_deletes_of_referenced_records() {
+        let used = Uuid::new_v4();
+        let response = delete_response(&[used], &HashMap::from([(used, 5)]), &[used]);
+
+        assert_eq!(response.deleted, 1);
+        assert_eq!((response.results[0].outcome, response.results[0].report_count), (DeleteOutcome::Deleted, 5));
+    }
+}
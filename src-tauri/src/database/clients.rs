@@ -1,15 +1,24 @@
 use anyhow::Result;
 use sqlx::PgPool;
 use uuid::Uuid;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
+use crate::database::audit::{record_merge, MergeEntity, MergeResponse, MergeSide};
+use crate::database::bulk::{delete_response, deletable_ids, plan_bulk, BulkCreateResponse, BulkDeleteResponse, OnConflict};
+use crate::database::concurrency::update_miss;
+use crate::database::import::{clean_text, normalize_key, normalized_column};
 use crate::database::models::{Client, CreateClient};
+use crate::database::reports::{record_field_changes, FieldChange};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PaginationParams {
     pub page: i64,
     pub limit: i64,
     pub search: Option<String>,
+    #[serde(default)]
+    pub include_archived: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -31,12 +40,36 @@ pub struct CreateClientRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BulkCreateClientsRequest {
     pub clients: Vec<CreateClientRequest>,
+    #[serde(default)]
+    pub on_conflict: OnConflict, // Matched on client name
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateClientRequest {
     pub id: Uuid,
     pub name: String,
+    /// `updated_at` of the copy being edited, the update is refused if the client changed since
+    pub expected_updated_at: DateTime<Utc>,
+}
+
+/// Client names are unique among non-archived clients, ignoring case and spacing.
+async fn ensure_unique_name<'e, E>(executor: E, name: &str, exclude_id: Option<Uuid>) -> Result<()>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let existing: Option<String> = sqlx::query_scalar(&format!(
+        "SELECT name FROM clients WHERE archived_at IS NULL AND ($2::UUID IS NULL OR id <> $2) AND {} = $1 LIMIT 1",
+        normalized_column("name")
+    ))
+    .bind(normalize_key(name))
+    .bind(exclude_id)
+    .fetch_optional(executor)
+    .await?;
+
+    match existing {
+        Some(existing) => Err(anyhow::anyhow!("Client {} already exists", existing)),
+        None => Ok(()),
+    }
 }
 
 pub struct ClientsService {
@@ -50,7 +83,7 @@ impl ClientsService {
 
     pub async fn get_all(&self) -> Result<Vec<Client>> {
         let clients = sqlx::query_as::<_, Client>(
-            "SELECT id, name, created_at, updated_at FROM clients ORDER BY name"
+            "SELECT id, name, created_at, updated_at, archived_at FROM clients WHERE archived_at IS NULL ORDER BY name"
         )
         .fetch_all(&self.pool)
         .await?;
@@ -61,13 +94,23 @@ impl ClientsService {
     pub async fn get_paginated(&self, params: PaginationParams) -> Result<PaginatedResponse<Client>> {
         let offset = (params.page - 1) * params.limit;
         
-        let mut query = "SELECT id, name, created_at, updated_at FROM clients".to_string();
+        let mut query = "SELECT id, name, created_at, updated_at, archived_at FROM clients".to_string();
         let mut count_query = "SELECT COUNT(*) as count FROM clients".to_string();
         
+        let mut conditions = Vec::new();
+
+        if !params.include_archived {
+            conditions.push("archived_at IS NULL".to_string());
+        }
+
         if let Some(search) = &params.search {
-            let search_condition = format!(" WHERE name ILIKE '%{}%'", search.replace("'", "''"));
-            query.push_str(&search_condition);
-            count_query.push_str(&search_condition);
+            conditions.push(format!("(name ILIKE '%{}%')", search.replace("'", "''")));
+        }
+
+        if !conditions.is_empty() {
+            let where_clause = format!(" WHERE {}", conditions.join(" AND "));
+            query.push_str(&where_clause);
+            count_query.push_str(&where_clause);
         }
         
         query.push_str(" ORDER BY name LIMIT $1 OFFSET $2");
@@ -97,6 +140,8 @@ impl ClientsService {
 
 
     pub async fn create(&self, client: CreateClient) -> Result<Client> {
+        ensure_unique_name(&self.pool, &client.name, None).await?;
+
         let client = sqlx::query_as::<_, Client>(
             r#"
             INSERT INTO clients (id, name, created_at, updated_at)
@@ -112,25 +157,104 @@ impl ClientsService {
         Ok(client)
     }
 
-    pub async fn update(&self, id: Uuid, name: String) -> Result<Client> {
+    pub async fn update(&self, id: Uuid, name: String, expected_updated_at: DateTime<Utc>) -> Result<Client> {
+        ensure_unique_name(&self.pool, &name, Some(id)).await?;
+
         let client = sqlx::query_as::<_, Client>(
             r#"
             UPDATE clients 
             SET name = $1, updated_at = NOW()
-            WHERE id = $2
-            RETURNING id, name, created_at, updated_at
+            WHERE id = $2 AND updated_at = $3
+            RETURNING id, name, created_at, updated_at, archived_at
             "#
         )
         .bind(name)
         .bind(id)
-        .fetch_one(&self.pool)
+        .bind(expected_updated_at)
+        .fetch_optional(&self.pool)
         .await?;
         
-        Ok(client)
+        match client {
+            Some(client) => Ok(client),
+            None => Err(update_miss(&self.pool, "clients", "Client", id, Some(expected_updated_at)).await?),
+        }
+    }
+
+    /// Points every report naming `source_id` at `target_id` instead, then
+    /// archives the source. Reports only carry the client name, so it is
+    /// rewritten wherever it matches.
+    pub async fn merge(&self, source_id: Uuid, target_id: Uuid, merged_by: Option<Uuid>) -> Result<MergeResponse<Client>> {
+        if source_id == target_id {
+            return Err(anyhow::anyhow!("A client cannot be merged into itself"));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let source_name: String = sqlx::query_scalar("SELECT name FROM clients WHERE id = $1 FOR UPDATE")
+            .bind(source_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Client to merge not found"))?;
+
+        let target = sqlx::query_as::<_, Client>(
+            "SELECT id, name, created_at, updated_at, archived_at FROM clients WHERE id = $1 FOR UPDATE"
+        )
+        .bind(target_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Target client not found"))?;
+
+        if target.archived_at.is_some() {
+            return Err(anyhow::anyhow!("Target client is archived"));
+        }
+
+        // Joined with itself to return the spelling each report had before
+        let moved: Vec<(Uuid, Option<String>)> = sqlx::query_as(&format!(
+            r#"
+            UPDATE non_conformity_reports ncr
+            SET claim_origin_detail = $2, updated_at = NOW(), version = ncr.version + 1
+            FROM non_conformity_reports old
+            WHERE old.id = ncr.id AND {} = $1
+            RETURNING ncr.id, old.claim_origin_detail
+            "#,
+            normalized_column("old.claim_origin_detail")
+        ))
+        .bind(normalize_key(&source_name))
+        .bind(&target.name)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let changes: Vec<(Uuid, FieldChange)> = moved
+            .into_iter()
+            .map(|(id, old_value)| (id, FieldChange { field: "claim_origin_detail", old_value, new_value: Some(target.name.clone()) }))
+            .collect();
+        record_field_changes(&mut *tx, &changes, merged_by, Utc::now()).await?;
+        let reports_moved = changes.len() as u64;
+
+        sqlx::query("UPDATE clients SET archived_at = NOW(), updated_at = NOW() WHERE id = $1 AND archived_at IS NULL")
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await?;
+
+        record_merge(
+            &mut *tx,
+            MergeEntity::Client,
+            MergeSide { id: source_id, label: &source_name },
+            MergeSide { id: target.id, label: &target.name },
+            reports_moved,
+            merged_by,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        println!("[CLIENTS_SERVICE] Merged client {} into {}, {} reports moved", source_id, target_id, reports_moved);
+
+        Ok(MergeResponse { record: target, reports_moved })
     }
 
     pub async fn delete(&self, id: Uuid) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM clients WHERE id = $1")
+        let result = sqlx::query("UPDATE clients SET archived_at = NOW() WHERE id = $1 AND archived_at IS NULL")
             .bind(id)
             .execute(&self.pool)
             .await?;
@@ -138,15 +262,120 @@ impl ClientsService {
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn bulk_create_clients(&self, request: BulkCreateClientsRequest) -> Result<Vec<Client>> {
-        let mut created_clients = Vec::new();
+    /// Refused while an active client has the same name.
+    pub async fn restore(&self, id: Uuid) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
 
-        for c in request.clients {
-            let created = self.create(CreateClient { name: c.name }).await?;
-            created_clients.push(created);
-        }
+        let name: Option<String> = sqlx::query_scalar(
+            "SELECT name FROM clients WHERE id = $1 AND archived_at IS NOT NULL FOR UPDATE"
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(name) = name else {
+            return Ok(false);
+        };
+
+        ensure_unique_name(&mut *tx, &name, Some(id)).await?;
+
+        sqlx::query("UPDATE clients SET archived_at = NULL WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    pub async fn bulk_create_clients(&self, request: BulkCreateClientsRequest) -> Result<BulkCreateResponse<Client>> {
+        let mut tx = self.pool.begin().await?;
+
+        // Keeps a concurrent import from adding the same names between the lookup and the insert
+        sqlx::query("LOCK TABLE clients IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&mut *tx)
+            .await?;
+
+        let existing: HashMap<String, Uuid> = sqlx::query_as::<_, (Uuid, String)>("SELECT id, name FROM clients WHERE archived_at IS NULL")
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|(id, name)| (normalize_key(&name), id))
+            .collect();
+
+        let plan = plan_bulk(request.clients, &existing, |c| normalize_key(&c.name), request.on_conflict, "Client")?;
+
+        let mut data = sqlx::query_as::<_, Client>(
+            r#"
+            INSERT INTO clients (id, name, created_at, updated_at)
+            SELECT t.id, t.name, NOW(), NOW()
+            FROM UNNEST($1::UUID[], $2::TEXT[]) AS t(id, name)
+            RETURNING id, name, created_at, updated_at
+            "#
+        )
+        .bind(plan.inserts.iter().map(|_| Uuid::new_v4()).collect::<Vec<_>>())
+        .bind(plan.inserts.iter().map(|c| clean_text(&c.name)).collect::<Vec<_>>())
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let updated = sqlx::query_as::<_, Client>(
+            r#"
+            UPDATE clients c SET name = t.name, updated_at = NOW()
+            FROM UNNEST($1::UUID[], $2::TEXT[]) AS t(id, name)
+            WHERE c.id = t.id
+            RETURNING c.id, c.name, c.created_at, c.updated_at
+            "#
+        )
+        .bind(plan.updates.iter().map(|(id, _)| *id).collect::<Vec<_>>())
+        .bind(plan.updates.iter().map(|(_, c)| clean_text(&c.name)).collect::<Vec<_>>())
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let inserted = data.len();
+        let updated_count = updated.len();
+        data.extend(updated);
+
+        Ok(BulkCreateResponse {
+            inserted,
+            updated: updated_count,
+            skipped: plan.skipped,
+            data,
+        })
+    }
+
+    /// Archives several clients in one transaction. Clients still referenced by
+    /// reports are left in place unless `force` is set. Reports only carry the
+    /// client name, so references are matched on it.
+    pub async fn delete_multiple(&self, ids: &[Uuid], force: bool) -> Result<BulkDeleteResponse> {
+        let mut tx = self.pool.begin().await?;
+
+        let report_counts: HashMap<Uuid, i64> = sqlx::query_as::<_, (Uuid, i64)>(
+            r#"
+            SELECT c.id, COUNT(ncr.id)
+            FROM clients c
+            JOIN non_conformity_reports ncr
+              ON LOWER(REGEXP_REPLACE(TRIM(ncr.claim_origin_detail), '\s+', ' ', 'g'))
+               = LOWER(REGEXP_REPLACE(TRIM(c.name), '\s+', ' ', 'g'))
+            WHERE c.id = ANY($1)
+            GROUP BY c.id
+            "#
+        )
+        .bind(ids)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+
+        let deleted: Vec<Uuid> = sqlx::query_scalar("UPDATE clients SET archived_at = NOW() WHERE id = ANY($1) AND archived_at IS NULL RETURNING id")
+            .bind(deletable_ids(ids, &report_counts, force))
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
 
-        Ok(created_clients)
+        Ok(delete_response(ids, &report_counts, &deleted))
     }
 
 }
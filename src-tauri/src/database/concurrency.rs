@@ -0,0 +1,51 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+use uuid::Uuid;
+
+/// Explains why a guarded `UPDATE ... WHERE id = $1 AND updated_at = $2` matched
+/// no row: either the row is gone or someone else saved it since it was loaded.
+///
+/// `table` is always one of our own table names, never user input.
+pub(crate) async fn update_miss<'e, E>(
+    executor: E,
+    table: &str,
+    entity: &str,
+    id: Uuid,
+    expected_updated_at: Option<DateTime<Utc>>,
+) -> Result<anyhow::Error>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let current: Option<DateTime<Utc>> = sqlx::query_scalar(&format!("SELECT updated_at FROM {} WHERE id = $1", table))
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(match (current, expected_updated_at) {
+        (None, _) => anyhow::anyhow!("{} not found", entity),
+        (Some(current), Some(expected)) if current != expected => anyhow::anyhow!(
+            "{} was modified by someone else at {}, reload it and try again",
+            entity,
+            current.format("%Y-%m-%d %H:%M:%S")
+        ),
+        (Some(_), _) => anyhow::anyhow!("{} could not be updated", entity),
+    })
+}
+
+/// A save based on a stale copy of a record. Carries the current server copy
+/// so the caller can show what changed and let the user merge.
+#[derive(Debug, Serialize)]
+pub struct Conflict<T> {
+    pub message: String,
+    pub current: T,
+}
+
+impl<T> fmt::Display for Conflict<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for Conflict<T> {}
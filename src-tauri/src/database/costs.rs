@@ -0,0 +1,309 @@
+use sqlx::PgPool;
+use anyhow::{Result, anyhow};
+use chrono::{Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::{RoundingStrategy, ToPrimitive};
+
+use crate::database::reports::{build_filter_clause, bind_filters, ReportFilters};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostReportParams {
+    pub year: Option<i32>,
+    pub filters: ReportFilters,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostBreakdownEntry {
+    pub key: String,
+    pub label: String,
+    pub amount: Decimal,
+    pub formatted: String,
+    pub report_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonthlyCost {
+    pub month: String, // YYYY-MM
+    pub amount: Decimal,
+    pub formatted: String,
+    pub report_count: i64,
+    pub delta: Decimal, // Change from the previous month
+    pub delta_percentage: Option<f64>, // None when the previous month was zero
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostOfNonQuality {
+    pub year: i32,
+    pub currency: String,
+    pub year_to_date: Decimal,
+    pub year_to_date_formatted: String,
+    pub previous_year_to_date: Decimal, // Same calendar period one year earlier
+    pub previous_year_to_date_formatted: String,
+    pub monthly: Vec<MonthlyCost>,
+    pub by_line: Vec<CostBreakdownEntry>,
+    pub by_product: Vec<CostBreakdownEntry>,
+    pub by_family: Vec<CostBreakdownEntry>,
+    pub by_claim_origin: Vec<CostBreakdownEntry>,
+    pub by_team: Vec<CostBreakdownEntry>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CostDimension {
+    Line,
+    Product,
+    Family,
+    ClaimOrigin,
+    Team,
+}
+
+impl CostDimension {
+    fn group_columns(&self) -> (&'static str, &'static str) {
+        match self {
+            CostDimension::Line => ("ncr.line_id::TEXT", "COALESCE(pl.name, 'Ligne inconnue')"),
+            CostDimension::Product => ("ncr.product_id::TEXT", "COALESCE(p.designation, 'Produit inconnu')"),
+            CostDimension::Family => ("COALESCE(p.family_id::TEXT, '')", "COALESCE(fam.name, 'Sans famille')"),
+            CostDimension::ClaimOrigin => ("ncr.claim_origin", "ncr.claim_origin"),
+            CostDimension::Team => ("ncr.team", "CONCAT('Équipe ', ncr.team)"),
+        }
+    }
+}
+
+/// Formats an amount the way it is written on French-language documents,
+/// e.g. `1 234 567,89 DZD`.
+pub fn format_dzd(amount: Decimal) -> String {
+    let rounded = amount.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero);
+    let text = format!("{:.2}", rounded.abs());
+    let (integer, fraction) = text.split_once('.').unwrap_or((text.as_str(), "00"));
+
+    let mut grouped = String::new();
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(' ');
+        }
+        grouped.push(digit);
+    }
+
+    let sign = if rounded < Decimal::ZERO { "-" } else { "" };
+    format!("{}{},{} DZD", sign, grouped, fraction)
+}
+
+pub struct CostsService {
+    pool: PgPool,
+}
+
+impl CostsService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Cost of non-quality built from report valuations for one calendar
+    /// year, honouring the usual report filters.
+    pub async fn get_cost_of_non_quality(&self, params: CostReportParams) -> Result<CostOfNonQuality> {
+        let today = Utc::now().date_naive();
+        let year = params.year.unwrap_or(today.year());
+
+        let year_start = NaiveDate::from_ymd_opt(year, 1, 1).ok_or_else(|| anyhow!("Invalid year: {}", year))?;
+        let next_year_start = NaiveDate::from_ymd_opt(year + 1, 1, 1).ok_or_else(|| anyhow!("Invalid year: {}", year))?;
+
+        // Year-to-date stops today for the current year, otherwise covers the full year
+        let period_end = if year == today.year() { today.succ_opt().unwrap_or(next_year_start) } else { next_year_start };
+        let previous_start = year_start.with_year(year - 1).ok_or_else(|| anyhow!("Invalid year: {}", year))?;
+        let previous_end = shift_back_one_year(period_end);
+
+        let year_to_date = self.total(&params.filters, year_start, period_end).await?;
+        let previous_year_to_date = self.total(&params.filters, previous_start, previous_end).await?;
+
+        // December of the previous year is fetched so January also gets a delta
+        let december = NaiveDate::from_ymd_opt(year - 1, 12, 1).ok_or_else(|| anyhow!("Invalid year: {}", year))?;
+        let monthly = self.monthly(&params.filters, december, period_end).await?;
+
+        Ok(CostOfNonQuality {
+            year,
+            currency: "DZD".to_string(),
+            year_to_date,
+            year_to_date_formatted: format_dzd(year_to_date),
+            previous_year_to_date,
+            previous_year_to_date_formatted: format_dzd(previous_year_to_date),
+            monthly,
+            by_line: self.breakdown(&params.filters, CostDimension::Line, year_start, period_end).await?,
+            by_product: self.breakdown(&params.filters, CostDimension::Product, year_start, period_end).await?,
+            by_family: self.breakdown(&params.filters, CostDimension::Family, year_start, period_end).await?,
+            by_claim_origin: self.breakdown(&params.filters, CostDimension::ClaimOrigin, year_start, period_end).await?,
+            by_team: self.breakdown(&params.filters, CostDimension::Team, year_start, period_end).await?,
+        })
+    }
+
+    async fn total(&self, filters: &ReportFilters, from: NaiveDate, to: NaiveDate) -> Result<Decimal> {
+        let clause = build_filter_clause(filters)?;
+        let from_index = clause.next_param();
+
+        let query = format!(
+            "SELECT COALESCE(SUM(ncr.valuation), 0) FROM non_conformity_reports ncr WHERE 1=1{} AND ncr.report_date >= ${} AND ncr.report_date < ${}",
+            clause.conditions,
+            from_index,
+            from_index + 1
+        );
+
+        let (total,) = bind_filters(sqlx::query_as::<_, (Decimal,)>(&query), &clause.values)
+            .bind(from)
+            .bind(to)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(total)
+    }
+
+    /// Monthly totals from `from` (first of a month) up to `to`, the first
+    /// month only serving as the reference for the second one's delta.
+    async fn monthly(&self, filters: &ReportFilters, from: NaiveDate, to: NaiveDate) -> Result<Vec<MonthlyCost>> {
+        let clause = build_filter_clause(filters)?;
+        let from_index = clause.next_param();
+
+        let query = format!(
+            r#"
+            SELECT to_char(date_trunc('month', ncr.report_date), 'YYYY-MM') AS month,
+                   COALESCE(SUM(ncr.valuation), 0),
+                   COUNT(*)
+            FROM non_conformity_reports ncr
+            WHERE 1=1{} AND ncr.report_date >= ${} AND ncr.report_date < ${}
+            GROUP BY 1
+            ORDER BY 1
+            "#,
+            clause.conditions,
+            from_index,
+            from_index + 1
+        );
+
+        let rows = bind_filters(sqlx::query_as::<_, (String, Decimal, i64)>(&query), &clause.values)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut monthly = Vec::new();
+        let mut previous: Option<Decimal> = None;
+        let mut month = from;
+
+        // Walk every month so months without reports still show up as zero
+        while month < to {
+            let key = month.format("%Y-%m").to_string();
+            let (amount, report_count) = rows
+                .iter()
+                .find(|(m, _, _)| *m == key)
+                .map(|(_, amount, count)| (*amount, *count))
+                .unwrap_or((Decimal::ZERO, 0));
+
+            if let Some(previous_amount) = previous {
+                let delta = amount - previous_amount;
+                let delta_percentage = if previous_amount.is_zero() {
+                    None
+                } else {
+                    (delta * Decimal::ONE_HUNDRED / previous_amount).round_dp(2).to_f64()
+                };
+
+                monthly.push(MonthlyCost {
+                    month: key,
+                    amount,
+                    formatted: format_dzd(amount),
+                    report_count,
+                    delta,
+                    delta_percentage,
+                });
+            }
+
+            previous = Some(amount);
+            month = next_month(month);
+        }
+
+        Ok(monthly)
+    }
+
+    async fn breakdown(
+        &self,
+        filters: &ReportFilters,
+        dimension: CostDimension,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<CostBreakdownEntry>> {
+        let clause = build_filter_clause(filters)?;
+        let from_index = clause.next_param();
+        let (key_expr, label_expr) = dimension.group_columns();
+
+        let query = format!(
+            r#"
+            SELECT {} AS key, {} AS label, COALESCE(SUM(ncr.valuation), 0) AS amount, COUNT(*)
+            FROM non_conformity_reports ncr
+            LEFT JOIN products p ON ncr.product_id = p.id
+            LEFT JOIN production_lines pl ON ncr.line_id = pl.id
+            LEFT JOIN product_families fam ON p.family_id = fam.id
+            WHERE 1=1{} AND ncr.report_date >= ${} AND ncr.report_date < ${}
+            GROUP BY 1, 2
+            ORDER BY amount DESC, label ASC
+            "#,
+            key_expr,
+            label_expr,
+            clause.conditions,
+            from_index,
+            from_index + 1
+        );
+
+        let rows = bind_filters(sqlx::query_as::<_, (String, String, Decimal, i64)>(&query), &clause.values)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(key, label, amount, report_count)| CostBreakdownEntry {
+                key,
+                label,
+                amount,
+                formatted: format_dzd(amount),
+                report_count,
+            })
+            .collect())
+    }
+}
+
+fn next_month(date: NaiveDate) -> NaiveDate {
+    if date.month() == 12 {
+        NaiveDate::from_ymd_opt(date.year() + 1, 1, 1).unwrap_or(date)
+    } else {
+        NaiveDate::from_ymd_opt(date.year(), date.month() + 1, 1).unwrap_or(date)
+    }
+}
+
+fn shift_back_one_year(date: NaiveDate) -> NaiveDate {
+    // 29 February has no counterpart, fall back to the 28th
+    date.with_year(date.year() - 1)
+        .or_else(|| NaiveDate::from_ymd_opt(date.year() - 1, date.month(), 28))
+        .unwrap_or(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dzd(amount: &str) -> String {
+        format_dzd(Decimal::from_str(amount).unwrap())
+    }
+
+    #[test]
+    fn format_dzd_groups_thousands_with_spaces() {
+        assert_eq!(dzd("0"), "0,00 DZD");
+        assert_eq!(dzd("999"), "999,00 DZD");
+        assert_eq!(dzd("1000"), "1 000,00 DZD");
+        assert_eq!(dzd("1234567.89"), "1 234 567,89 DZD");
+    }
+
+    #[test]
+    fn format_dzd_rounds_to_centimes_and_keeps_the_sign() {
+        assert_eq!(dzd("12.345"), "12,35 DZD");
+        assert_eq!(dzd("0.5"), "0,50 DZD");
+        assert_eq!(dzd("-1500.1"), "-1 500,10 DZD");
+        assert_eq!(dzd("-0.001"), "0,00 DZD");
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::NcDes;
+use super::import::clean_text;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateDescriptionTypeRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateDescriptionTypeRequest {
+    pub id: i32,
+    pub name: String,
+}
+
+pub struct DescriptionTypesService {
+    pool: PgPool,
+}
+
+impl DescriptionTypesService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_all(&self) -> Result<Vec<NcDes>> {
+        let types = sqlx::query_as::<_, NcDes>(
+            r#"
+            SELECT d.id, d.name, d.created_at,
+                   COUNT(ncr.id) AS report_count
+            FROM nc_des d
+            LEFT JOIN non_conformity_reports ncr ON ncr.description_type = d.name
+            GROUP BY d.id
+            ORDER BY d.name
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(types)
+    }
+
+    pub async fn create(&self, request: CreateDescriptionTypeRequest) -> Result<NcDes> {
+        let name = Self::validate(&request.name)?;
+        self.ensure_unique(&name, None).await?;
+
+        let description_type = sqlx::query_as::<_, NcDes>(
+            r#"
+            INSERT INTO nc_des (name)
+            VALUES ($1)
+            RETURNING id, name, created_at
+            "#
+        )
+        .bind(&name)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(description_type)
+    }
+
+    /// Renaming cascades to the reports using the type through the nc_des foreign key.
+    pub async fn update(&self, request: UpdateDescriptionTypeRequest) -> Result<NcDes> {
+        let name = Self::validate(&request.name)?;
+        self.ensure_unique(&name, Some(request.id)).await?;
+
+        let description_type = sqlx::query_as::<_, NcDes>(
+            r#"
+            UPDATE nc_des
+            SET name = $1
+            WHERE id = $2
+            RETURNING id, name, created_at
+            "#
+        )
+        .bind(&name)
+        .bind(request.id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Description type not found"))?;
+
+        Ok(description_type)
+    }
+
+    /// Description types still referenced by reports cannot be deleted.
+    pub async fn delete(&self, id: i32) -> Result<bool> {
+        let report_count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM non_conformity_reports ncr
+            JOIN nc_des d ON d.name = ncr.description_type
+            WHERE d.id = $1
+            "#
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        if report_count > 0 {
+            return Err(anyhow::anyhow!("Description type is used by {} report(s) and cannot be deleted", report_count));
+        }
+
+        let result = sqlx::query("DELETE FROM nc_des WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn validate(name: &str) -> Result<String> {
+        let name = clean_text(name);
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Description type name cannot be empty"));
+        }
+        if name.chars().count() > 50 {
+            return Err(anyhow::anyhow!("Description type name cannot exceed 50 characters"));
+        }
+
+        Ok(name)
+    }
+
+    async fn ensure_unique(&self, name: &str, exclude_id: Option<i32>) -> Result<()> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM nc_des WHERE LOWER(name) = LOWER($1) AND ($2::INTEGER IS NULL OR id <> $2))"
+        )
+        .bind(name)
+        .bind(exclude_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        if exists {
+            return Err(anyhow::anyhow!("Description type {} already exists", name));
+        }
+
+        Ok(())
+    }
+}
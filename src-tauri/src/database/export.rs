@@ -0,0 +1,181 @@
+use sqlx::{FromRow, PgPool};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use rust_xlsxwriter::{Color, Format, FormatAlign, FormatBorder, Workbook};
+
+use crate::database::reports::{build_filter_clause, bind_filters, ReportFilters};
+
+const HEADERS: [(&str, f64); 19] = [
+    ("N° rapport", 20.0),
+    ("Date du rapport", 14.0),
+    ("Date de production", 14.0),
+    ("Heure", 8.0),
+    ("Équipe", 8.0),
+    ("Ligne", 16.0),
+    ("Produit", 30.0),
+    ("Code produit", 12.0),
+    ("Format", 10.0),
+    ("Type de NC", 12.0),
+    ("Description", 50.0),
+    ("Quantité", 10.0),
+    ("Origine de la réclamation", 20.0),
+    ("Client / détail", 30.0),
+    ("Valorisation (DZD)", 16.0),
+    ("Performance", 20.0),
+    ("Statut", 10.0),
+    ("Déclaré par", 14.0),
+    ("Créé le", 17.0),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub path: String,
+    pub row_count: u32,
+}
+
+#[derive(Debug, FromRow)]
+struct ExportRow {
+    report_number: String,
+    report_date: DateTime<Utc>,
+    production_date: NaiveDate,
+    time: NaiveTime,
+    team: String,
+    line_name: Option<String>,
+    product_name: Option<String>,
+    product_code: Option<String>,
+    format_display: Option<String>,
+    description_type: String,
+    description_details: String,
+    quantity: i32,
+    claim_origin: String,
+    claim_origin_detail: Option<String>,
+    valuation: Decimal,
+    performance: Option<String>,
+    status: String,
+    reported_by_name: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+/// French label for a report status, as shown to users and on documents.
+pub fn status_label(status: &str) -> &str {
+    match status {
+        "open" => "Ouvert",
+        "in_progress" => "En cours",
+        "resolved" => "Résolu",
+        "closed" => "Clôturé",
+        other => other,
+    }
+}
+
+pub struct ExportService {
+    pool: PgPool,
+}
+
+impl ExportService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Writes every report matching `filters` (not just one page) to an
+    /// Excel workbook at `path`.
+    pub async fn export_reports_xlsx(&self, filters: ReportFilters, path: &str) -> Result<ExportSummary> {
+        let clause = build_filter_clause(&filters)?;
+
+        // picture_data is left out on purpose, images don't belong in the sheet
+        let query = format!(
+            r#"
+            SELECT ncr.report_number, ncr.report_date, ncr.production_date, ncr.time, ncr.team,
+                   pl.name as line_name,
+                   p.designation as product_name,
+                   p.code as product_code,
+                   CASE
+                       WHEN f.format_index IS NOT NULL THEN CONCAT(f.format_index, ' ', f.format_unit)
+                       ELSE NULL
+                   END as format_display,
+                   ncr.description_type, ncr.description_details, ncr.quantity,
+                   ncr.claim_origin, ncr.claim_origin_detail, ncr.valuation, ncr.performance,
+                   ncr.status,
+                   u.username as reported_by_name,
+                   ncr.created_at
+            FROM non_conformity_reports ncr
+            LEFT JOIN products p ON ncr.product_id = p.id
+            LEFT JOIN production_lines pl ON ncr.line_id = pl.id
+            LEFT JOIN formats f ON ncr.format_id = f.id
+            LEFT JOIN users u ON ncr.reported_by = u.id
+            WHERE 1=1{}
+            ORDER BY ncr.report_date DESC, ncr.report_number DESC
+            "#,
+            clause.conditions
+        );
+
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name("Non-conformités")?;
+
+        let header_format = Format::new()
+            .set_bold()
+            .set_font_color(Color::White)
+            .set_background_color(Color::RGB(0x1F4E78))
+            .set_align(FormatAlign::Center)
+            .set_align(FormatAlign::VerticalCenter)
+            .set_text_wrap()
+            .set_border(FormatBorder::Thin);
+        let text_format = Format::new().set_border(FormatBorder::Thin);
+        let wrap_format = Format::new().set_text_wrap().set_border(FormatBorder::Thin);
+        let date_format = Format::new().set_num_format("dd/mm/yyyy").set_border(FormatBorder::Thin);
+        let datetime_format = Format::new().set_num_format("dd/mm/yyyy hh:mm").set_border(FormatBorder::Thin);
+        let time_format = Format::new().set_num_format("hh:mm").set_border(FormatBorder::Thin);
+        let integer_format = Format::new().set_num_format("#,##0").set_border(FormatBorder::Thin);
+        let amount_format = Format::new().set_num_format("#,##0.00").set_border(FormatBorder::Thin);
+
+        for (col, (title, width)) in HEADERS.iter().enumerate() {
+            worksheet.write_string_with_format(0, col as u16, *title, &header_format)?;
+            worksheet.set_column_width(col as u16, *width)?;
+        }
+        worksheet.set_row_height(0, 30)?;
+        worksheet.set_freeze_panes(1, 0)?;
+
+        let mut rows = bind_filters(sqlx::query_as::<_, ExportRow>(&query), &clause.values).fetch(&self.pool);
+        let mut row_count: u32 = 0;
+
+        while let Some(report) = rows.try_next().await? {
+            let row = row_count + 1;
+
+            worksheet.write_string_with_format(row, 0, &report.report_number, &text_format)?;
+            worksheet.write_with_format(row, 1, &report.report_date.date_naive(), &date_format)?;
+            worksheet.write_with_format(row, 2, &report.production_date, &date_format)?;
+            worksheet.write_with_format(row, 3, &report.time, &time_format)?;
+            worksheet.write_string_with_format(row, 4, &report.team, &text_format)?;
+            worksheet.write_string_with_format(row, 5, report.line_name.as_deref().unwrap_or(""), &text_format)?;
+            worksheet.write_string_with_format(row, 6, report.product_name.as_deref().unwrap_or(""), &text_format)?;
+            worksheet.write_string_with_format(row, 7, report.product_code.as_deref().unwrap_or(""), &text_format)?;
+            worksheet.write_string_with_format(row, 8, report.format_display.as_deref().unwrap_or(""), &text_format)?;
+            worksheet.write_string_with_format(row, 9, &report.description_type, &text_format)?;
+            worksheet.write_string_with_format(row, 10, &report.description_details, &wrap_format)?;
+            worksheet.write_number_with_format(row, 11, report.quantity, &integer_format)?;
+            worksheet.write_string_with_format(row, 12, &report.claim_origin, &text_format)?;
+            worksheet.write_string_with_format(row, 13, report.claim_origin_detail.as_deref().unwrap_or(""), &text_format)?;
+            worksheet.write_number_with_format(row, 14, report.valuation.to_f64().unwrap_or_default(), &amount_format)?;
+            worksheet.write_string_with_format(row, 15, report.performance.as_deref().unwrap_or(""), &wrap_format)?;
+            worksheet.write_string_with_format(row, 16, status_label(&report.status), &text_format)?;
+            worksheet.write_string_with_format(row, 17, report.reported_by_name.as_deref().unwrap_or(""), &text_format)?;
+            worksheet.write_with_format(row, 18, &report.created_at.naive_utc(), &datetime_format)?;
+
+            row_count += 1;
+        }
+
+        worksheet.autofilter(0, 0, row_count, HEADERS.len() as u16 - 1)?;
+        workbook.save(path)?;
+
+        println!("[EXPORT_SERVICE] Exported {} reports to {}", row_count, path);
+
+        Ok(ExportSummary {
+            path: path.to_string(),
+            row_count,
+        })
+    }
+}
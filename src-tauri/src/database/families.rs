@@ -0,0 +1,133 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::database::concurrency::update_miss;
+use crate::database::import::{normalize_key, normalized_column};
+use crate::database::models::ProductFamily;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateFamilyRequest {
+    pub name: String,
+    pub brand: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateFamilyRequest {
+    pub id: Uuid,
+    pub name: String,
+    pub brand: Option<String>,
+    /// `updated_at` of the copy being edited, the update is refused if the family changed since
+    pub expected_updated_at: DateTime<Utc>,
+}
+
+/// Family names are unique ignoring case and spacing. Families are deleted
+/// rather than archived, so there is no restore to check.
+async fn ensure_unique_name<'e, E>(executor: E, name: &str, exclude_id: Option<Uuid>) -> Result<()>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let existing: Option<String> = sqlx::query_scalar(&format!(
+        "SELECT name FROM product_families WHERE ($2::UUID IS NULL OR id <> $2) AND {} = $1 LIMIT 1",
+        normalized_column("name")
+    ))
+    .bind(normalize_key(name))
+    .bind(exclude_id)
+    .fetch_optional(executor)
+    .await?;
+
+    match existing {
+        Some(existing) => Err(anyhow::anyhow!("Product family {} already exists", existing)),
+        None => Ok(()),
+    }
+}
+
+pub struct FamiliesService {
+    pool: PgPool,
+}
+
+impl FamiliesService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_all(&self) -> Result<Vec<ProductFamily>> {
+        let families = sqlx::query_as::<_, ProductFamily>(
+            r#"
+            SELECT pf.id, pf.name, pf.brand, pf.created_at, pf.updated_at,
+                   COUNT(p.id) FILTER (WHERE p.archived_at IS NULL) AS product_count
+            FROM product_families pf
+            LEFT JOIN products p ON p.family_id = pf.id
+            GROUP BY pf.id
+            ORDER BY pf.name
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(families)
+    }
+
+    pub async fn create(&self, request: CreateFamilyRequest) -> Result<ProductFamily> {
+        let name = request.name.trim();
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Family name cannot be empty"));
+        }
+        ensure_unique_name(&self.pool, name, None).await?;
+
+        let family = sqlx::query_as::<_, ProductFamily>(
+            r#"
+            INSERT INTO product_families (id, name, brand, created_at, updated_at)
+            VALUES ($1, $2, $3, NOW(), NOW())
+            RETURNING id, name, brand, created_at, updated_at
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(name)
+        .bind(request.brand.as_deref().map(str::trim).filter(|b| !b.is_empty()))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(family)
+    }
+
+    pub async fn update(&self, request: UpdateFamilyRequest) -> Result<ProductFamily> {
+        let name = request.name.trim();
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Family name cannot be empty"));
+        }
+        ensure_unique_name(&self.pool, name, Some(request.id)).await?;
+
+        let family = sqlx::query_as::<_, ProductFamily>(
+            r#"
+            UPDATE product_families
+            SET name = $1, brand = $2, updated_at = NOW()
+            WHERE id = $3 AND updated_at = $4
+            RETURNING id, name, brand, created_at, updated_at
+            "#
+        )
+        .bind(name)
+        .bind(request.brand.as_deref().map(str::trim).filter(|b| !b.is_empty()))
+        .bind(request.id)
+        .bind(request.expected_updated_at)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match family {
+            Some(family) => Ok(family),
+            None => Err(update_miss(&self.pool, "product_families", "Product family", request.id, Some(request.expected_updated_at)).await?),
+        }
+    }
+
+    /// Products of a deleted family are kept, they just lose their family.
+    pub async fn delete(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM product_families WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,162 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::Format;
+
+/// Formats are sized in millilitres only, so a size has a single spelling and
+/// `format_index` alone keeps them unique (1.5 L is stored as 1500 ML).
+const FORMAT_UNIT: &str = "ML";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateFormatRequest {
+    pub format_index: i32,
+    pub format_unit: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateFormatRequest {
+    pub id: i32,
+    pub format_index: i32,
+    pub format_unit: String,
+}
+
+pub struct FormatsService {
+    pool: PgPool,
+}
+
+impl FormatsService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_all(&self) -> Result<Vec<Format>> {
+        let formats = sqlx::query_as::<_, Format>(
+            r#"
+            SELECT f.id, f.format_index, f.format_unit, f.created_at,
+                   COUNT(ncr.id) AS report_count
+            FROM formats f
+            LEFT JOIN non_conformity_reports ncr ON ncr.format_id = f.id
+            GROUP BY f.id
+            ORDER BY f.format_index
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(formats)
+    }
+
+    pub async fn create(&self, request: CreateFormatRequest) -> Result<Format> {
+        let unit = Self::validate(request.format_index, &request.format_unit)?;
+        self.ensure_unique(request.format_index, None).await?;
+
+        let format = sqlx::query_as::<_, Format>(
+            r#"
+            INSERT INTO formats (format_index, format_unit)
+            VALUES ($1, $2)
+            RETURNING id, format_index, format_unit, created_at
+            "#
+        )
+        .bind(request.format_index)
+        .bind(unit)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(format)
+    }
+
+    pub async fn update(&self, request: UpdateFormatRequest) -> Result<Format> {
+        let unit = Self::validate(request.format_index, &request.format_unit)?;
+        self.ensure_unique(request.format_index, Some(request.id)).await?;
+
+        let format = sqlx::query_as::<_, Format>(
+            r#"
+            UPDATE formats
+            SET format_index = $1, format_unit = $2
+            WHERE id = $3
+            RETURNING id, format_index, format_unit, created_at
+            "#
+        )
+        .bind(request.format_index)
+        .bind(unit)
+        .bind(request.id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Format not found"))?;
+
+        Ok(format)
+    }
+
+    /// Formats still referenced by reports cannot be deleted, product format
+    /// restrictions on the deleted format are dropped with it.
+    pub async fn delete(&self, id: i32) -> Result<bool> {
+        let report_count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM non_conformity_reports WHERE format_id = $1"
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        if report_count > 0 {
+            return Err(anyhow::anyhow!("Format is used by {} report(s) and cannot be deleted", report_count));
+        }
+
+        let result = sqlx::query("DELETE FROM formats WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn validate(format_index: i32, format_unit: &str) -> Result<&'static str> {
+        if format_index <= 0 {
+            return Err(anyhow::anyhow!("Format size must be greater than zero"));
+        }
+
+        if !format_unit.trim().eq_ignore_ascii_case(FORMAT_UNIT) {
+            return Err(anyhow::anyhow!("Format sizes are given in {}, e.g. 1500 {} for 1.5 L", FORMAT_UNIT, FORMAT_UNIT));
+        }
+
+        Ok(FORMAT_UNIT)
+    }
+
+    async fn ensure_unique(&self, format_index: i32, exclude_id: Option<i32>) -> Result<()> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM formats WHERE format_index = $1 AND ($2::INTEGER IS NULL OR id <> $2))"
+        )
+        .bind(format_index)
+        .bind(exclude_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        if exists {
+            return Err(anyhow::anyhow!("Format {} {} already exists", format_index, FORMAT_UNIT));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_keeps_sizes_in_millilitres() {
+        assert_eq!(FormatsService::validate(1500, " ml ").unwrap(), "ML");
+        assert_eq!(
+            FormatsService::validate(1, "L").unwrap_err().to_string(),
+            "Format sizes are given in ML, e.g. 1500 ML for 1.5 L"
+        );
+        assert!(FormatsService::validate(33, "CL").is_err());
+        assert!(FormatsService::validate(250, "").is_err());
+    }
+
+    #[test]
+    fn validate_refuses_empty_sizes() {
+        assert!(FormatsService::validate(0, "ML").is_err());
+        assert!(FormatsService::validate(-250, "ML").is_err());
+    }
+}
@@ -0,0 +1,544 @@
+use sqlx::PgPool;
+use anyhow::{Result, anyhow};
+use calamine::{open_workbook_auto, Reader};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::database::lines::record_line_activation;
+
+const MAX_NAME_LENGTH: usize = 255;
+const MAX_CODE_LENGTH: usize = 100;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportEntity {
+    Products,
+    Lines,
+    Clients,
+}
+
+impl FromStr for ImportEntity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "products" => Ok(ImportEntity::Products),
+            "lines" => Ok(ImportEntity::Lines),
+            "clients" => Ok(ImportEntity::Clients),
+            _ => Err(format!("Invalid import entity: {}", s)),
+        }
+    }
+}
+
+impl ImportEntity {
+    /// Maps a normalized header to the column it feeds, accepting the
+    /// French labels found in ERP exports.
+    fn column(&self, header: &str) -> Option<&'static str> {
+        match (self, header) {
+            (ImportEntity::Products, "designation" | "libelle" | "produit" | "article" | "nom" | "name") => Some("designation"),
+            (ImportEntity::Products, "code" | "code produit" | "code article" | "reference" | "ref" | "sku") => Some("code"),
+            (ImportEntity::Lines, "name" | "nom" | "ligne" | "nom ligne" | "line") => Some("name"),
+            (ImportEntity::Lines, "description") => Some("description"),
+            (ImportEntity::Lines, "is active" | "active" | "actif") => Some("is_active"),
+            (ImportEntity::Clients, "name" | "nom" | "client" | "raison sociale") => Some("name"),
+            _ => None,
+        }
+    }
+
+    fn required_columns(&self) -> &'static [&'static str] {
+        match self {
+            ImportEntity::Products => &["designation", "code"],
+            ImportEntity::Lines => &["name"],
+            ImportEntity::Clients => &["name"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    Create,
+    Update,
+    Unchanged,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRowResult {
+    pub row_number: usize, // Row in the source file, the header being row 1
+    pub action: ImportAction,
+    pub key: String,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub entity: ImportEntity,
+    pub dry_run: bool,
+    pub applied: bool,
+    pub total_rows: usize,
+    pub creates: usize,
+    pub updates: usize,
+    pub unchanged: usize,
+    pub errors: usize,
+    pub rows: Vec<ImportRowResult>,
+}
+
+struct SourceRow {
+    row_number: usize,
+    values: HashMap<&'static str, String>,
+}
+
+impl SourceRow {
+    fn get(&self, column: &str) -> String {
+        self.values.get(column).map(|v| clean_text(v)).unwrap_or_default()
+    }
+}
+
+enum PlannedChange {
+    Product { id: Option<Uuid>, designation: String, code: String },
+    Line { id: Option<Uuid>, name: String, description: Option<String>, is_active: bool },
+    Client { name: String },
+}
+
+#[derive(sqlx::FromRow)]
+struct ExistingLine {
+    id: Uuid,
+    name: String,
+    description: Option<String>,
+    is_active: bool,
+}
+
+pub struct ImportService {
+    pool: PgPool,
+}
+
+impl ImportService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Validates every row of a CSV or Excel file against the file itself and
+    /// the database. When `dry_run` is off and no row failed, the creates and
+    /// updates are then applied in a single transaction.
+    pub async fn import_file(&self, entity: ImportEntity, path: &str, dry_run: bool) -> Result<ImportReport> {
+        let rows = read_source(entity, path)?;
+
+        let planned = match entity {
+            ImportEntity::Products => self.plan_products(&rows).await?,
+            ImportEntity::Lines => self.plan_lines(&rows).await?,
+            ImportEntity::Clients => self.plan_clients(&rows).await?,
+        };
+
+        let count = |action: ImportAction| planned.iter().filter(|(row, _)| row.action == action).count();
+        let errors = count(ImportAction::Error);
+
+        let mut report = ImportReport {
+            entity,
+            dry_run,
+            applied: false,
+            total_rows: planned.len(),
+            creates: count(ImportAction::Create),
+            updates: count(ImportAction::Update),
+            unchanged: count(ImportAction::Unchanged),
+            errors,
+            rows: Vec::new(),
+        };
+
+        if !dry_run && errors == 0 {
+            self.apply(planned.iter().filter_map(|(_, change)| change.as_ref())).await?;
+            report.applied = true;
+        }
+
+        report.rows = planned.into_iter().map(|(row, _)| row).collect();
+
+        println!(
+            "[IMPORT_SERVICE] {:?} import from {}: {} rows, {} creates, {} updates, {} errors, applied={}",
+            entity, path, report.total_rows, report.creates, report.updates, report.errors, report.applied
+        );
+
+        Ok(report)
+    }
+
+    async fn plan_products(&self, rows: &[SourceRow]) -> Result<Vec<(ImportRowResult, Option<PlannedChange>)>> {
+        let existing = sqlx::query_as::<_, (Uuid, String, String)>(
+            "SELECT id, designation, code FROM products WHERE archived_at IS NULL"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let by_designation: HashMap<String, Uuid> = existing
+            .iter()
+            .map(|(id, designation, _)| (normalize_key(designation), *id))
+            .collect();
+
+        let mut by_code: HashMap<String, Vec<(Uuid, String, String)>> = HashMap::new();
+        for product in existing {
+            by_code.entry(normalize_key(&product.2)).or_default().push(product);
+        }
+
+        let mut seen = HashSet::new();
+        let mut seen_designations = HashSet::new();
+        let mut planned = Vec::new();
+
+        for row in rows {
+            let designation = row.get("designation");
+            let code = row.get("code");
+            let mut errors = Vec::new();
+
+            if designation.is_empty() {
+                errors.push("Designation is required".to_string());
+            } else if designation.chars().count() > MAX_NAME_LENGTH {
+                errors.push(format!("Designation is longer than {} characters", MAX_NAME_LENGTH));
+            }
+
+            if code.is_empty() {
+                errors.push("Code is required".to_string());
+            } else if !is_valid_code(&code) {
+                errors.push(format!(
+                    "Invalid code format (letters, digits, '-', '_', '.' and '/' only, at most {} characters)",
+                    MAX_CODE_LENGTH
+                ));
+            } else if !seen.insert(normalize_key(&code)) {
+                errors.push(format!("Code {} appears more than once in the file", code));
+            }
+
+            let matches = by_code.get(&normalize_key(&code)).map(Vec::as_slice).unwrap_or(&[]);
+            if matches.len() > 1 {
+                errors.push(format!("Several existing products share code {}", code));
+            }
+
+            if !designation.is_empty() {
+                let designation_key = normalize_key(&designation);
+                let owner = by_designation.get(&designation_key);
+                if owner.is_some_and(|id| matches.first().map(|m| m.0) != Some(*id)) {
+                    errors.push(format!("Designation {} is already used by another product", designation));
+                } else if !seen_designations.insert(designation_key) {
+                    errors.push(format!("Designation {} appears more than once in the file", designation));
+                }
+            }
+
+            let result = if !errors.is_empty() {
+                (ImportAction::Error, None)
+            } else if let Some((id, current_designation, current_code)) = matches.first() {
+                if *current_designation == designation && *current_code == code {
+                    (ImportAction::Unchanged, None)
+                } else {
+                    (ImportAction::Update, Some(PlannedChange::Product { id: Some(*id), designation, code: code.clone() }))
+                }
+            } else {
+                (ImportAction::Create, Some(PlannedChange::Product { id: None, designation, code: code.clone() }))
+            };
+
+            planned.push((
+                ImportRowResult { row_number: row.row_number, action: result.0, key: code, errors },
+                result.1,
+            ));
+        }
+
+        Ok(planned)
+    }
+
+    async fn plan_lines(&self, rows: &[SourceRow]) -> Result<Vec<(ImportRowResult, Option<PlannedChange>)>> {
+        let existing = sqlx::query_as::<_, ExistingLine>(
+            "SELECT id, name, description, is_active FROM production_lines WHERE archived_at IS NULL"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut by_name: HashMap<String, Vec<ExistingLine>> = HashMap::new();
+        for line in existing {
+            by_name.entry(normalize_key(&line.name)).or_default().push(line);
+        }
+
+        let mut seen = HashSet::new();
+        let mut planned = Vec::new();
+
+        for row in rows {
+            let name = row.get("name");
+            let description = Some(row.get("description")).filter(|d| !d.is_empty());
+            let mut errors = Vec::new();
+
+            if name.is_empty() {
+                errors.push("Name is required".to_string());
+            } else if name.chars().count() > MAX_NAME_LENGTH {
+                errors.push(format!("Name is longer than {} characters", MAX_NAME_LENGTH));
+            } else if !seen.insert(normalize_key(&name)) {
+                errors.push(format!("Line {} appears more than once in the file", name));
+            }
+
+            let is_active = match parse_bool(&row.get("is_active")) {
+                Ok(value) => value,
+                Err(e) => {
+                    errors.push(e);
+                    true
+                }
+            };
+
+            let matches = by_name.get(&normalize_key(&name)).map(Vec::as_slice).unwrap_or(&[]);
+            if matches.len() > 1 {
+                errors.push(format!("Several existing lines are named {}", name));
+            }
+
+            let result = if !errors.is_empty() {
+                (ImportAction::Error, None)
+            } else if let Some(current) = matches.first() {
+                // An empty description column keeps the stored one
+                let description = description.or_else(|| current.description.clone());
+                if current.name == name && current.description == description && current.is_active == is_active {
+                    (ImportAction::Unchanged, None)
+                } else {
+                    (ImportAction::Update, Some(PlannedChange::Line { id: Some(current.id), name: name.clone(), description, is_active }))
+                }
+            } else {
+                (ImportAction::Create, Some(PlannedChange::Line { id: None, name: name.clone(), description, is_active }))
+            };
+
+            planned.push((
+                ImportRowResult { row_number: row.row_number, action: result.0, key: name, errors },
+                result.1,
+            ));
+        }
+
+        Ok(planned)
+    }
+
+    async fn plan_clients(&self, rows: &[SourceRow]) -> Result<Vec<(ImportRowResult, Option<PlannedChange>)>> {
+        let existing: Vec<String> = sqlx::query_scalar("SELECT name FROM clients WHERE archived_at IS NULL")
+            .fetch_all(&self.pool)
+            .await?;
+        let existing: HashSet<String> = existing.iter().map(|name| normalize_key(name)).collect();
+
+        let mut seen = HashSet::new();
+        let mut planned = Vec::new();
+
+        for row in rows {
+            let name = row.get("name");
+            let mut errors = Vec::new();
+
+            if name.is_empty() {
+                errors.push("Name is required".to_string());
+            } else if name.chars().count() > MAX_NAME_LENGTH {
+                errors.push(format!("Name is longer than {} characters", MAX_NAME_LENGTH));
+            } else if !seen.insert(normalize_key(&name)) {
+                errors.push(format!("Client {} appears more than once in the file", name));
+            }
+
+            let result = if !errors.is_empty() {
+                (ImportAction::Error, None)
+            } else if existing.contains(&normalize_key(&name)) {
+                (ImportAction::Unchanged, None)
+            } else {
+                (ImportAction::Create, Some(PlannedChange::Client { name: name.clone() }))
+            };
+
+            planned.push((
+                ImportRowResult { row_number: row.row_number, action: result.0, key: name, errors },
+                result.1,
+            ));
+        }
+
+        Ok(planned)
+    }
+
+    async fn apply<'a>(&self, changes: impl Iterator<Item = &'a PlannedChange>) -> Result<()> {
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+        let mut line_ids = Vec::new();
+
+        for change in changes {
+            match change {
+                PlannedChange::Product { id: Some(id), designation, code } => {
+                    sqlx::query("UPDATE products SET designation = $2, code = $3, updated_at = $4 WHERE id = $1")
+                        .bind(id)
+                        .bind(designation)
+                        .bind(code)
+                        .bind(now)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                PlannedChange::Product { id: None, designation, code } => {
+                    sqlx::query(
+                        "INSERT INTO products (id, designation, code, created_at, updated_at) VALUES ($1, $2, $3, $4, $5)"
+                    )
+                    .bind(Uuid::new_v4())
+                    .bind(designation)
+                    .bind(code)
+                    .bind(now)
+                    .bind(now)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                PlannedChange::Line { id: Some(id), name, description, is_active } => {
+                    line_ids.push(*id);
+                    sqlx::query(
+                        "UPDATE production_lines SET name = $2, description = $3, is_active = $4, updated_at = $5 WHERE id = $1"
+                    )
+                    .bind(id)
+                    .bind(name)
+                    .bind(description)
+                    .bind(is_active)
+                    .bind(now)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                PlannedChange::Line { id: None, name, description, is_active } => {
+                    let id = Uuid::new_v4();
+                    line_ids.push(id);
+                    sqlx::query(
+                        "INSERT INTO production_lines (id, name, description, is_active, created_at, updated_at)
+                         VALUES ($1, $2, $3, $4, $5, $6)"
+                    )
+                    .bind(id)
+                    .bind(name)
+                    .bind(description)
+                    .bind(is_active)
+                    .bind(now)
+                    .bind(now)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                PlannedChange::Client { name } => {
+                    sqlx::query("INSERT INTO clients (id, name, created_at, updated_at) VALUES ($1, $2, $3, $4)")
+                        .bind(Uuid::new_v4())
+                        .bind(name)
+                        .bind(now)
+                        .bind(now)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+            }
+        }
+
+        record_line_activation(&mut *tx, &line_ids, now).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// Trims a value and collapses inner runs of whitespace, the form names and
+/// codes are stored in.
+pub fn clean_text(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Case- and whitespace-insensitive comparison key.
+pub fn normalize_key(value: &str) -> String {
+    clean_text(value).to_lowercase()
+}
+
+/// SQL counterpart of `normalize_key` applied to `column`.
+pub fn normalized_column(column: &str) -> String {
+    format!("LOWER(TRIM(REGEXP_REPLACE({}, '[[:space:]]+', ' ', 'g')))", column)
+}
+
+fn normalize_header(header: &str) -> String {
+    let header: String = header
+        .trim_start_matches('\u{feff}')
+        .chars()
+        .map(|c| match c {
+            'é' | 'è' | 'ê' | 'É' | 'È' => 'e',
+            '_' | '-' => ' ',
+            c => c,
+        })
+        .collect();
+    normalize_key(&header)
+}
+
+fn is_valid_code(code: &str) -> bool {
+    code.len() <= MAX_CODE_LENGTH
+        && code.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.trim().to_lowercase().as_str() {
+        "" | "1" | "true" | "oui" | "yes" | "actif" | "active" | "vrai" => Ok(true),
+        "0" | "false" | "non" | "no" | "inactif" | "inactive" | "faux" => Ok(false),
+        other => Err(format!("Invalid active flag: {}", other)),
+    }
+}
+
+/// Reads the first sheet of an Excel workbook or a CSV file and keys every
+/// data row by canonical column name.
+fn read_source(entity: ImportEntity, path: &str) -> Result<Vec<SourceRow>> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    let records = match extension.as_str() {
+        "csv" | "txt" => read_csv(path)?,
+        "xlsx" | "xlsm" | "xls" | "ods" => read_workbook(path)?,
+        _ => return Err(anyhow!("Unsupported file type: {} (expected .csv or .xlsx)", path)),
+    };
+
+    let mut records = records.into_iter().enumerate();
+    let (_, header) = records.next().ok_or_else(|| anyhow!("The file is empty"))?;
+    let columns: Vec<Option<&'static str>> = header.iter().map(|h| entity.column(&normalize_header(h))).collect();
+
+    for required in entity.required_columns() {
+        if !columns.contains(&Some(*required)) {
+            return Err(anyhow!("Missing required column: {}", required));
+        }
+    }
+
+    let rows = records
+        .filter(|(_, record)| record.iter().any(|value| !value.trim().is_empty()))
+        .map(|(index, record)| SourceRow {
+            row_number: index + 1,
+            values: columns
+                .iter()
+                .zip(record)
+                .filter_map(|(column, value)| column.map(|c| (c, value)))
+                .collect(),
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+fn read_csv(path: &str) -> Result<Vec<Vec<String>>> {
+    let bytes = std::fs::read(path)?;
+    // Excel saves CSV as Windows-1252 on French systems
+    let content = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => encoding_rs::WINDOWS_1252.decode(e.as_bytes()).0.into_owned(),
+    };
+
+    let first_line = content.lines().next().unwrap_or_default();
+    let delimiter = [b';', b',', b'\t']
+        .into_iter()
+        .max_by_key(|d| first_line.matches(*d as char).count())
+        .unwrap_or(b';');
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let mut records = Vec::new();
+    for record in reader.records() {
+        records.push(record?.iter().map(str::to_string).collect());
+    }
+
+    Ok(records)
+}
+
+fn read_workbook(path: &str) -> Result<Vec<Vec<String>>> {
+    let mut workbook = open_workbook_auto(path)?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| anyhow!("The workbook has no sheet"))??;
+
+    Ok(range
+        .rows()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect())
+}
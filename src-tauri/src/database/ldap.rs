@@ -0,0 +1,249 @@
+use anyhow::{anyhow, Result};
+use ldap3::{ldap_escape, Ldap, LdapConnAsync, LdapConnSettings, Scope, SearchEntry};
+use std::env;
+use std::time::Duration;
+
+use super::security::env_or;
+
+/// LDAP result code of a bind with a wrong password (also returned by Active
+/// Directory for disabled or expired accounts).
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// Directory used when `AUTH_PROVIDER=ldap`, configured through the `LDAP_*`
+/// environment variables described in DATABASE.md.
+#[derive(Debug, Clone)]
+pub struct LdapConfig {
+    pub url: String,
+    pub starttls: bool,
+    pub base_dn: String,
+    pub bind_dn: Option<String>, // Service account used to find users, anonymous search when unset
+    pub bind_password: Option<String>,
+    pub username_attribute: String,
+    pub user_filter: String,
+    pub group_base_dn: Option<String>, // Groups are searched here when the server has no memberOf
+    pub group_roles: Vec<(String, String)>, // Group DN or CN and the name of the role it grants
+    pub default_role: Option<String>,
+    pub timeout_seconds: u64,
+}
+
+/// A user the directory accepted, with the roles granted by their groups.
+#[derive(Debug, Clone)]
+pub struct DirectoryUser {
+    pub username: String,
+    pub roles: Vec<String>,
+}
+
+impl LdapConfig {
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            url: optional_env("LDAP_URL").ok_or_else(|| anyhow!("LDAP_URL must be set when AUTH_PROVIDER is ldap"))?,
+            starttls: env_or("LDAP_STARTTLS", false),
+            base_dn: optional_env("LDAP_BASE_DN").ok_or_else(|| anyhow!("LDAP_BASE_DN must be set when AUTH_PROVIDER is ldap"))?,
+            bind_dn: optional_env("LDAP_BIND_DN"),
+            bind_password: optional_env("LDAP_BIND_PASSWORD"),
+            username_attribute: optional_env("LDAP_USERNAME_ATTRIBUTE").unwrap_or_else(|| "sAMAccountName".to_string()),
+            user_filter: optional_env("LDAP_USER_FILTER").unwrap_or_else(|| "(objectClass=person)".to_string()),
+            group_base_dn: optional_env("LDAP_GROUP_BASE_DN"),
+            group_roles: parse_group_roles(&env::var("LDAP_GROUP_ROLES").unwrap_or_default())?,
+            default_role: optional_env("LDAP_DEFAULT_ROLE"),
+            timeout_seconds: env_or("LDAP_TIMEOUT_SECONDS", 10),
+        })
+    }
+
+    /// Checks the credentials against the directory. `None` means an unknown
+    /// user or a wrong password, errors mean the directory could not be asked.
+    pub async fn authenticate(&self, username: &str, password: &str) -> Result<Option<DirectoryUser>> {
+        // A simple bind with an empty password is an anonymous bind, which servers accept
+        if username.trim().is_empty() || password.is_empty() {
+            return Ok(None);
+        }
+
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(Duration::from_secs(self.timeout_seconds))
+            .set_starttls(self.starttls);
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &self.url)
+            .await
+            .map_err(|e| anyhow!("Cannot reach the directory server: {}", e))?;
+        ldap3::drive!(conn);
+
+        let result = self.bind_user(&mut ldap, username.trim(), password).await;
+        let _ = ldap.unbind().await;
+
+        result.map_err(|e| anyhow!("Directory login failed: {}", e))
+    }
+
+    async fn bind_user(&self, ldap: &mut Ldap, username: &str, password: &str) -> Result<Option<DirectoryUser>> {
+        let timeout = Duration::from_secs(self.timeout_seconds);
+
+        if let Some(bind_dn) = &self.bind_dn {
+            ldap.with_timeout(timeout)
+                .simple_bind(bind_dn, self.bind_password.as_deref().unwrap_or_default())
+                .await?
+                .success()?;
+        }
+
+        let filter = format!("(&{}({}={}))", self.user_filter, self.username_attribute, ldap_escape(username));
+        let (entries, _) = ldap
+            .with_timeout(timeout)
+            .search(&self.base_dn, Scope::Subtree, &filter, vec![self.username_attribute.as_str(), "memberOf"])
+            .await?
+            .success()?;
+
+        // Unknown users and ambiguous filters are both refused
+        let mut entries = entries.into_iter().map(SearchEntry::construct);
+        let (Some(entry), None) = (entries.next(), entries.next()) else {
+            return Ok(None);
+        };
+
+        let mut groups = attribute(&entry, "memberOf");
+        if let Some(group_base_dn) = &self.group_base_dn {
+            let filter = format!(
+                "(|(member={dn})(uniqueMember={dn})(memberUid={uid}))",
+                dn = ldap_escape(entry.dn.as_str()),
+                uid = ldap_escape(username)
+            );
+            let (group_entries, _) = ldap
+                .with_timeout(timeout)
+                .search(group_base_dn, Scope::Subtree, &filter, vec!["cn"])
+                .await?
+                .success()?;
+            groups.extend(group_entries.into_iter().map(|group| SearchEntry::construct(group).dn));
+        }
+
+        let bind = ldap.with_timeout(timeout).simple_bind(&entry.dn, password).await?;
+        if bind.rc == INVALID_CREDENTIALS {
+            return Ok(None);
+        }
+        bind.success()?;
+
+        let username = attribute(&entry, &self.username_attribute)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| username.to_string());
+
+        Ok(Some(DirectoryUser {
+            username,
+            roles: self.roles_for(&groups),
+        }))
+    }
+
+    /// Role names granted by `groups`, in the order of `LDAP_GROUP_ROLES`.
+    fn roles_for(&self, groups: &[String]) -> Vec<String> {
+        let mut roles: Vec<String> = Vec::new();
+
+        for (group, role) in &self.group_roles {
+            let member = groups.iter().any(|dn| {
+                dn.eq_ignore_ascii_case(group) || common_name(dn).is_some_and(|cn| cn.eq_ignore_ascii_case(group))
+            });
+            if member && !roles.contains(role) {
+                roles.push(role.clone());
+            }
+        }
+
+        if roles.is_empty() {
+            roles.extend(self.default_role.clone());
+        }
+
+        roles
+    }
+}
+
+/// Parses `group=role;group=role`. Group DNs contain `=` themselves, so each
+/// entry is split at its last one.
+fn parse_group_roles(value: &str) -> Result<Vec<(String, String)>> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (group, role) = entry
+                .rsplit_once('=')
+                .ok_or_else(|| anyhow!("LDAP_GROUP_ROLES: expected group=role, got {}", entry))?;
+            Ok((group.trim().to_string(), role.trim().to_string()))
+        })
+        .collect()
+}
+
+fn attribute(entry: &SearchEntry, name: &str) -> Vec<String> {
+    entry
+        .attrs
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, values)| values.clone())
+        .unwrap_or_default()
+}
+
+/// `Quality` for `CN=Quality,OU=Groups,DC=plant,DC=local`.
+fn common_name(dn: &str) -> Option<&str> {
+    let (key, value) = dn.split(',').next()?.split_once('=')?;
+    key.trim().eq_ignore_ascii_case("cn").then(|| value.trim())
+}
+
+fn optional_env(name: &str) -> Option<String> {
+    env::var(name).ok().map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(group_roles: &str, default_role: Option<&str>) -> LdapConfig {
+        LdapConfig {
+            url: "ldap://localhost".to_string(),
+            starttls: false,
+            base_dn: "DC=plant,DC=local".to_string(),
+            bind_dn: None,
+            bind_password: None,
+            username_attribute: "sAMAccountName".to_string(),
+            user_filter: "(objectClass=person)".to_string(),
+            group_base_dn: None,
+            group_roles: parse_group_roles(group_roles).unwrap(),
+            default_role: default_role.map(str::to_string),
+            timeout_seconds: 10,
+        }
+    }
+
+    #[test]
+    fn parse_group_roles_splits_entries_at_their_last_equal_sign() {
+        let roles = parse_group_roles(" CN=Quality,OU=Groups,DC=plant,DC=local = performance ; ;Operators=site01;").unwrap();
+        assert_eq!(
+            roles,
+            [
+                ("CN=Quality,OU=Groups,DC=plant,DC=local".to_string(), "performance".to_string()),
+                ("Operators".to_string(), "site01".to_string()),
+            ]
+        );
+
+        assert!(parse_group_roles("").unwrap().is_empty());
+        assert!(parse_group_roles("Quality").is_err());
+    }
+
+    #[test]
+    fn common_name_reads_the_first_rdn_only() {
+        assert_eq!(common_name("CN=Quality,OU=Groups,DC=plant,DC=local"), Some("Quality"));
+        assert_eq!(common_name(" cn = Quality "), Some("Quality"));
+        assert_eq!(common_name("OU=Groups,CN=Quality"), None);
+        assert_eq!(common_name("Quality"), None);
+    }
+
+    #[test]
+    fn roles_for_matches_group_dns_and_names_without_case() {
+        let config = config("CN=Quality,OU=Groups,DC=plant,DC=local=performance;operators=site01;Admins=performance", Some("viewer"));
+        let groups = vec![
+            "cn=quality,ou=groups,dc=plant,dc=local".to_string(),
+            "CN=Admins,OU=Groups,DC=plant,DC=local".to_string(),
+            "CN=Operators,OU=Groups,DC=plant,DC=local".to_string(),
+        ];
+
+        // In the order of LDAP_GROUP_ROLES, without duplicates
+        assert_eq!(config.roles_for(&groups), ["performance", "site01"]);
+    }
+
+    #[test]
+    fn roles_for_falls_back_to_the_default_role() {
+        let groups = vec!["CN=Visitors,OU=Groups,DC=plant,DC=local".to_string()];
+
+        assert_eq!(config("Operators=site01", Some("viewer")).roles_for(&groups), ["viewer"]);
+        assert!(config("Operators=site01", None).roles_for(&groups).is_empty());
+    }
+}
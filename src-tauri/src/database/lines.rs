@@ -1,16 +1,24 @@
 use sqlx::PgPool;
 use anyhow::Result;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-use super::models::ProductionLine;
+use super::audit::{record_merge, MergeEntity, MergeResponse, MergeSide};
+use super::bulk::{delete_response, deletable_ids, plan_bulk, BulkCreateResponse, BulkDeleteResponse, OnConflict};
+use super::concurrency::update_miss;
+use super::import::{clean_text, normalize_key, normalized_column};
+use super::models::{LineActivation, ProductionLine};
+use super::reports::{record_field_changes, FieldChange};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PaginationParams {
     pub page: i64,
     pub limit: i64,
     pub search: Option<String>,
+    #[serde(default)]
+    pub include_archived: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,6 +42,8 @@ pub struct CreateLineRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BulkCreateLinesRequest {
     pub lines: Vec<CreateLineRequest>,
+    #[serde(default)]
+    pub on_conflict: OnConflict, // Matched on line name
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -42,6 +52,68 @@ pub struct UpdateLineRequest {
     pub name: String,
     pub description: Option<String>,
     pub is_active: bool,
+    /// `updated_at` of the copy being edited, the update is refused if the line changed since
+    pub expected_updated_at: DateTime<Utc>,
+}
+
+/// NC counts of a line over a period, next to how long it actually ran.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LineActivity {
+    pub line_id: Uuid,
+    pub line_name: String,
+    pub is_active: bool,
+    pub active_days: f64,
+    pub report_count: i64,
+    pub reports_per_active_day: Option<f64>, // None when the line never ran in the period
+}
+
+/// Appends an activation entry for each line whose `is_active` differs from
+/// its latest recorded state. Called after any write to `production_lines`.
+pub(crate) async fn record_line_activation<'e, E>(executor: E, line_ids: &[Uuid], at: DateTime<Utc>) -> Result<()>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO line_activation_history (id, line_id, is_active, changed_at)
+        SELECT t.id, pl.id, pl.is_active, $3
+        FROM UNNEST($1::UUID[], $2::UUID[]) AS t(id, line_id)
+        JOIN production_lines pl ON pl.id = t.line_id
+        WHERE pl.is_active IS DISTINCT FROM (
+            SELECT h.is_active FROM line_activation_history h
+            WHERE h.line_id = pl.id
+            ORDER BY h.changed_at DESC
+            LIMIT 1
+        )
+        "#,
+    )
+    .bind(line_ids.iter().map(|_| Uuid::new_v4()).collect::<Vec<_>>())
+    .bind(line_ids)
+    .bind(at)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Line names are unique among non-archived lines, ignoring case and spacing.
+async fn ensure_unique_name<'e, E>(executor: E, name: &str, exclude_id: Option<Uuid>) -> Result<()>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let existing: Option<String> = sqlx::query_scalar(&format!(
+        "SELECT name FROM production_lines WHERE archived_at IS NULL AND ($2::UUID IS NULL OR id <> $2) AND {} = $1 LIMIT 1",
+        normalized_column("name")
+    ))
+    .bind(normalize_key(name))
+    .bind(exclude_id)
+    .fetch_optional(executor)
+    .await?;
+
+    match existing {
+        Some(existing) => Err(anyhow::anyhow!("Production line {} already exists", existing)),
+        None => Ok(()),
+    }
 }
 
 pub struct LinesService {
@@ -53,10 +125,13 @@ impl LinesService {
         Self { pool }
     }
 
-    pub async fn get_all_lines(&self) -> Result<Vec<ProductionLine>> {
+    pub async fn get_all_lines(&self, active_only: bool) -> Result<Vec<ProductionLine>> {
         let lines = sqlx::query_as::<_, ProductionLine>(
-            "SELECT id, name, description, is_active, created_at, updated_at FROM production_lines ORDER BY name ASC"
+            "SELECT id, name, description, is_active, created_at, updated_at, archived_at FROM production_lines
+             WHERE archived_at IS NULL AND (is_active OR NOT $1)
+             ORDER BY name ASC"
         )
+        .bind(active_only)
         .fetch_all(&self.pool)
         .await?;
 
@@ -66,14 +141,24 @@ impl LinesService {
     pub async fn get_paginated_lines(&self, params: PaginationParams) -> Result<PaginatedResponse<ProductionLine>> {
         let offset = (params.page - 1) * params.limit;
         
-        let mut query = "SELECT id, name, description, is_active, created_at, updated_at FROM production_lines".to_string();
+        let mut query = "SELECT id, name, description, is_active, created_at, updated_at, archived_at FROM production_lines".to_string();
         let mut count_query = "SELECT COUNT(*) as count FROM production_lines".to_string();
         
+        let mut conditions = Vec::new();
+
+        if !params.include_archived {
+            conditions.push("archived_at IS NULL".to_string());
+        }
+
         if let Some(search) = &params.search {
-            let search_condition = format!(" WHERE name ILIKE '%{}%' OR description ILIKE '%{}%'", 
-                search.replace("'", "''"), search.replace("'", "''"));
-            query.push_str(&search_condition);
-            count_query.push_str(&search_condition);
+            conditions.push(format!("(name ILIKE '%{}%' OR description ILIKE '%{}%')", 
+                search.replace("'", "''"), search.replace("'", "''")));
+        }
+
+        if !conditions.is_empty() {
+            let where_clause = format!(" WHERE {}", conditions.join(" AND "));
+            query.push_str(&where_clause);
+            count_query.push_str(&where_clause);
         }
         
         query.push_str(" ORDER BY name ASC LIMIT $1 OFFSET $2");
@@ -106,6 +191,8 @@ impl LinesService {
         let line_id = Uuid::new_v4();
         let now = Utc::now();
 
+        ensure_unique_name(&self.pool, &request.name, None).await?;
+
         sqlx::query(
             "INSERT INTO production_lines (id, name, description, is_active, created_at, updated_at)
              VALUES ($1, $2, $3, $4, $5, $6)"
@@ -119,6 +206,8 @@ impl LinesService {
         .execute(&self.pool)
         .await?;
 
+        record_line_activation(&self.pool, &[line_id], now).await?;
+
         let line = ProductionLine {
             id: line_id,
             name: request.name,
@@ -126,50 +215,245 @@ impl LinesService {
             is_active: request.is_active,
             created_at: now,
             updated_at: now,
+            archived_at: None,
         };
 
         Ok(line)
     }
 
-    pub async fn bulk_create_lines(&self, request: BulkCreateLinesRequest) -> Result<Vec<ProductionLine>> {
-        let mut created_lines = Vec::new();
-        
-        for line_request in request.lines {
-            let created_line = self.create_line(line_request).await?;
-            created_lines.push(created_line);
-        }
+    pub async fn bulk_create_lines(&self, request: BulkCreateLinesRequest) -> Result<BulkCreateResponse<ProductionLine>> {
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+
+        // Keeps a concurrent import from adding the same names between the lookup and the insert
+        sqlx::query("LOCK TABLE production_lines IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&mut *tx)
+            .await?;
+
+        let existing: HashMap<String, Uuid> = sqlx::query_as::<_, (Uuid, String)>("SELECT id, name FROM production_lines WHERE archived_at IS NULL")
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|(id, name)| (normalize_key(&name), id))
+            .collect();
+
+        let plan = plan_bulk(request.lines, &existing, |l| normalize_key(&l.name), request.on_conflict, "Line")?;
 
-        Ok(created_lines)
+        let mut data = sqlx::query_as::<_, ProductionLine>(
+            "INSERT INTO production_lines (id, name, description, is_active, created_at, updated_at)
+             SELECT t.id, t.name, t.description, t.is_active, $5, $5
+             FROM UNNEST($1::UUID[], $2::TEXT[], $3::TEXT[], $4::BOOLEAN[]) AS t(id, name, description, is_active)
+             RETURNING id, name, description, is_active, created_at, updated_at"
+        )
+        .bind(plan.inserts.iter().map(|_| Uuid::new_v4()).collect::<Vec<_>>())
+        .bind(plan.inserts.iter().map(|l| clean_text(&l.name)).collect::<Vec<_>>())
+        .bind(plan.inserts.iter().map(|l| l.description.clone()).collect::<Vec<_>>())
+        .bind(plan.inserts.iter().map(|l| l.is_active).collect::<Vec<_>>())
+        .bind(now)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let updated = sqlx::query_as::<_, ProductionLine>(
+            "UPDATE production_lines pl SET name = t.name, description = t.description, is_active = t.is_active, updated_at = $5
+             FROM UNNEST($1::UUID[], $2::TEXT[], $3::TEXT[], $4::BOOLEAN[]) AS t(id, name, description, is_active)
+             WHERE pl.id = t.id
+             RETURNING pl.id, pl.name, pl.description, pl.is_active, pl.created_at, pl.updated_at"
+        )
+        .bind(plan.updates.iter().map(|(id, _)| *id).collect::<Vec<_>>())
+        .bind(plan.updates.iter().map(|(_, l)| clean_text(&l.name)).collect::<Vec<_>>())
+        .bind(plan.updates.iter().map(|(_, l)| l.description.clone()).collect::<Vec<_>>())
+        .bind(plan.updates.iter().map(|(_, l)| l.is_active).collect::<Vec<_>>())
+        .bind(now)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let line_ids: Vec<Uuid> = data.iter().chain(updated.iter()).map(|l| l.id).collect();
+        record_line_activation(&mut *tx, &line_ids, now).await?;
+
+        tx.commit().await?;
+
+        let inserted = data.len();
+        let updated_count = updated.len();
+        data.extend(updated);
+
+        Ok(BulkCreateResponse {
+            inserted,
+            updated: updated_count,
+            skipped: plan.skipped,
+            data,
+        })
     }
 
     pub async fn update_line(&self, request: UpdateLineRequest) -> Result<ProductionLine> {
         let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
 
-        sqlx::query(
-            "UPDATE production_lines SET name = $2, description = $3, is_active = $4, updated_at = $5 WHERE id = $1"
+        ensure_unique_name(&mut *tx, &request.name, Some(request.id)).await?;
+
+        let line = sqlx::query_as::<_, ProductionLine>(
+            r#"
+            UPDATE production_lines SET name = $2, description = $3, is_active = $4, updated_at = $5
+            WHERE id = $1 AND updated_at = $6
+            RETURNING id, name, description, is_active, created_at, updated_at, archived_at
+            "#
         )
         .bind(&request.id)
         .bind(&request.name)
         .bind(&request.description)
         .bind(request.is_active)
         .bind(now)
-        .execute(&self.pool)
+        .bind(request.expected_updated_at)
+        .fetch_optional(&mut *tx)
         .await?;
 
-        let line = ProductionLine {
-            id: request.id,
-            name: request.name,
-            description: request.description,
-            is_active: request.is_active,
-            created_at: now, // This would ideally be fetched from DB
-            updated_at: now,
+        let Some(line) = line else {
+            return Err(update_miss(&mut *tx, "production_lines", "Production line", request.id, Some(request.expected_updated_at)).await?);
         };
 
+        record_line_activation(&mut *tx, &[request.id], now).await?;
+        tx.commit().await?;
+
         Ok(line)
     }
 
+    pub async fn get_line_activation_history(&self, line_id: &Uuid) -> Result<Vec<LineActivation>> {
+        let history = sqlx::query_as::<_, LineActivation>(
+            "SELECT id, line_id, is_active, changed_at FROM line_activation_history WHERE line_id = $1 ORDER BY changed_at ASC"
+        )
+        .bind(line_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(history)
+    }
+
+    /// Report counts per line between two dates (inclusive), normalized by the
+    /// days each line was active according to its activation history.
+    pub async fn get_line_activity(&self, start_date: NaiveDate, end_date: NaiveDate) -> Result<Vec<LineActivity>> {
+        if end_date < start_date {
+            return Err(anyhow::anyhow!("End date must not be before start date"));
+        }
+
+        let rows = sqlx::query_as::<_, (Uuid, String, bool, f64, i64)>(
+            r#"
+            WITH bounds AS (
+                SELECT $1::DATE::TIMESTAMPTZ AS start_at, ($2::DATE + 1)::TIMESTAMPTZ AS end_at
+            ),
+            periods AS (
+                SELECT h.line_id,
+                       GREATEST(h.changed_at, b.start_at) AS period_start,
+                       LEAST(
+                           COALESCE(LEAD(h.changed_at) OVER (PARTITION BY h.line_id ORDER BY h.changed_at), NOW()),
+                           b.end_at
+                       ) AS period_end,
+                       h.is_active
+                FROM line_activation_history h
+                CROSS JOIN bounds b
+            )
+            SELECT pl.id, pl.name, pl.is_active,
+                   COALESCE((
+                       SELECT SUM(EXTRACT(EPOCH FROM (p.period_end - p.period_start)))::FLOAT8 / 86400
+                       FROM periods p
+                       WHERE p.line_id = pl.id AND p.is_active AND p.period_end > p.period_start
+                   ), 0),
+                   (
+                       SELECT COUNT(*)
+                       FROM non_conformity_reports ncr, bounds b
+                       WHERE ncr.line_id = pl.id
+                         AND ncr.archived_at IS NULL
+                         AND ncr.report_date >= b.start_at
+                         AND ncr.report_date < b.end_at
+                   )
+            FROM production_lines pl
+            WHERE pl.archived_at IS NULL
+            ORDER BY pl.name ASC
+            "#,
+        )
+        .bind(start_date)
+        .bind(end_date)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let activity = rows
+            .into_iter()
+            .map(|(line_id, line_name, is_active, active_days, report_count)| LineActivity {
+                line_id,
+                line_name,
+                is_active,
+                active_days,
+                report_count,
+                reports_per_active_day: (active_days > 0.0).then(|| report_count as f64 / active_days),
+            })
+            .collect();
+
+        Ok(activity)
+    }
+
+    /// Moves every report of `source_id` to `target_id`, then archives the source.
+    pub async fn merge_lines(&self, source_id: &Uuid, target_id: &Uuid, merged_by: Option<Uuid>) -> Result<MergeResponse<ProductionLine>> {
+        if source_id == target_id {
+            return Err(anyhow::anyhow!("A line cannot be merged into itself"));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let source_name: String = sqlx::query_scalar("SELECT name FROM production_lines WHERE id = $1 FOR UPDATE")
+            .bind(source_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Line to merge not found"))?;
+
+        let target = sqlx::query_as::<_, ProductionLine>(
+            "SELECT id, name, description, is_active, created_at, updated_at, archived_at FROM production_lines WHERE id = $1 FOR UPDATE"
+        )
+        .bind(target_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Target line not found"))?;
+
+        if target.archived_at.is_some() {
+            return Err(anyhow::anyhow!("Target line is archived"));
+        }
+
+        let moved: Vec<Uuid> = sqlx::query_scalar(
+            "UPDATE non_conformity_reports SET line_id = $2, updated_at = NOW(), version = version + 1 WHERE line_id = $1 RETURNING id"
+        )
+        .bind(source_id)
+        .bind(target_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let changes: Vec<(Uuid, FieldChange)> = moved
+            .iter()
+            .map(|id| (*id, FieldChange { field: "line_id", old_value: Some(source_id.to_string()), new_value: Some(target_id.to_string()) }))
+            .collect();
+        record_field_changes(&mut *tx, &changes, merged_by, Utc::now()).await?;
+        let reports_moved = moved.len() as u64;
+
+        sqlx::query("UPDATE production_lines SET archived_at = NOW(), updated_at = NOW() WHERE id = $1 AND archived_at IS NULL")
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await?;
+
+        record_merge(
+            &mut *tx,
+            MergeEntity::Line,
+            MergeSide { id: *source_id, label: &source_name },
+            MergeSide { id: target.id, label: &target.name },
+            reports_moved,
+            merged_by,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        println!("[LINES_SERVICE] Merged line {} into {}, {} reports moved", source_id, target_id, reports_moved);
+
+        Ok(MergeResponse { record: target, reports_moved })
+    }
+
     pub async fn delete_line(&self, line_id: &Uuid) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM production_lines WHERE id = $1")
+        let result = sqlx::query("UPDATE production_lines SET archived_at = NOW() WHERE id = $1 AND archived_at IS NULL")
             .bind(line_id)
             .execute(&self.pool)
             .await?;
@@ -177,4 +461,54 @@ impl LinesService {
         Ok(result.rows_affected() > 0)
     }
 
+    /// Refused while an active line has the same name.
+    pub async fn restore_line(&self, line_id: &Uuid) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let name: Option<String> = sqlx::query_scalar(
+            "SELECT name FROM production_lines WHERE id = $1 AND archived_at IS NOT NULL FOR UPDATE"
+        )
+        .bind(line_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(name) = name else {
+            return Ok(false);
+        };
+
+        ensure_unique_name(&mut *tx, &name, Some(*line_id)).await?;
+
+        sqlx::query("UPDATE production_lines SET archived_at = NULL WHERE id = $1")
+            .bind(line_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Archives several lines in one transaction. Lines still referenced by
+    /// reports are left in place unless `force` is set.
+    pub async fn delete_multiple_lines(&self, ids: &[Uuid], force: bool) -> Result<BulkDeleteResponse> {
+        let mut tx = self.pool.begin().await?;
+
+        let report_counts: HashMap<Uuid, i64> = sqlx::query_as::<_, (Uuid, i64)>(
+            "SELECT line_id, COUNT(*) FROM non_conformity_reports WHERE line_id = ANY($1) GROUP BY line_id"
+        )
+        .bind(ids)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+
+        let deleted: Vec<Uuid> = sqlx::query_scalar("UPDATE production_lines SET archived_at = NOW() WHERE id = ANY($1) AND archived_at IS NULL RETURNING id")
+            .bind(deletable_ids(ids, &report_counts, force))
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(delete_response(ids, &report_counts, &deleted))
+    }
+
 }
@@ -1,9 +1,11 @@
 use sqlx::PgPool;
 use anyhow::Result;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use bcrypt::{hash, DEFAULT_COST};
 
+use super::import::normalized_column;
+
 const WILAYAS: [&str; 58] = [
     "Adrar", "Chlef", "Laghouat", "Oum El Bouaghi", "Batna", "Béjaïa", "Biskra",
     "Bechar", "Blida", "Bouira", "Tamanrasset", "Tbessa", "Tlemcen", "Tiaret",
@@ -24,8 +26,6 @@ pub async fn create_users_table(pool: &PgPool) -> Result<()> {
             id UUID PRIMARY KEY,
             username VARCHAR(255) UNIQUE NOT NULL,
             password_hash TEXT NOT NULL,
-            role VARCHAR(50) NOT NULL CHECK (role IN ('Réclamation client', 'Retour client', 'site01', 'site02', 'performance', 'admin', 'consommateur')),
-            roles TEXT[], 
             created_at TIMESTAMPTZ NOT NULL,
             updated_at TIMESTAMPTZ NOT NULL
         )
@@ -37,37 +37,155 @@ pub async fn create_users_table(pool: &PgPool) -> Result<()> {
     Ok(())
 }
 
-async fn migrate_single_roles_to_multiple(pool: &PgPool) -> Result<()> {
-    // Check if roles column exists and has data
-    let has_roles_data: i64 = sqlx::query_scalar(
-        "SELECT COUNT(*) FROM users WHERE roles IS NOT NULL AND array_length(roles, 1) > 0"
+/// Built in roles, the fixed roles of earlier versions: name, description,
+/// permissions and the claim origins they can see and report on.
+const SYSTEM_ROLES: [(&str, &str, &[&str], &[&str]); 7] = [
+    ("admin", "Administration complète", &["create_report", "edit_performance", "manage_master_data", "manage_users"], &ALL_ORIGINS),
+    ("performance", "Suivi de la performance sur toutes les origines", &["create_report", "edit_performance"], &ALL_ORIGINS),
+    ("site01", "Rapports du site 01", &["create_report"], &["site01"]),
+    ("site02", "Rapports du site 02", &["create_report"], &["site02"]),
+    ("Réclamation client", "Rapports des réclamations client", &["create_report"], &["Réclamation client"]),
+    ("Retour client", "Rapports des retours client", &["create_report"], &["Retour client"]),
+    ("consommateur", "Rapports des réclamations consommateur", &["create_report"], &["consommateur"]),
+];
+
+const ALL_ORIGINS: [&str; 5] = ["Réclamation client", "Retour client", "site01", "site02", "consommateur"];
+
+async fn create_roles_tables(pool: &PgPool) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS roles (
+            id UUID PRIMARY KEY,
+            name VARCHAR(50) UNIQUE NOT NULL,
+            description TEXT,
+            is_system BOOLEAN NOT NULL DEFAULT FALSE,
+            created_at TIMESTAMPTZ NOT NULL,
+            updated_at TIMESTAMPTZ NOT NULL
+        )
+        "#,
     )
-    .fetch_one(pool)
+    .execute(pool)
     .await?;
 
-    if has_roles_data == 0 {
-        // Migrate existing single roles to roles array
-        sqlx::query(
-            "UPDATE users SET roles = ARRAY[role] WHERE roles IS NULL OR array_length(roles, 1) IS NULL"
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS role_permissions (
+            role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+            permission VARCHAR(30) NOT NULL CHECK (permission IN ('create_report', 'edit_performance', 'manage_master_data', 'manage_users')),
+            PRIMARY KEY (role_id, permission)
         )
-        .execute(pool)
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS role_origins (
+            role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+            claim_origin VARCHAR(20) NOT NULL CHECK (claim_origin IN ('Réclamation client', 'Retour client', 'site01', 'site02', 'consommateur')),
+            PRIMARY KEY (role_id, claim_origin)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS user_roles (
+            user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            role_id UUID NOT NULL REFERENCES roles(id) ON DELETE RESTRICT,
+            PRIMARY KEY (user_id, role_id)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Built in roles are only inserted once, admins may have changed them since
+    for (name, description, permissions, origins) in SYSTEM_ROLES {
+        let inserted: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            INSERT INTO roles (id, name, description, is_system, created_at, updated_at)
+            VALUES ($1, $2, $3, TRUE, NOW(), NOW())
+            ON CONFLICT (name) DO NOTHING
+            RETURNING id
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(name)
+        .bind(description)
+        .fetch_optional(pool)
         .await?;
-        
-        println!("Migrated {} users from single role to multiple roles", 
-                 sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users")
-                     .fetch_one(pool)
-                     .await?);
+
+        if let Some(role_id) = inserted {
+            sqlx::query("INSERT INTO role_permissions (role_id, permission) SELECT $1, UNNEST($2::TEXT[])")
+                .bind(role_id)
+                .bind(permissions)
+                .execute(pool)
+                .await?;
+            sqlx::query("INSERT INTO role_origins (role_id, claim_origin) SELECT $1, UNNEST($2::TEXT[])")
+                .bind(role_id)
+                .bind(origins)
+                .execute(pool)
+                .await?;
+        }
     }
 
     Ok(())
 }
 
+/// Moves the `role` and `roles` columns of older databases into user_roles, then drops them.
+async fn migrate_legacy_user_roles(pool: &PgPool) -> Result<()> {
+    let has_legacy_roles: bool = sqlx::query_scalar(
+        "SELECT EXISTS (
+            SELECT FROM information_schema.columns
+            WHERE table_schema = 'public' AND table_name = 'users' AND column_name = 'role'
+        )"
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if !has_legacy_roles {
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let migrated = sqlx::query(
+        r#"
+        INSERT INTO user_roles (user_id, role_id)
+        SELECT u.id, r.id
+        FROM users u
+        CROSS JOIN LATERAL UNNEST(
+            CASE WHEN array_length(u.roles, 1) > 0 THEN u.roles ELSE ARRAY[u.role] END
+        ) AS legacy(name)
+        JOIN roles r ON r.name = legacy.name
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("ALTER TABLE users DROP COLUMN IF EXISTS roles, DROP COLUMN IF EXISTS role")
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    println!("Migrated {} user roles to the user_roles table", migrated.rows_affected());
+
+    Ok(())
+}
+
 pub async fn run_migrations(pool: &PgPool) -> Result<()> {
     // Create users table
     create_users_table(pool).await?;
     
-    // Migrate existing single roles to multiple roles
-    migrate_single_roles_to_multiple(pool).await?;
+    // Create roles tables and move the roles of existing users into them
+    create_roles_tables(pool).await?;
+    migrate_legacy_user_roles(pool).await?;
 
     // Create production_lines table
     sqlx::query(
@@ -175,6 +293,43 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
         println!("Inserted wilayas data");
     }
 
+    // Create product_families table, groups products of the same range (e.g. "Izem")
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS product_families (
+            id UUID PRIMARY KEY,
+            name VARCHAR(255) UNIQUE NOT NULL,
+            brand VARCHAR(255),
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        ALTER TABLE products
+        ADD COLUMN IF NOT EXISTS family_id UUID REFERENCES product_families(id) ON DELETE SET NULL
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create product_formats table, the formats each product is produced in
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS product_formats (
+            product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+            format_id INTEGER NOT NULL REFERENCES formats(id) ON DELETE CASCADE,
+            PRIMARY KEY (product_id, format_id)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
     // Create non_conformity_reports table
     sqlx::query(
         r#"
@@ -182,13 +337,13 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
             id UUID PRIMARY KEY,
             report_number VARCHAR(50) UNIQUE NOT NULL,
             report_date TIMESTAMPTZ NOT NULL,
-            line_id UUID NOT NULL REFERENCES production_lines(id) ON DELETE CASCADE,
-            product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
-            format_id INTEGER REFERENCES formats(id) ON DELETE SET NULL,
+            line_id UUID NOT NULL REFERENCES production_lines(id) ON DELETE RESTRICT,
+            product_id UUID NOT NULL REFERENCES products(id) ON DELETE RESTRICT,
+            format_id INTEGER REFERENCES formats(id) ON DELETE RESTRICT,
             production_date DATE NOT NULL,
             team VARCHAR(1) NOT NULL CHECK (team IN ('A', 'B', 'C')),
             time TIME NOT NULL,
-            description_type VARCHAR(50) NOT NULL CHECK (description_type IN ('Physique', 'Chimique', 'Biologique', 'Process', 'GDS')),
+            description_type VARCHAR(50) NOT NULL,
             description_details TEXT NOT NULL,
             quantity INTEGER NOT NULL CHECK (quantity > 0),
             claim_origin VARCHAR(20) NOT NULL CHECK (claim_origin IN ('Réclamation client', 'Retour client', 'site01', 'site02', 'consommateur')),
@@ -196,7 +351,7 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
             valuation DECIMAL(10, 2) NOT NULL,
             performance TEXT,
             status VARCHAR(20) NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'in_progress', 'resolved', 'closed')),
-            reported_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            reported_by UUID NOT NULL REFERENCES users(id) ON DELETE RESTRICT,
             created_at TIMESTAMPTZ NOT NULL,
             updated_at TIMESTAMPTZ NOT NULL
         )
@@ -225,8 +380,218 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
     .execute(pool)
     .await?;
 
+    // Row version, bumped on every edit so stale copies cannot overwrite newer changes
+    sqlx::query(
+        r#"
+        ALTER TABLE non_conformity_reports 
+        ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
     // Remove the separate ALTER TABLE for format_id since it's now in the main CREATE TABLE
 
+    // Create report_status_history table, one row per status change
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS report_status_history (
+            id UUID PRIMARY KEY,
+            report_id UUID NOT NULL REFERENCES non_conformity_reports(id) ON DELETE CASCADE,
+            old_status VARCHAR(20),
+            new_status VARCHAR(20) NOT NULL,
+            changed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+            changed_at TIMESTAMPTZ NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Failed login counter and temporary lockout
+    sqlx::query(
+        r#"
+        ALTER TABLE users
+        ADD COLUMN IF NOT EXISTS failed_login_attempts INTEGER NOT NULL DEFAULT 0,
+        ADD COLUMN IF NOT EXISTS locked_until TIMESTAMPTZ
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Forced password change and password age, existing passwords count from the last user update
+    sqlx::query(
+        r#"
+        ALTER TABLE users
+        ADD COLUMN IF NOT EXISTS must_change_password BOOLEAN NOT NULL DEFAULT FALSE,
+        ADD COLUMN IF NOT EXISTS password_changed_at TIMESTAMPTZ
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("UPDATE users SET password_changed_at = updated_at WHERE password_changed_at IS NULL")
+        .execute(pool)
+        .await?;
+
+    // Disabled users cannot log in but stay listed with their reports
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS is_active BOOLEAN NOT NULL DEFAULT TRUE")
+        .execute(pool)
+        .await?;
+
+    // Last login and login count, counted from the login log for existing users
+    sqlx::query(
+        r#"
+        ALTER TABLE users
+        ADD COLUMN IF NOT EXISTS last_login_at TIMESTAMPTZ,
+        ADD COLUMN IF NOT EXISTS login_count INTEGER NOT NULL DEFAULT 0
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Where the password is checked, directory accounts are provisioned at their first login
+    sqlx::query(
+        r#"
+        ALTER TABLE users
+        ADD COLUMN IF NOT EXISTS auth_source VARCHAR(10) NOT NULL DEFAULT 'local' CHECK (auth_source IN ('local', 'ldap'))
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create password_history table, the hashes of recent passwords that cannot be reused
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS password_history (
+            id UUID PRIMARY KEY,
+            user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            password_hash TEXT NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create login_attempts table, one row per login attempt whatever its outcome
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS login_attempts (
+            id UUID PRIMARY KEY,
+            username VARCHAR(255) NOT NULL,
+            user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+            outcome VARCHAR(20) NOT NULL,
+            attempted_at TIMESTAMPTZ NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Recreated so that tables created with fewer outcomes accept the new ones
+    sqlx::query("ALTER TABLE login_attempts DROP CONSTRAINT IF EXISTS login_attempts_outcome_check")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        r#"
+        ALTER TABLE login_attempts ADD CONSTRAINT login_attempts_outcome_check
+        CHECK (outcome IN ('success', 'unknown_user', 'invalid_password', 'locked', 'no_role', 'disabled'))
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Users who logged in before last_login_at existed get it from the login log
+    sqlx::query(
+        r#"
+        UPDATE users u SET last_login_at = logins.last_login_at, login_count = logins.login_count
+        FROM (
+            SELECT user_id, MAX(attempted_at) AS last_login_at, COUNT(*)::INTEGER AS login_count
+            FROM login_attempts
+            WHERE outcome = 'success' AND user_id IS NOT NULL
+            GROUP BY user_id
+        ) logins
+        WHERE u.id = logins.user_id AND u.last_login_at IS NULL
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create report_field_history table, one row per field changed by a report edit
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS report_field_history (
+            id UUID PRIMARY KEY,
+            report_id UUID NOT NULL REFERENCES non_conformity_reports(id) ON DELETE CASCADE,
+            field VARCHAR(50) NOT NULL,
+            old_value TEXT,
+            new_value TEXT,
+            changed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+            changed_at TIMESTAMPTZ NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create line_activation_history table, one row each time a line is switched on or off
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS line_activation_history (
+            id UUID PRIMARY KEY,
+            line_id UUID NOT NULL REFERENCES production_lines(id) ON DELETE CASCADE,
+            is_active BOOLEAN NOT NULL,
+            changed_at TIMESTAMPTZ NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Lines created before the history existed start from their current state
+    let untracked_lines: Vec<(Uuid, bool, DateTime<Utc>)> = sqlx::query_as(
+        r#"
+        SELECT pl.id, pl.is_active, pl.created_at
+        FROM production_lines pl
+        WHERE NOT EXISTS (SELECT 1 FROM line_activation_history h WHERE h.line_id = pl.id)
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for (line_id, is_active, created_at) in untracked_lines {
+        sqlx::query(
+            "INSERT INTO line_activation_history (id, line_id, is_active, changed_at) VALUES ($1, $2, $3, $4)"
+        )
+        .bind(Uuid::new_v4())
+        .bind(line_id)
+        .bind(is_active)
+        .bind(created_at)
+        .execute(pool)
+        .await?;
+    }
+
+    // Create merge_audit table, one row per merge of duplicate master data
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS merge_audit (
+            id UUID PRIMARY KEY,
+            entity VARCHAR(20) NOT NULL CHECK (entity IN ('product', 'line', 'client')),
+            source_id UUID NOT NULL,
+            source_label TEXT NOT NULL,
+            target_id UUID NOT NULL,
+            target_label TEXT NOT NULL,
+            reports_moved BIGINT NOT NULL,
+            merged_by UUID REFERENCES users(id) ON DELETE SET NULL,
+            merged_at TIMESTAMPTZ NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
     // Create clients table
     sqlx::query(
         r#"
@@ -425,7 +790,7 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
 
         for client in &clients {
             sqlx::query(
-                "INSERT INTO clients (id, name) VALUES ($1, $2) ON CONFLICT (name) DO NOTHING"
+                "INSERT INTO clients (id, name) VALUES ($1, $2) ON CONFLICT DO NOTHING"
             )
             .bind(Uuid::new_v4())
             .bind(client.trim())
@@ -464,13 +829,67 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
         .await?;
     }
 
+    // Description types used to be a fixed CHECK list, they now reference nc_des so they can be managed
+    sqlx::query(
+        r#"
+        INSERT INTO nc_des (name)
+        SELECT DISTINCT description_type FROM non_conformity_reports
+        ON CONFLICT (name) DO NOTHING
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        ALTER TABLE non_conformity_reports
+        DROP CONSTRAINT IF EXISTS non_conformity_reports_description_type_check,
+        DROP CONSTRAINT IF EXISTS non_conformity_reports_description_type_fkey,
+        ADD CONSTRAINT non_conformity_reports_description_type_fkey
+            FOREIGN KEY (description_type) REFERENCES nc_des(name) ON UPDATE CASCADE ON DELETE RESTRICT
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Soft deletion: archived rows are hidden from pickers but kept for history
+    for table in ["production_lines", "products", "clients", "users", "non_conformity_reports"] {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN IF NOT EXISTS archived_at TIMESTAMPTZ", table))
+            .execute(pool)
+            .await?;
+    }
+
+    // Reports used to be deleted along with their line, product or author, and lost their format
+    for (column, target) in [("line_id", "production_lines"), ("product_id", "products"), ("reported_by", "users"), ("format_id", "formats")] {
+        sqlx::query(&format!(
+            r#"
+            ALTER TABLE non_conformity_reports
+            DROP CONSTRAINT IF EXISTS non_conformity_reports_{column}_fkey,
+            ADD CONSTRAINT non_conformity_reports_{column}_fkey
+                FOREIGN KEY ({column}) REFERENCES {target}(id) ON DELETE RESTRICT
+            "#
+        ))
+        .execute(pool)
+        .await?;
+    }
+
     // Create indexes
     let index_queries = [
         "CREATE INDEX IF NOT EXISTS idx_non_conformity_reports_report_number ON non_conformity_reports (report_number)",
         "CREATE INDEX IF NOT EXISTS idx_non_conformity_reports_line_id ON non_conformity_reports (line_id)",
         "CREATE INDEX IF NOT EXISTS idx_non_conformity_reports_product_id ON non_conformity_reports (product_id)",
         "CREATE INDEX IF NOT EXISTS idx_non_conformity_reports_status ON non_conformity_reports (status)",
-        "CREATE INDEX IF NOT EXISTS idx_non_conformity_reports_report_date ON non_conformity_reports (report_date)"
+        "CREATE INDEX IF NOT EXISTS idx_non_conformity_reports_report_date ON non_conformity_reports (report_date)",
+        "CREATE INDEX IF NOT EXISTS idx_non_conformity_reports_reported_by ON non_conformity_reports (reported_by)",
+        "CREATE INDEX IF NOT EXISTS idx_report_status_history_report_id ON report_status_history (report_id)",
+        "CREATE INDEX IF NOT EXISTS idx_report_status_history_changed_by ON report_status_history (changed_by)",
+        "CREATE INDEX IF NOT EXISTS idx_report_field_history_report_id ON report_field_history (report_id, changed_at)",
+        "CREATE INDEX IF NOT EXISTS idx_report_field_history_changed_by ON report_field_history (changed_by)",
+        "CREATE INDEX IF NOT EXISTS idx_password_history_user_id ON password_history (user_id, created_at)",
+        "CREATE INDEX IF NOT EXISTS idx_login_attempts_attempted_at ON login_attempts (attempted_at)",
+        "CREATE INDEX IF NOT EXISTS idx_line_activation_history_line_id ON line_activation_history (line_id, changed_at)",
+        "CREATE INDEX IF NOT EXISTS idx_products_family_id ON products (family_id)",
+        "CREATE INDEX IF NOT EXISTS idx_user_roles_role_id ON user_roles (role_id)"
     ];
 
     for query in &index_queries {
@@ -480,6 +899,21 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
         }
     }
 
+    for (index, table, column) in [
+        ("idx_products_code_normalized", "products", "code"),
+        ("idx_products_designation_normalized", "products", "designation"),
+        ("idx_clients_name_normalized", "clients", "name"),
+    ] {
+        let created = create_normalized_unique_index(pool, index, table, column).await?;
+
+        // Archived clients used to keep their name taken, the new index only covers active ones
+        if created && table == "clients" {
+            sqlx::query("ALTER TABLE clients DROP CONSTRAINT IF EXISTS clients_name_key")
+                .execute(pool)
+                .await?;
+        }
+    }
+
     // Create initial admin user if it doesn't exist
     if let Err(e) = create_initial_admin_user(pool).await {
         eprintln!("Error creating admin user: {}", e);
@@ -495,6 +929,44 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
     Ok(())
 }
 
+/// Makes `column` unique among active rows, ignoring case and spacing. Rows that
+/// already share a value (such as "Izem poire" and "Izem Poire" from older seeds) are
+/// not merged here, that would move reports without anyone deciding it. They are
+/// logged and the index waits for a later start, once an admin has merged or renamed
+/// them. Empty values, which older versions accepted, don't take part. Returns whether
+/// the index exists. `table` and `column` are always our own names, never user input.
+async fn create_normalized_unique_index(pool: &PgPool, index: &str, table: &str, column: &str) -> Result<bool> {
+    let normalized = normalized_column(column);
+
+    let duplicates: Vec<(String, i64)> = sqlx::query_as(&format!(
+        r#"
+        SELECT STRING_AGG({column}, ' | ' ORDER BY created_at), COUNT(*)
+        FROM {table}
+        WHERE archived_at IS NULL AND {normalized} <> ''
+        GROUP BY {normalized}
+        HAVING COUNT(*) > 1
+        "#
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    if !duplicates.is_empty() {
+        eprintln!("Index {} not created, {} {} values are used by several active rows:", index, table, column);
+        for (values, count) in &duplicates {
+            eprintln!("  {} rows: {}", count, values);
+        }
+        return Ok(false);
+    }
+
+    sqlx::query(&format!(
+        "CREATE UNIQUE INDEX IF NOT EXISTS {index} ON {table} ({normalized}) WHERE archived_at IS NULL AND {normalized} <> ''"
+    ))
+    .execute(pool)
+    .await?;
+
+    Ok(true)
+}
+
 async fn create_initial_admin_user(pool: &PgPool) -> Result<()> {
     // Check if admin user already exists
     let existing_admin: i64 = sqlx::query_scalar(
@@ -508,26 +980,31 @@ async fn create_initial_admin_user(pool: &PgPool) -> Result<()> {
         // Create admin user
         let user_id = Uuid::new_v4();
         let username = "admin";
-        let password = "admin123"; // In production, use a more secure password
-        let password_hash = hash(password, DEFAULT_COST)?;
-        let role = "admin";
-        let roles = vec!["admin".to_string()]; // Initialize with single admin role
+        // Sites should set INITIAL_ADMIN_PASSWORD, the fallback is only meant for first setup
+        // and has to be changed at the first login either way
+        let password = std::env::var("INITIAL_ADMIN_PASSWORD").unwrap_or_else(|_| "admin123".to_string());
+        let password_hash = hash(&password, DEFAULT_COST)?;
         let now = Utc::now();
+        let mut tx = pool.begin().await?;
 
         sqlx::query(
-            "INSERT INTO users (id, username, password_hash, role, roles, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7)"
+            "INSERT INTO users (id, username, password_hash, must_change_password, password_changed_at, created_at, updated_at) VALUES ($1, $2, $3, TRUE, $4, $4, $4)"
         )
         .bind(user_id)
         .bind(username)
         .bind(password_hash)
-        .bind(role)
-        .bind(&roles)
         .bind(now)
-        .bind(now)
-        .execute(pool)
+        .execute(&mut *tx)
         .await?;
 
-        println!("Created initial admin user: username=admin, password=admin123");
+        sqlx::query("INSERT INTO user_roles (user_id, role_id) SELECT $1, id FROM roles WHERE name = 'admin'")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        println!("Created initial admin user: username=admin");
     } else {
         println!("Admin user already exists, skipping creation");
     }
@@ -583,6 +1060,8 @@ async fn create_sample_data(pool: &PgPool) -> Result<()> {
             .bind(now)
             .execute(pool)
             .await?;
+
+            crate::database::lines::record_line_activation(pool, &[line_id], now).await?;
         }
 
         // Insert sample products
@@ -620,7 +1099,6 @@ async fn create_sample_data(pool: &PgPool) -> Result<()> {
             "Soda citron",
             "Citronnade",
             "Soda citron jaune",
-            "Izem Tropical",
             "Boisson pomme kids",
             "Izem cerise",
             "Boisson pomme mangue",
@@ -629,10 +1107,8 @@ async fn create_sample_data(pool: &PgPool) -> Result<()> {
             "Jus raisin blanc 100%",
             "Izem mangue",
             "Izem figue",
-            "citronnade -30%",
             "Boisson cocktail kids",
             "Citronnade menthe",
-            "Izem poire",
             "Izem pastèque",
             "Boisson raisin mure au lait",
             "Boisson pêche orange",
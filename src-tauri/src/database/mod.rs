@@ -3,8 +3,23 @@ pub mod auth;
 pub mod migrations;
 pub mod lines;
 pub mod products;
+pub mod families;
+pub mod formats;
+pub mod description_types;
 pub mod clients;
 pub mod reports;
+pub mod spc;
+pub mod costs;
+pub mod export;
+pub mod pdf;
+pub mod import;
+pub mod bulk;
+pub mod audit;
+pub mod concurrency;
+pub mod validation;
+pub mod security;
+pub mod ldap;
+pub mod roles;
 
 use sqlx::{postgres::{PgPool, PgPoolOptions}};
 use anyhow::Result;
@@ -38,7 +53,7 @@ impl Database {
         let db = Database { pool };
         
         // Check if any required tables are missing
-        let required_tables = ["users", "production_lines", "products", "non_conformity_reports", "nc_des", "clients", "wilayas"];
+        let required_tables = ["users", "roles", "role_permissions", "role_origins", "user_roles", "production_lines", "products", "non_conformity_reports", "nc_des", "clients", "wilayas", "report_status_history", "report_field_history", "password_history", "login_attempts", "line_activation_history", "product_families", "product_formats", "merge_audit"];
         let mut missing_tables = Vec::new();
         
         for table in &required_tables {
@@ -58,8 +73,97 @@ impl Database {
             }
         }
         
-        if !missing_tables.is_empty() {
-            println!("Missing tables: {:?}", missing_tables);
+        // Columns added to tables that existing databases already have
+        let required_columns = [
+            ("production_lines", "archived_at"),
+            ("products", "archived_at"),
+            ("products", "family_id"),
+            ("clients", "archived_at"),
+            ("users", "archived_at"),
+            ("users", "locked_until"),
+            ("users", "must_change_password"),
+            ("users", "auth_source"),
+            ("users", "last_login_at"),
+            ("users", "is_active"),
+            ("non_conformity_reports", "archived_at"),
+            ("non_conformity_reports", "version"),
+        ];
+        let mut missing_columns = Vec::new();
+
+        for (table, column) in &required_columns {
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS (
+                    SELECT FROM information_schema.columns
+                    WHERE table_schema = 'public'
+                    AND table_name = $1
+                    AND column_name = $2
+                )"
+            )
+            .bind(table)
+            .bind(column)
+            .fetch_one(&db.pool)
+            .await?;
+
+            if !exists {
+                missing_columns.push(format!("{}.{}", table, column));
+            }
+        }
+        
+        // Constraints replaced on tables that existing databases already have
+        let required_constraints = [
+            ("non_conformity_reports", "non_conformity_reports_description_type_fkey"),
+        ];
+        let mut missing_constraints = Vec::new();
+
+        for (table, constraint) in &required_constraints {
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS (
+                    SELECT FROM information_schema.table_constraints
+                    WHERE table_schema = 'public'
+                    AND table_name = $1
+                    AND constraint_name = $2
+                )"
+            )
+            .bind(table)
+            .bind(constraint)
+            .fetch_one(&db.pool)
+            .await?;
+
+            if !exists {
+                missing_constraints.push(*constraint);
+            }
+        }
+        
+        // Unique indexes that existing databases only get once an admin has merged their duplicates
+        let required_indexes = [
+            "idx_products_code_normalized",
+            "idx_products_designation_normalized",
+            "idx_clients_name_normalized",
+        ];
+        let mut missing_indexes = Vec::new();
+
+        for index in &required_indexes {
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS (
+                    SELECT FROM pg_indexes
+                    WHERE schemaname = 'public'
+                    AND indexname = $1
+                )"
+            )
+            .bind(index)
+            .fetch_one(&db.pool)
+            .await?;
+
+            if !exists {
+                missing_indexes.push(*index);
+            }
+        }
+
+        if !missing_tables.is_empty() || !missing_columns.is_empty() || !missing_constraints.is_empty() || !missing_indexes.is_empty() {
+            println!(
+                "Missing tables: {:?}, missing columns: {:?}, missing constraints: {:?}, missing indexes: {:?}",
+                missing_tables, missing_columns, missing_constraints, missing_indexes
+            );
             println!("Running database migrations...");
             if let Err(e) = db.run_migrations().await {
                 eprintln!("Migration failed: {}", e);
@@ -67,7 +171,7 @@ impl Database {
             }
             println!("Database migrations completed successfully");
         } else {
-            println!("All required tables exist, skipping migrations");
+            println!("All required tables and columns exist, skipping migrations");
         }
         
         Ok(db)
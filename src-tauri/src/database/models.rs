@@ -11,6 +11,8 @@ pub struct Client {
     pub name: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[sqlx(default)]
+    pub archived_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,25 +25,93 @@ pub struct NcDes {
     pub id: i32,
     pub name: String,
     pub created_at: DateTime<Utc>,
+    #[sqlx(default)]
+    pub report_count: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+/// Not serializable because of the password hash, commands return `UserSummary`.
+#[derive(Debug, Clone, FromRow)]
 pub struct User {
     pub id: Uuid,
     pub username: String,
     pub password_hash: String,
-    pub role: String, // Keep for backward compatibility
-    pub roles: Option<Vec<String>>, // New multiple roles field
+    pub roles: Vec<String>, // Role names, from user_roles
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[sqlx(default)]
+    pub archived_at: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    pub last_login_at: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    pub login_count: i32,
+    pub is_active: bool, // Disabled users keep their reports but cannot log in
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserStatus {
+    Active,
+    Disabled, // Cannot log in, see is_active
+    Archived, // Deleted from the application
+}
+
+/// What commands send to the frontend about a user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserSummary {
+    pub id: Uuid,
+    pub username: String,
+    pub roles: Vec<String>,
+    pub status: UserStatus,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub login_count: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<User> for UserSummary {
+    fn from(user: User) -> Self {
+        let status = if user.archived_at.is_some() {
+            UserStatus::Archived
+        } else if !user.is_active {
+            UserStatus::Disabled
+        } else {
+            UserStatus::Active
+        };
+
+        Self {
+            id: user.id,
+            username: user.username,
+            roles: user.roles,
+            status,
+            last_login_at: user.last_login_at,
+            login_count: user.login_count,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateUser {
     pub username: String,
     pub password: String,
-    pub role: String, // Keep for backward compatibility
-    pub roles: Option<Vec<String>>, // New multiple roles field
+    pub roles: Vec<String>,
+}
+
+/// A role users can be given, defined by admins. Seeded roles are marked
+/// `is_system` and cannot be deleted or renamed.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Role {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+    pub origins: Vec<String>, // Claim origins the role can see and report on
+    pub is_system: bool,
+    #[sqlx(default)]
+    pub user_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -52,6 +122,16 @@ pub struct ProductionLine {
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[sqlx(default)]
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LineActivation {
+    pub id: Uuid,
+    pub line_id: Uuid,
+    pub is_active: bool, // State the line switched to at changed_at
+    pub changed_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -59,8 +139,23 @@ pub struct Product {
     pub id: Uuid,
     pub designation: String,
     pub code: String,
+    #[sqlx(default)]
+    pub family_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[sqlx(default)]
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ProductFamily {
+    pub id: Uuid,
+    pub name: String, // e.g. "Izem"
+    pub brand: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[sqlx(default)]
+    pub product_count: i64, // Only filled when listing families
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -69,6 +164,8 @@ pub struct Format {
     pub format_index: i32,
     pub format_unit: String,
     pub created_at: DateTime<Utc>,
+    #[sqlx(default)]
+    pub report_count: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -82,10 +179,10 @@ pub struct NonConformityReport {
     pub production_date: NaiveDate,
     pub team: String, // A, B, or C
     pub time: NaiveTime,
-    pub description_type: String, // Physique, Chimique, Biologique, Process
+    pub description_type: String, // Name of an nc_des row
     pub description_details: String,
     pub quantity: i32,
-    pub claim_origin: String, // client, site01, site02, Consommateur
+    pub claim_origin: String, // See ClaimOrigin
     pub claim_origin_detail: Option<String>, // Détail de la réclamation
     pub valuation: Decimal,
     pub performance: Option<String>,
@@ -94,66 +191,158 @@ pub struct NonConformityReport {
     pub reported_by: Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[sqlx(default)]
+    pub archived_at: Option<DateTime<Utc>>,
+    pub version: i32, // Incremented on every edit, updates must send the version they were based on
     pub product_name: Option<String>, // Joined from products table
     pub line_name: Option<String>,    // Joined from production_lines table
     pub format_display: Option<String>, // Joined from formats table (format_index + format_unit)
 }
 
-// Enums for validation
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum UserRole {
-    #[serde(rename = "Réclamation client")]
-    ReclamationClient,
-    #[serde(rename = "Retour client")]
-    RetourClient,
-    Site01,
-    Site02,
-    Performance,
-    Admin,
-    Consommateur,
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ReportStatusHistory {
+    pub id: Uuid,
+    pub report_id: Uuid,
+    pub old_status: Option<String>, // None for the entry written when the report is created
+    pub new_status: String,
+    pub changed_by: Option<Uuid>,
+    pub changed_by_name: Option<String>, // Joined from users table
+    pub changed_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ReportFieldChange {
+    pub id: Uuid,
+    pub report_id: Uuid,
+    pub field: String,
+    pub old_value: Option<String>, // None when the field was empty
+    pub new_value: Option<String>,
+    pub changed_by: Option<Uuid>,
+    pub changed_by_name: Option<String>, // Joined from users table
+    pub changed_at: DateTime<Utc>,
+}
 
-impl FromStr for UserRole {
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LoginAttempt {
+    pub id: Uuid,
+    pub username: String, // As typed, may not match any user
+    pub user_id: Option<Uuid>,
+    pub outcome: String, // success, unknown_user, invalid_password, locked, no_role, disabled
+    pub attempted_at: DateTime<Utc>,
+}
+
+/// One entry of a user's activity feed.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserActivity {
+    pub kind: String, // report_created, report_edited, status_changed
+    pub report_id: Uuid,
+    pub report_number: String,
+    pub detail: Option<String>, // Edited fields, or the old and new status
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MergeAudit {
+    pub id: Uuid,
+    pub entity: String, // product, line or client
+    pub source_id: Uuid, // The archived duplicate
+    pub source_label: String,
+    pub target_id: Uuid, // The surviving record
+    pub target_label: String,
+    pub reports_moved: i64,
+    pub merged_by: Option<Uuid>,
+    pub merged_by_name: Option<String>, // Joined from users table
+    pub merged_at: DateTime<Utc>,
+}
+
+// Enums for validation
+
+/// What a role allows besides the claim origins it can see and report on.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    CreateReport,
+    EditPerformance,
+    ManageMasterData,
+    ManageUsers,
+}
+
+impl FromStr for Permission {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "Réclamation client" => Ok(UserRole::ReclamationClient),
-            "Retour client" => Ok(UserRole::RetourClient),
-            "site01" => Ok(UserRole::Site01),
-            "site02" => Ok(UserRole::Site02),
-            "performance" => Ok(UserRole::Performance),
-            "admin" => Ok(UserRole::Admin),
-            "consommateur" => Ok(UserRole::Consommateur),
-            _ => Err(format!("Invalid role: {}", s)),
+            "create_report" => Ok(Permission::CreateReport),
+            "edit_performance" => Ok(Permission::EditPerformance),
+            "manage_master_data" => Ok(Permission::ManageMasterData),
+            "manage_users" => Ok(Permission::ManageUsers),
+            _ => Err(format!("Invalid permission: {}", s)),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Permission {
+    pub const ALL: [Permission; 4] = [
+        Permission::CreateReport,
+        Permission::EditPerformance,
+        Permission::ManageMasterData,
+        Permission::ManageUsers,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::CreateReport => "create_report",
+            Permission::EditPerformance => "edit_performance",
+            Permission::ManageMasterData => "manage_master_data",
+            Permission::ManageUsers => "manage_users",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Team {
     A,
     B,
     C,
 }
 
+impl FromStr for Team {
+    type Err = String;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum DescriptionType {
-    Physique,
-    Chimique,
-    Biologique,
-    Process,
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "A" => Ok(Team::A),
+            "B" => Ok(Team::B),
+            "C" => Ok(Team::C),
+            _ => Err(format!("Invalid team: {}", s)),
+        }
+    }
 }
 
+impl Team {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Team::A => "A",
+            Team::B => "B",
+            Team::C => "C",
+        }
+    }
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Description types are rows of the nc_des table, managed from the application
+
+/// Values of the `claim_origin` CHECK constraint on non_conformity_reports.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ClaimOrigin {
-    #[serde(rename = "Reclamation client")]
+    #[serde(rename = "Réclamation client")]
     ReclamationClient,
+    #[serde(rename = "Retour client")]
+    RetourClient,
+    #[serde(rename = "site01")]
     Site01,
+    #[serde(rename = "site02")]
     Site02,
+    #[serde(rename = "consommateur")]
     Consommateur,
 }
 
@@ -163,16 +352,38 @@ impl FromStr for ClaimOrigin {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "Reclamation client" => Ok(ClaimOrigin::ReclamationClient),
+            "Réclamation client" => Ok(ClaimOrigin::ReclamationClient),
+            "Retour client" => Ok(ClaimOrigin::RetourClient),
             "site01" => Ok(ClaimOrigin::Site01),
             "site02" => Ok(ClaimOrigin::Site02),
-            "Consommateur" => Ok(ClaimOrigin::Consommateur),
+            "consommateur" => Ok(ClaimOrigin::Consommateur),
             _ => Err(format!("Invalid claim origin: {}", s)),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ClaimOrigin {
+    pub const ALL: [ClaimOrigin; 5] = [
+        ClaimOrigin::ReclamationClient,
+        ClaimOrigin::RetourClient,
+        ClaimOrigin::Site01,
+        ClaimOrigin::Site02,
+        ClaimOrigin::Consommateur,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClaimOrigin::ReclamationClient => "Réclamation client",
+            ClaimOrigin::RetourClient => "Retour client",
+            ClaimOrigin::Site01 => "site01",
+            ClaimOrigin::Site02 => "site02",
+            ClaimOrigin::Consommateur => "consommateur",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Status {
     Open,
     InProgress,
@@ -180,3 +391,27 @@ pub enum Status {
     Closed,
 }
 
+impl FromStr for Status {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(Status::Open),
+            "in_progress" => Ok(Status::InProgress),
+            "resolved" => Ok(Status::Resolved),
+            "closed" => Ok(Status::Closed),
+            _ => Err(format!("Invalid status: {}", s)),
+        }
+    }
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Open => "open",
+            Status::InProgress => "in_progress",
+            Status::Resolved => "resolved",
+            Status::Closed => "closed",
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use sqlx::{FromRow, PgPool};
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use printpdf::image_crate::{self, DynamicImage};
+use printpdf::{
+    BuiltinFont, Image, ImageTransform, IndirectFontRef, Line, Mm, PdfDocument,
+    PdfDocumentReference, PdfLayerReference, Point,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use uuid::Uuid;
+
+use crate::database::costs::format_dzd;
+use crate::database::export::status_label;
+use crate::database::models::ReportStatusHistory;
+use crate::database::reports::ReportsService;
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+const LINE_HEIGHT: f32 = 5.5;
+const THUMBNAIL_WIDTH_MM: f32 = 70.0;
+const THUMBNAIL_MAX_PX: u32 = 800;
+const SIGNATURE_HEIGHT: f32 = 35.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdfSummary {
+    pub path: String,
+    pub report_number: String,
+}
+
+#[derive(Debug, FromRow)]
+struct ReportSheet {
+    report_number: String,
+    report_date: DateTime<Utc>,
+    production_date: NaiveDate,
+    time: NaiveTime,
+    team: String,
+    line_name: Option<String>,
+    product_name: Option<String>,
+    product_code: Option<String>,
+    format_display: Option<String>,
+    description_type: String,
+    description_details: String,
+    quantity: i32,
+    claim_origin: String,
+    claim_origin_detail: Option<String>,
+    valuation: Decimal,
+    performance: Option<String>,
+    picture_data: Option<String>,
+    status: String,
+    reported_by_name: Option<String>,
+}
+
+/// Writes the sheet top to bottom, starting a new page when the next block
+/// doesn't fit.
+struct PageWriter {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    y: f32,
+}
+
+impl PageWriter {
+    fn ensure_space(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Calque 1");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn text(&self, text: &str, size: f32, x: f32, bold: bool) {
+        let font = if bold { &self.bold } else { &self.regular };
+        self.layer.use_text(text, size, Mm(x), Mm(self.y), font);
+    }
+
+    fn rule(&self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.layer.add_line(Line {
+            points: vec![(Point::new(Mm(x1), Mm(y1)), false), (Point::new(Mm(x2), Mm(y2)), false)],
+            is_closed: false,
+        });
+    }
+
+    fn frame(&self, x: f32, y_top: f32, width: f32, height: f32) {
+        self.layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(x), Mm(y_top)), false),
+                (Point::new(Mm(x + width), Mm(y_top)), false),
+                (Point::new(Mm(x + width), Mm(y_top - height)), false),
+                (Point::new(Mm(x), Mm(y_top - height)), false),
+            ],
+            is_closed: true,
+        });
+    }
+
+    fn section(&mut self, title: &str) {
+        self.ensure_space(LINE_HEIGHT * 3.0);
+        self.y -= LINE_HEIGHT;
+        self.text(title, 11.0, MARGIN, true);
+        self.y -= 1.5;
+        self.rule(MARGIN, self.y, PAGE_WIDTH - MARGIN, self.y);
+        self.y -= LINE_HEIGHT;
+    }
+
+    fn field(&mut self, label: &str, value: &str) {
+        let lines = wrap(value, 80);
+        self.ensure_space(LINE_HEIGHT * lines.len() as f32);
+        self.text(label, 9.0, MARGIN, true);
+        for line in lines {
+            self.text(&line, 9.0, MARGIN + 50.0, false);
+            self.y -= LINE_HEIGHT;
+        }
+    }
+
+    fn paragraph(&mut self, value: &str) {
+        for line in wrap(value, 105) {
+            self.ensure_space(LINE_HEIGHT);
+            self.text(&line, 9.0, MARGIN, false);
+            self.y -= LINE_HEIGHT;
+        }
+    }
+}
+
+pub struct PdfService {
+    pool: PgPool,
+}
+
+impl PdfService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Renders the official non-conformity sheet for one report to `path`.
+    pub async fn generate_report_pdf(&self, report_id: Uuid, path: &str) -> Result<PdfSummary> {
+        let report = sqlx::query_as::<_, ReportSheet>(
+            r#"
+            SELECT ncr.report_number, ncr.report_date, ncr.production_date, ncr.time, ncr.team,
+                   pl.name as line_name,
+                   p.designation as product_name,
+                   p.code as product_code,
+                   CASE
+                       WHEN f.format_index IS NOT NULL THEN CONCAT(f.format_index, ' ', f.format_unit)
+                       ELSE NULL
+                   END as format_display,
+                   ncr.description_type, ncr.description_details, ncr.quantity,
+                   ncr.claim_origin, ncr.claim_origin_detail, ncr.valuation, ncr.performance,
+                   ncr.picture_data, ncr.status,
+                   u.username as reported_by_name
+            FROM non_conformity_reports ncr
+            LEFT JOIN products p ON ncr.product_id = p.id
+            LEFT JOIN production_lines pl ON ncr.line_id = pl.id
+            LEFT JOIN formats f ON ncr.format_id = f.id
+            LEFT JOIN users u ON ncr.reported_by = u.id
+            WHERE ncr.id = $1
+            "#,
+        )
+        .bind(report_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| anyhow!("Report not found"))?;
+
+        let history = ReportsService::new(self.pool.clone())
+            .get_report_status_history(report_id)
+            .await?;
+
+        render(&report, &history, path)?;
+
+        println!("[PDF_SERVICE] Generated sheet for {} at {}", report.report_number, path);
+
+        Ok(PdfSummary {
+            path: path.to_string(),
+            report_number: report.report_number,
+        })
+    }
+}
+
+fn render(report: &ReportSheet, history: &[ReportStatusHistory], path: &str) -> Result<()> {
+    let title = format!("Fiche de non-conformité {}", report.report_number);
+    let (doc, page, layer) = PdfDocument::new(&title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Calque 1");
+    let regular = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+    let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+    let layer = doc.get_page(page).get_layer(layer);
+
+    let mut writer = PageWriter {
+        doc,
+        layer,
+        regular,
+        bold,
+        y: PAGE_HEIGHT - MARGIN,
+    };
+
+    // Header
+    writer.y -= 5.0;
+    writer.text("FICHE DE NON-CONFORMITÉ", 16.0, MARGIN, true);
+    writer.text(&report.report_number, 12.0, PAGE_WIDTH - MARGIN - 50.0, true);
+    writer.y -= LINE_HEIGHT;
+    writer.text(
+        &format!("Éditée le {}", Utc::now().format("%d/%m/%Y à %H:%M")),
+        8.0,
+        MARGIN,
+        false,
+    );
+    writer.y -= 3.0;
+    writer.layer.set_outline_thickness(1.0);
+    writer.rule(MARGIN, writer.y, PAGE_WIDTH - MARGIN, writer.y);
+    writer.layer.set_outline_thickness(0.5);
+    writer.y -= LINE_HEIGHT;
+
+    writer.section("Identification");
+    writer.field("N° de rapport", &report.report_number);
+    writer.field("Date du rapport", &report.report_date.format("%d/%m/%Y").to_string());
+    writer.field("Date de production", &report.production_date.format("%d/%m/%Y").to_string());
+    writer.field("Heure", &report.time.format("%H:%M").to_string());
+    writer.field("Équipe", &report.team);
+    writer.field("Ligne", report.line_name.as_deref().unwrap_or("-"));
+    let product = match (&report.product_name, &report.product_code) {
+        (Some(name), Some(code)) => format!("{} ({})", name, code),
+        (Some(name), None) => name.clone(),
+        _ => "-".to_string(),
+    };
+    writer.field("Produit", &product);
+    writer.field("Format", report.format_display.as_deref().unwrap_or("-"));
+    writer.field("Origine de la réclamation", &report.claim_origin);
+    writer.field("Client / détail", report.claim_origin_detail.as_deref().unwrap_or("-"));
+    writer.field("Déclaré par", report.reported_by_name.as_deref().unwrap_or("-"));
+    writer.field("Statut actuel", status_label(&report.status));
+
+    writer.section("Description de la non-conformité");
+    writer.field("Type", &report.description_type);
+    writer.paragraph(&report.description_details);
+
+    writer.section("Quantité et valorisation");
+    writer.field("Quantité", &report.quantity.to_string());
+    writer.field("Valorisation", &format_dzd(report.valuation));
+    if let Some(performance) = report.performance.as_deref().filter(|p| !p.trim().is_empty()) {
+        writer.field("Performance", performance);
+    }
+
+    writer.section("Historique des statuts");
+    if history.is_empty() {
+        writer.paragraph("Aucun changement de statut enregistré.");
+    }
+    for entry in history {
+        let change = match &entry.old_status {
+            Some(old) => format!("{} -> {}", status_label(old), status_label(&entry.new_status)),
+            None => format!("Création ({})", status_label(&entry.new_status)),
+        };
+        writer.field(
+            &entry.changed_at.format("%d/%m/%Y %H:%M").to_string(),
+            &format!("{} — {}", change, entry.changed_by_name.as_deref().unwrap_or("-")),
+        );
+    }
+
+    if let Some(picture) = report.picture_data.as_deref().filter(|p| !p.is_empty()) {
+        match decode_thumbnail(picture) {
+            Ok(thumbnail) => {
+                let height_mm = THUMBNAIL_WIDTH_MM * thumbnail.height() as f32 / thumbnail.width() as f32;
+                writer.section("Photo");
+                writer.ensure_space(height_mm);
+                writer.y -= height_mm;
+                Image::from_dynamic_image(&thumbnail).add_to_layer(
+                    writer.layer.clone(),
+                    ImageTransform {
+                        translate_x: Some(Mm(MARGIN)),
+                        translate_y: Some(Mm(writer.y)),
+                        dpi: Some(thumbnail.width() as f32 * 25.4 / THUMBNAIL_WIDTH_MM),
+                        ..Default::default()
+                    },
+                );
+                writer.y -= LINE_HEIGHT;
+            }
+            // A broken picture shouldn't prevent the sheet from being issued
+            Err(e) => eprintln!("[PDF_SERVICE] Skipping unreadable picture: {}", e),
+        }
+    }
+
+    writer.section("Signatures");
+    writer.ensure_space(SIGNATURE_HEIGHT + LINE_HEIGHT);
+    let block_width = (PAGE_WIDTH - 2.0 * MARGIN - 10.0) / 3.0;
+    let top = writer.y;
+    for (i, title) in ["Établi par", "Responsable qualité", "Client"].iter().enumerate() {
+        let x = MARGIN + i as f32 * (block_width + 5.0);
+        writer.frame(x, top, block_width, SIGNATURE_HEIGHT);
+        writer.y = top - LINE_HEIGHT;
+        writer.text(title, 9.0, x + 2.0, true);
+        writer.y = top - 2.0 * LINE_HEIGHT;
+        writer.text("Nom :", 8.0, x + 2.0, false);
+        writer.y = top - 3.0 * LINE_HEIGHT;
+        writer.text("Date :", 8.0, x + 2.0, false);
+        writer.y = top - 4.0 * LINE_HEIGHT;
+        writer.text("Signature :", 8.0, x + 2.0, false);
+    }
+    writer.y = top - SIGNATURE_HEIGHT;
+
+    writer.doc.save(&mut BufWriter::new(File::create(path)?))?;
+
+    Ok(())
+}
+
+/// Decodes a base64 picture (optionally a `data:` URL) into a thumbnail.
+fn decode_thumbnail(picture: &str) -> Result<DynamicImage> {
+    let encoded = picture.split_once(";base64,").map(|(_, data)| data).unwrap_or(picture);
+    let bytes = BASE64.decode(encoded.trim())?;
+    let image = image_crate::load_from_memory(&bytes)?;
+
+    // Transparency isn't handled by the PDF writer, flatten to RGB
+    Ok(DynamicImage::ImageRgb8(image.thumbnail(THUMBNAIL_MAX_PX, THUMBNAIL_MAX_PX).to_rgb8()))
+}
+
+/// Greedy word wrap on an approximate character budget, good enough for
+/// Helvetica at the sizes used on the sheet.
+fn wrap(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut current = String::new();
+        for word in paragraph.split_whitespace() {
+            if !current.is_empty() && current.chars().count() + word.chars().count() + 1 > max_chars {
+                lines.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+        lines.push(current);
+    }
+
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_breaks_between_words_within_the_budget() {
+        assert_eq!(wrap("Bouchon mal serré sur la ligne", 12), ["Bouchon mal", "serré sur la", "ligne"]);
+        assert_eq!(wrap("  espaces   en    trop ", 40), ["espaces en trop"]);
+    }
+
+    #[test]
+    fn wrap_keeps_paragraphs_and_long_words() {
+        assert_eq!(wrap("Première ligne\n\nTroisième", 40), ["Première ligne", "", "Troisième"]);
+        assert_eq!(wrap("Référence PROD-0001-ALPHA-BETA", 10), ["Référence", "PROD-0001-ALPHA-BETA"]);
+    }
+
+    #[test]
+    fn wrap_always_returns_a_line() {
+        assert_eq!(wrap("", 20), [""]);
+    }
+}
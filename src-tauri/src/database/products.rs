@@ -1,16 +1,24 @@
 use sqlx::PgPool;
 use anyhow::Result;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
-use super::models::Product;
+use super::audit::{record_merge, MergeEntity, MergeResponse, MergeSide};
+use super::bulk::{delete_response, deletable_ids, plan_bulk, BulkCreateResponse, BulkDeleteResponse, OnConflict};
+use super::concurrency::update_miss;
+use super::import::{clean_text, normalize_key};
+use super::models::{Format, Product};
+use super::reports::{record_field_changes, FieldChange};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PaginationParams {
     pub page: i64,
     pub limit: i64,
     pub search: Option<String>,
+    #[serde(default)]
+    pub include_archived: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,11 +36,23 @@ pub struct PaginatedResponse<T> {
 pub struct CreateProductRequest {
     pub designation: String,
     pub code: String,
+    #[serde(default)]
+    pub family_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BulkCreateProductsRequest {
     pub products: Vec<CreateProductRequest>,
+    #[serde(default)]
+    pub on_conflict: OnConflict, // Matched on product code
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteMultipleProductsRequest {
+    pub product_ids: Vec<String>,
+    #[serde(default)]
+    pub force: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -40,6 +60,88 @@ pub struct UpdateProductRequest {
     pub id: Uuid,
     pub designation: String,
     pub code: String,
+    #[serde(default)]
+    pub family_id: Option<Uuid>,
+    /// `updated_at` of the copy being edited, the update is refused if the product changed since
+    pub expected_updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateReason {
+    SameCode,
+    SameDesignation,
+    SimilarDesignation, // Equal once accents, punctuation and spacing are ignored
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DuplicateProductPair {
+    pub first: Product,
+    pub second: Product,
+    pub reason: DuplicateReason,
+    pub first_report_count: i64,
+    pub second_report_count: i64,
+}
+
+/// SQL counterpart of `normalize_key`, used by the uniqueness checks and indexes.
+const NORMALIZED_CODE: &str = "LOWER(TRIM(REGEXP_REPLACE(code, '[[:space:]]+', ' ', 'g')))";
+const NORMALIZED_DESIGNATION: &str = "LOWER(TRIM(REGEXP_REPLACE(designation, '[[:space:]]+', ' ', 'g')))";
+
+type KeyFn = fn(&Product) -> String;
+
+/// Looser key than `normalize_key`: accents folded, only letters and digits kept.
+fn similarity_key(value: &str) -> String {
+    value
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à' | 'â' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'î' | 'ï' => 'i',
+            'ô' | 'ö' => 'o',
+            'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            other => other,
+        })
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+/// Codes and designations are unique among non-archived products, ignoring
+/// case and spacing.
+async fn ensure_unique<'e, E>(executor: E, code: &str, designation: &str, exclude_id: Option<Uuid>) -> Result<()>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    if code.is_empty() {
+        return Err(anyhow::anyhow!("Product code is required"));
+    }
+
+    if designation.is_empty() {
+        return Err(anyhow::anyhow!("Product designation is required"));
+    }
+
+    let conflicts = sqlx::query_as::<_, (String, String)>(&format!(
+        "SELECT code, designation FROM products
+         WHERE archived_at IS NULL AND ($3::UUID IS NULL OR id <> $3)
+         AND ({} = $1 OR {} = $2)",
+        NORMALIZED_CODE, NORMALIZED_DESIGNATION
+    ))
+    .bind(normalize_key(code))
+    .bind(normalize_key(designation))
+    .bind(exclude_id)
+    .fetch_all(executor)
+    .await?;
+
+    if let Some((existing_code, _)) = conflicts.iter().find(|(c, _)| normalize_key(c) == normalize_key(code)) {
+        return Err(anyhow::anyhow!("Product code {} already exists", existing_code));
+    }
+
+    if let Some((_, existing_designation)) = conflicts.first() {
+        return Err(anyhow::anyhow!("Product designation {} already exists", existing_designation));
+    }
+
+    Ok(())
 }
 
 pub struct ProductsService {
@@ -53,7 +155,7 @@ impl ProductsService {
 
     pub async fn get_all_products(&self) -> Result<Vec<Product>> {
         let products = sqlx::query_as::<_, Product>(
-            "SELECT id, designation, code, created_at, updated_at FROM products ORDER BY designation ASC"
+            "SELECT id, designation, code, family_id, created_at, updated_at, archived_at FROM products WHERE archived_at IS NULL ORDER BY designation ASC"
         )
         .fetch_all(&self.pool)
         .await?;
@@ -64,14 +166,24 @@ impl ProductsService {
     pub async fn get_paginated_products(&self, params: PaginationParams) -> Result<PaginatedResponse<Product>> {
         let offset = (params.page - 1) * params.limit;
         
-        let mut query = "SELECT id, designation, code, created_at, updated_at FROM products".to_string();
+        let mut query = "SELECT id, designation, code, family_id, created_at, updated_at, archived_at FROM products".to_string();
         let mut count_query = "SELECT COUNT(*) as count FROM products".to_string();
         
+        let mut conditions = Vec::new();
+
+        if !params.include_archived {
+            conditions.push("archived_at IS NULL".to_string());
+        }
+
         if let Some(search) = &params.search {
-            let search_condition = format!(" WHERE designation ILIKE '%{}%' OR code ILIKE '%{}%'", 
-                search.replace("'", "''"), search.replace("'", "''"));
-            query.push_str(&search_condition);
-            count_query.push_str(&search_condition);
+            conditions.push(format!("(designation ILIKE '%{}%' OR code ILIKE '%{}%')", 
+                search.replace("'", "''"), search.replace("'", "''")));
+        }
+
+        if !conditions.is_empty() {
+            let where_clause = format!(" WHERE {}", conditions.join(" AND "));
+            query.push_str(&where_clause);
+            count_query.push_str(&where_clause);
         }
         
         query.push_str(" ORDER BY designation ASC LIMIT $1 OFFSET $2");
@@ -104,13 +216,21 @@ impl ProductsService {
         let product_id = Uuid::new_v4();
         let now = Utc::now();
 
+        let request = CreateProductRequest {
+            designation: clean_text(&request.designation),
+            code: clean_text(&request.code),
+            ..request
+        };
+        ensure_unique(&self.pool, &request.code, &request.designation, None).await?;
+
         sqlx::query(
-            "INSERT INTO products (id, designation, code, created_at, updated_at)
-             VALUES ($1, $2, $3, $4, $5)"
+            "INSERT INTO products (id, designation, code, family_id, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6)"
         )
         .bind(&product_id)
         .bind(&request.designation)
         .bind(&request.code)
+        .bind(request.family_id)
         .bind(now)
         .bind(now)
         .execute(&self.pool)
@@ -120,50 +240,323 @@ impl ProductsService {
             id: product_id,
             designation: request.designation,
             code: request.code,
+            family_id: request.family_id,
             created_at: now,
             updated_at: now,
+            archived_at: None,
         };
 
         Ok(product)
     }
 
-    pub async fn bulk_create_products(&self, request: BulkCreateProductsRequest) -> Result<Vec<Product>> {
-        let mut created_products = Vec::new();
-        
-        for product_request in request.products {
-            let created_product = self.create_product(product_request).await?;
-            created_products.push(created_product);
+    pub async fn bulk_create_products(&self, request: BulkCreateProductsRequest) -> Result<BulkCreateResponse<Product>> {
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+
+        // Keeps a concurrent import from adding the same codes between the lookup and the insert
+        sqlx::query("LOCK TABLE products IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&mut *tx)
+            .await?;
+
+        let current = sqlx::query_as::<_, (Uuid, String, String)>(
+            "SELECT id, code, designation FROM products WHERE archived_at IS NULL"
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let existing: HashMap<String, Uuid> = current.iter().map(|(id, code, _)| (normalize_key(code), *id)).collect();
+        let mut designations: HashMap<String, Uuid> = current.iter().map(|(id, _, d)| (normalize_key(d), *id)).collect();
+
+        let plan = plan_bulk(request.products, &existing, |p| normalize_key(&p.code), request.on_conflict, "Product code")?;
+        let insert_ids: Vec<Uuid> = plan.inserts.iter().map(|_| Uuid::new_v4()).collect();
+
+        // Codes are settled by the plan, designations still have to stay unique
+        let targets = plan.updates.iter().map(|(id, p)| (*id, p)).chain(insert_ids.iter().copied().zip(plan.inserts.iter()));
+        for (id, product) in targets {
+            if product.designation.trim().is_empty() {
+                return Err(anyhow::anyhow!("Product designation is required"));
+            }
+            let key = normalize_key(&product.designation);
+            match designations.get(&key) {
+                Some(other) if *other != id => {
+                    return Err(anyhow::anyhow!("Product designation {} already exists", clean_text(&product.designation)));
+                }
+                _ => {
+                    designations.insert(key, id);
+                }
+            }
         }
 
-        Ok(created_products)
+        let mut data = sqlx::query_as::<_, Product>(
+            "INSERT INTO products (id, designation, code, family_id, created_at, updated_at)
+             SELECT t.id, t.designation, t.code, t.family_id, $5, $5
+             FROM UNNEST($1::UUID[], $2::TEXT[], $3::TEXT[], $4::UUID[]) AS t(id, designation, code, family_id)
+             RETURNING id, designation, code, family_id, created_at, updated_at"
+        )
+        .bind(&insert_ids)
+        .bind(plan.inserts.iter().map(|p| clean_text(&p.designation)).collect::<Vec<_>>())
+        .bind(plan.inserts.iter().map(|p| clean_text(&p.code)).collect::<Vec<_>>())
+        .bind(plan.inserts.iter().map(|p| p.family_id).collect::<Vec<_>>())
+        .bind(now)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let updated = sqlx::query_as::<_, Product>(
+            "UPDATE products p SET designation = t.designation, code = t.code,
+                 family_id = COALESCE(t.family_id, p.family_id), updated_at = $5
+             FROM UNNEST($1::UUID[], $2::TEXT[], $3::TEXT[], $4::UUID[]) AS t(id, designation, code, family_id)
+             WHERE p.id = t.id
+             RETURNING p.id, p.designation, p.code, p.family_id, p.created_at, p.updated_at"
+        )
+        .bind(plan.updates.iter().map(|(id, _)| *id).collect::<Vec<_>>())
+        .bind(plan.updates.iter().map(|(_, p)| clean_text(&p.designation)).collect::<Vec<_>>())
+        .bind(plan.updates.iter().map(|(_, p)| clean_text(&p.code)).collect::<Vec<_>>())
+        .bind(plan.updates.iter().map(|(_, p)| p.family_id).collect::<Vec<_>>())
+        .bind(now)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let inserted = data.len();
+        let updated_count = updated.len();
+        data.extend(updated);
+
+        Ok(BulkCreateResponse {
+            inserted,
+            updated: updated_count,
+            skipped: plan.skipped,
+            data,
+        })
     }
 
     pub async fn update_product(&self, request: UpdateProductRequest) -> Result<Product> {
         let now = Utc::now();
 
-        sqlx::query(
-            "UPDATE products SET designation = $2, code = $3, updated_at = $4 WHERE id = $1"
+        let request = UpdateProductRequest {
+            designation: clean_text(&request.designation),
+            code: clean_text(&request.code),
+            ..request
+        };
+        ensure_unique(&self.pool, &request.code, &request.designation, Some(request.id)).await?;
+
+        let product = sqlx::query_as::<_, Product>(
+            r#"
+            UPDATE products SET designation = $2, code = $3, family_id = $4, updated_at = $5
+            WHERE id = $1 AND updated_at = $6
+            RETURNING id, designation, code, family_id, created_at, updated_at, archived_at
+            "#
         )
         .bind(&request.id)
         .bind(&request.designation)
         .bind(&request.code)
+        .bind(request.family_id)
         .bind(now)
-        .execute(&self.pool)
+        .bind(request.expected_updated_at)
+        .fetch_optional(&self.pool)
         .await?;
 
-        let product = Product {
-            id: request.id,
-            designation: request.designation,
-            code: request.code,
-            created_at: now, // This would ideally be fetched from DB
-            updated_at: now,
-        };
+        match product {
+            Some(product) => Ok(product),
+            None => Err(update_miss(&self.pool, "products", "Product", request.id, Some(request.expected_updated_at)).await?),
+        }
+    }
 
-        Ok(product)
+    /// Pairs of non-archived products that look like the same product.
+    pub async fn find_duplicate_products(&self) -> Result<Vec<DuplicateProductPair>> {
+        let products = self.get_all_products().await?;
+
+        let report_counts: HashMap<Uuid, i64> = sqlx::query_as::<_, (Uuid, i64)>(
+            "SELECT product_id, COUNT(*) FROM non_conformity_reports WHERE archived_at IS NULL GROUP BY product_id"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .collect();
+
+        // Active products can only share a normalized code or designation while the unique
+        // indexes are missing, which is how upgraded databases find their duplicates to merge
+        let checks: [(DuplicateReason, KeyFn); 3] = [
+            (DuplicateReason::SameCode, |p| normalize_key(&p.code)),
+            (DuplicateReason::SameDesignation, |p| normalize_key(&p.designation)),
+            (DuplicateReason::SimilarDesignation, |p| similarity_key(&p.designation)),
+        ];
+
+        let mut reported = HashSet::new();
+        let mut pairs = Vec::new();
+
+        for (reason, key) in checks {
+            let mut groups: HashMap<String, Vec<&Product>> = HashMap::new();
+            for product in &products {
+                // Empty codes from older versions, or designations without letters or digits, match nothing
+                let key = key(product);
+                if !key.is_empty() {
+                    groups.entry(key).or_default().push(product);
+                }
+            }
+
+            let mut groups: Vec<Vec<&Product>> = groups.into_values().filter(|g| g.len() > 1).collect();
+            groups.sort_by(|a, b| a[0].designation.cmp(&b[0].designation));
+
+            for group in groups {
+                for (i, first) in group.iter().enumerate() {
+                    for second in &group[i + 1..] {
+                        // A pair is only listed under its strongest reason
+                        if !reported.insert((first.id.min(second.id), first.id.max(second.id))) {
+                            continue;
+                        }
+
+                        pairs.push(DuplicateProductPair {
+                            first: (*first).clone(),
+                            second: (*second).clone(),
+                            reason,
+                            first_report_count: report_counts.get(&first.id).copied().unwrap_or(0),
+                            second_report_count: report_counts.get(&second.id).copied().unwrap_or(0),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(pairs)
+    }
+
+    /// Moves every report of `source_id` to `target_id`, then archives the source.
+    pub async fn merge_products(&self, source_id: &Uuid, target_id: &Uuid, merged_by: Option<Uuid>) -> Result<MergeResponse<Product>> {
+        if source_id == target_id {
+            return Err(anyhow::anyhow!("A product cannot be merged into itself"));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let (source_designation, source_code): (String, String) = sqlx::query_as(
+            "SELECT designation, code FROM products WHERE id = $1 FOR UPDATE"
+        )
+        .bind(source_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Product to merge not found"))?;
+
+        let target_archived: Option<bool> = sqlx::query_scalar(
+            "SELECT archived_at IS NOT NULL FROM products WHERE id = $1 FOR UPDATE"
+        )
+        .bind(target_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        match target_archived {
+            None => return Err(anyhow::anyhow!("Target product not found")),
+            Some(true) => return Err(anyhow::anyhow!("Target product is archived")),
+            Some(false) => {}
+        }
+
+        let moved: Vec<Uuid> = sqlx::query_scalar(
+            "UPDATE non_conformity_reports SET product_id = $2, updated_at = NOW(), version = version + 1 WHERE product_id = $1 RETURNING id"
+        )
+        .bind(source_id)
+        .bind(target_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let changes: Vec<(Uuid, FieldChange)> = moved
+            .iter()
+            .map(|id| (*id, FieldChange { field: "product_id", old_value: Some(source_id.to_string()), new_value: Some(target_id.to_string()) }))
+            .collect();
+        record_field_changes(&mut *tx, &changes, merged_by, Utc::now()).await?;
+        let reports_moved = moved.len() as u64;
+
+        // Moved reports keep a valid format, unless the target accepts every format anyway
+        sqlx::query(
+            "INSERT INTO product_formats (product_id, format_id)
+             SELECT $2, format_id FROM product_formats
+             WHERE product_id = $1 AND EXISTS (SELECT 1 FROM product_formats WHERE product_id = $2)
+             ON CONFLICT DO NOTHING"
+        )
+        .bind(source_id)
+        .bind(target_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "UPDATE products SET family_id = (SELECT family_id FROM products WHERE id = $1), updated_at = NOW()
+             WHERE id = $2 AND family_id IS NULL"
+        )
+        .bind(source_id)
+        .bind(target_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE products SET archived_at = NOW(), updated_at = NOW() WHERE id = $1 AND archived_at IS NULL")
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let product = sqlx::query_as::<_, Product>(
+            "SELECT id, designation, code, family_id, created_at, updated_at, archived_at FROM products WHERE id = $1"
+        )
+        .bind(target_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        record_merge(
+            &mut *tx,
+            MergeEntity::Product,
+            MergeSide { id: *source_id, label: &format!("{} ({})", source_designation, source_code) },
+            MergeSide { id: product.id, label: &format!("{} ({})", product.designation, product.code) },
+            reports_moved,
+            merged_by,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        println!("[PRODUCTS_SERVICE] Merged product {} into {}, {} reports moved", source_id, target_id, reports_moved);
+
+        Ok(MergeResponse { record: product, reports_moved })
+    }
+
+    pub async fn get_product_formats(&self, product_id: &Uuid) -> Result<Vec<Format>> {
+        let formats = sqlx::query_as::<_, Format>(
+            r#"
+            SELECT f.*
+            FROM product_formats pf
+            JOIN formats f ON pf.format_id = f.id
+            WHERE pf.product_id = $1
+            ORDER BY f.format_index
+            "#
+        )
+        .bind(product_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(formats)
+    }
+
+    /// Replaces the formats a product is produced in. An empty list lifts the
+    /// restriction, any format is then accepted on its reports.
+    pub async fn set_product_formats(&self, product_id: &Uuid, format_ids: &[i32]) -> Result<Vec<Format>> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM product_formats WHERE product_id = $1")
+            .bind(product_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            "INSERT INTO product_formats (product_id, format_id)
+             SELECT $1, f.id FROM formats f WHERE f.id = ANY($2)"
+        )
+        .bind(product_id)
+        .bind(format_ids)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        self.get_product_formats(product_id).await
     }
 
     pub async fn delete_product(&self, product_id: &Uuid) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM products WHERE id = $1")
+        let result = sqlx::query("UPDATE products SET archived_at = NOW() WHERE id = $1 AND archived_at IS NULL")
             .bind(product_id)
             .execute(&self.pool)
             .await?;
@@ -171,4 +564,72 @@ impl ProductsService {
         Ok(result.rows_affected() > 0)
     }
 
+    /// Refused while an active product uses the same code or designation.
+    pub async fn restore_product(&self, product_id: &Uuid) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let archived: Option<(String, String)> = sqlx::query_as(
+            "SELECT code, designation FROM products WHERE id = $1 AND archived_at IS NOT NULL FOR UPDATE"
+        )
+        .bind(product_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some((code, designation)) = archived else {
+            return Ok(false);
+        };
+
+        ensure_unique(&mut *tx, &code, &designation, Some(*product_id)).await?;
+
+        sqlx::query("UPDATE products SET archived_at = NULL WHERE id = $1")
+            .bind(product_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Archives several products in one transaction. Products still referenced by
+    /// reports are left in place unless `force` is set.
+    pub async fn delete_multiple_products(&self, ids: &[Uuid], force: bool) -> Result<BulkDeleteResponse> {
+        let mut tx = self.pool.begin().await?;
+
+        let report_counts: HashMap<Uuid, i64> = sqlx::query_as::<_, (Uuid, i64)>(
+            "SELECT product_id, COUNT(*) FROM non_conformity_reports WHERE product_id = ANY($1) GROUP BY product_id"
+        )
+        .bind(ids)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+
+        let deleted: Vec<Uuid> = sqlx::query_scalar("UPDATE products SET archived_at = NOW() WHERE id = ANY($1) AND archived_at IS NULL RETURNING id")
+            .bind(deletable_ids(ids, &report_counts, force))
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(delete_response(ids, &report_counts, &deleted))
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similarity_key_ignores_case_accents_and_punctuation() {
+        assert_eq!(similarity_key("Crème Brûlée"), "cremebrulee");
+        assert_eq!(similarity_key("JUS D'ORANGE - 1 L"), similarity_key("jus d orange 1L"));
+        assert_eq!(similarity_key("Boisson gazeuse à l'ananas"), "boissongazeusealananas");
+    }
+
+    #[test]
+    fn similarity_key_keeps_digits_apart() {
+        assert_ne!(similarity_key("Eau minérale 1.5 L"), similarity_key("Eau minérale 0.5 L"));
+        assert_eq!(similarity_key(" - "), "");
+    }
 }
@@ -1,11 +1,18 @@
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres};
+use sqlx::postgres::PgArguments;
+use sqlx::query::QueryAs;
 use anyhow::Result;
 use uuid::Uuid;
-use chrono::{Utc, NaiveDate, NaiveTime};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc, NaiveDate};
+use serde::{Deserialize, Deserializer, Serialize};
 use rust_decimal::Decimal;
-use rust_decimal::prelude::FromPrimitive;
-use crate::database::models::{NonConformityReport, NcDes, Format};
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::HashMap;
+use std::str::FromStr;
+use crate::database::bulk::{delete_response, BulkDeleteResponse};
+use crate::database::concurrency::Conflict;
+use crate::database::models::{ClaimOrigin, NonConformityReport, ReportFieldChange, ReportStatusHistory, Status, Team};
+use crate::database::validation::Validator;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateReportRequest {
@@ -21,7 +28,7 @@ pub struct CreateReportRequest {
     pub quantity: i32,
     pub claim_origin: String,
     pub claim_origin_detail: Option<String>,
-    pub valuation: f64,
+    pub valuation: Decimal, // Decimal string such as "1250.50"; malformed values are rejected
     pub performance: Option<String>,
     pub picture_data: Option<String>, // Base64 encoded image data
 }
@@ -40,9 +47,56 @@ pub struct UpdateReportRequest {
     pub quantity: i32,
     pub claim_origin: String,
     pub claim_origin_detail: Option<String>,
-    pub valuation: f64,
+    pub valuation: Decimal, // Decimal string such as "1250.50"; malformed values are rejected
     pub performance: Option<String>,
     pub picture_data: Option<String>, // Base64 encoded image data
+    pub version: i32, // Version of the copy being edited
+}
+
+/// Changes to apply to a report. Absent fields are left as they are, an
+/// explicit `null` clears a nullable field and is rejected on required ones.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ReportPatch {
+    pub version: i32, // Version of the copy being edited
+    #[serde(default, deserialize_with = "patch_field")]
+    pub line_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub product_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub format_id: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub report_date: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub production_date: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub team: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub time: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub description_type: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub description_details: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub quantity: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub claim_origin: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub claim_origin_detail: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub valuation: Option<Option<Decimal>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub performance: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub picture_data: Option<Option<String>>,
+}
+
+/// Keeps an explicit `null` as `Some(None)`, absent fields fall back to `None` through `#[serde(default)]`.
+fn patch_field<'de, D, T>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,7 +109,12 @@ pub struct PaginationParams {
     pub start_date: Option<String>,
     pub end_date: Option<String>,
     pub claim_origin: Option<String>,
+    /// Origins the user's roles give access to. Set by the commands from the
+    /// session, never taken from the client.
+    #[serde(skip)]
     pub user_accessible_origins: Option<Vec<String>>,
+    #[serde(default)]
+    pub family_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +126,378 @@ pub struct PaginatedResponse<T> {
     pub total_pages: i64,
 }
 
+impl PaginationParams {
+    pub fn filters(&self) -> ReportFilters {
+        ReportFilters {
+            search: self.search.clone(),
+            product_id: self.product_id.clone(),
+            line_id: self.line_id.clone(),
+            start_date: self.start_date.clone(),
+            end_date: self.end_date.clone(),
+            claim_origin: self.claim_origin.clone(),
+            user_accessible_origins: self.user_accessible_origins.clone(),
+            family_id: self.family_id.clone(),
+        }
+    }
+}
+
+/// Report filters shared by the paginated list and the analytics queries.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportFilters {
+    pub search: Option<String>,
+    pub product_id: Option<String>,
+    pub line_id: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub claim_origin: Option<String>,
+    /// Origins the user's roles give access to. Set by the commands from the
+    /// session, never taken from the client.
+    #[serde(skip)]
+    pub user_accessible_origins: Option<Vec<String>>,
+    #[serde(default)]
+    pub family_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParetoDimension {
+    Product,
+    Line,
+    DescriptionType,
+    Format,
+    Family,
+}
+
+impl FromStr for ParetoDimension {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "product" => Ok(ParetoDimension::Product),
+            "line" => Ok(ParetoDimension::Line),
+            "description_type" => Ok(ParetoDimension::DescriptionType),
+            "format" => Ok(ParetoDimension::Format),
+            "family" => Ok(ParetoDimension::Family),
+            _ => Err(format!("Invalid pareto dimension: {}", s)),
+        }
+    }
+}
+
+impl ParetoDimension {
+    /// Key and label expressions used to group reports along this dimension.
+    fn group_columns(&self) -> (&'static str, &'static str) {
+        match self {
+            ParetoDimension::Product => ("ncr.product_id::TEXT", "COALESCE(p.designation, 'Produit inconnu')"),
+            ParetoDimension::Line => ("ncr.line_id::TEXT", "COALESCE(pl.name, 'Ligne inconnue')"),
+            ParetoDimension::DescriptionType => ("ncr.description_type", "ncr.description_type"),
+            ParetoDimension::Format => (
+                "COALESCE(ncr.format_id::TEXT, '')",
+                "CASE WHEN f.format_index IS NOT NULL THEN CONCAT(f.format_index, ' ', f.format_unit) ELSE 'Non spécifié' END",
+            ),
+            ParetoDimension::Family => ("COALESCE(p.family_id::TEXT, '')", "COALESCE(fam.name, 'Sans famille')"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParetoMetric {
+    Count,
+    Quantity,
+    Valuation,
+}
+
+impl FromStr for ParetoMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "count" => Ok(ParetoMetric::Count),
+            "quantity" => Ok(ParetoMetric::Quantity),
+            "valuation" => Ok(ParetoMetric::Valuation),
+            _ => Err(format!("Invalid pareto metric: {}", s)),
+        }
+    }
+}
+
+impl ParetoMetric {
+    fn aggregate(&self) -> &'static str {
+        match self {
+            ParetoMetric::Count => "COUNT(*)::NUMERIC",
+            ParetoMetric::Quantity => "SUM(ncr.quantity)::NUMERIC",
+            ParetoMetric::Valuation => "SUM(ncr.valuation)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParetoParams {
+    pub dimension: String,
+    pub metric: String,
+    pub top_n: Option<i64>,
+    pub filters: ReportFilters,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParetoEntry {
+    pub rank: i64,
+    pub key: String,
+    pub label: String,
+    pub value: Decimal,
+    pub percentage: f64,
+    pub cumulative_percentage: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParetoResponse {
+    pub dimension: ParetoDimension,
+    pub metric: ParetoMetric,
+    pub total: Decimal,
+    pub entries: Vec<ParetoEntry>,
+    pub others: Option<ParetoEntry>, // Groups ranked below top_n, folded together
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum FilterValue {
+    Uuid(Uuid),
+    Date(NaiveDate),
+    Text(String),
+}
+
+/// `AND ...` conditions on the `ncr` alias together with their bind values,
+/// numbered from `$1`.
+pub(crate) struct FilterClause {
+    pub(crate) conditions: String,
+    pub(crate) values: Vec<FilterValue>,
+}
+
+impl FilterClause {
+    pub(crate) fn next_param(&self) -> usize {
+        self.values.len() + 1
+    }
+
+    fn push(&mut self, condition: &str, value: FilterValue) {
+        self.values.push(value);
+        let placeholder = format!("${}", self.values.len());
+        self.conditions.push_str(" AND ");
+        self.conditions.push_str(&condition.replace("$PLACEHOLDER", &placeholder));
+    }
+}
+
+pub(crate) fn build_filter_clause(filters: &ReportFilters) -> Result<FilterClause> {
+    // Archived reports are deleted as far as users are concerned
+    let mut clause = FilterClause {
+        conditions: " AND ncr.archived_at IS NULL".to_string(),
+        values: Vec::new(),
+    };
+
+    if let Some(search) = &filters.search {
+        if !search.trim().is_empty() {
+            clause.push(
+                "(ncr.report_number ILIKE $PLACEHOLDER OR ncr.description_details ILIKE $PLACEHOLDER)",
+                FilterValue::Text(format!("%{}%", search)),
+            );
+        }
+    }
+
+    if let Some(product_id) = &filters.product_id {
+        if !product_id.trim().is_empty() {
+            let product_uuid = Uuid::parse_str(product_id)
+                .map_err(|e| anyhow::anyhow!("Invalid product UUID: {}", e))?;
+            clause.push("ncr.product_id = $PLACEHOLDER", FilterValue::Uuid(product_uuid));
+        }
+    }
+
+    if let Some(family_id) = &filters.family_id {
+        if !family_id.trim().is_empty() {
+            let family_uuid = Uuid::parse_str(family_id)
+                .map_err(|e| anyhow::anyhow!("Invalid family UUID: {}", e))?;
+            clause.push(
+                "ncr.product_id IN (SELECT id FROM products WHERE family_id = $PLACEHOLDER)",
+                FilterValue::Uuid(family_uuid),
+            );
+        }
+    }
+
+    if let Some(line_id) = &filters.line_id {
+        if !line_id.trim().is_empty() {
+            let line_uuid = Uuid::parse_str(line_id)
+                .map_err(|e| anyhow::anyhow!("Invalid line UUID: {}", e))?;
+            clause.push("ncr.line_id = $PLACEHOLDER", FilterValue::Uuid(line_uuid));
+        }
+    }
+
+    if let Some(start_date) = &filters.start_date {
+        if !start_date.trim().is_empty() {
+            let date = NaiveDate::parse_from_str(start_date, "%Y-%m-%d")
+                .map_err(|e| anyhow::anyhow!("Invalid start date format: {}", e))?;
+            clause.push("ncr.report_date >= $PLACEHOLDER", FilterValue::Date(date));
+        }
+    }
+
+    if let Some(end_date) = &filters.end_date {
+        if !end_date.trim().is_empty() {
+            let date = NaiveDate::parse_from_str(end_date, "%Y-%m-%d")
+                .map_err(|e| anyhow::anyhow!("Invalid end date format: {}", e))?;
+            clause.push("ncr.report_date <= $PLACEHOLDER", FilterValue::Date(date));
+        }
+    }
+
+    if let Some(claim_origin) = &filters.claim_origin {
+        if !claim_origin.trim().is_empty() {
+            clause.push("ncr.claim_origin = $PLACEHOLDER", FilterValue::Text(claim_origin.clone()));
+        }
+    }
+
+    // Always restrict to the origins the user may see, a chosen claim_origin only narrows it
+    if let Some(accessible_origins) = &filters.user_accessible_origins {
+        if accessible_origins.is_empty() {
+            clause.conditions.push_str(" AND FALSE");
+        } else {
+            let first = clause.next_param();
+            let placeholders: Vec<String> = (first..first + accessible_origins.len())
+                .map(|i| format!("${}", i))
+                .collect();
+            clause.conditions.push_str(&format!(" AND ncr.claim_origin IN ({})", placeholders.join(", ")));
+            clause.values.extend(accessible_origins.iter().cloned().map(FilterValue::Text));
+        }
+    }
+
+    Ok(clause)
+}
+
+pub(crate) fn bind_filters<'q, O>(
+    mut query: QueryAs<'q, Postgres, O, PgArguments>,
+    values: &'q [FilterValue],
+) -> QueryAs<'q, Postgres, O, PgArguments> {
+    for value in values {
+        query = match value {
+            FilterValue::Uuid(uuid) => query.bind(*uuid),
+            FilterValue::Date(date) => query.bind(*date),
+            FilterValue::Text(text) => query.bind(text.as_str()),
+        };
+    }
+    query
+}
+
+/// One field changed by a report edit, as written to `report_field_history`.
+pub(crate) struct FieldChange {
+    pub(crate) field: &'static str,
+    pub(crate) old_value: Option<String>,
+    pub(crate) new_value: Option<String>,
+}
+
+/// Sets `target` to `value` and records the change when they differ.
+fn apply_change<T: PartialEq>(
+    changes: &mut Vec<FieldChange>,
+    field: &'static str,
+    target: &mut T,
+    value: T,
+    display: impl Fn(&T) -> Option<String>,
+) {
+    if *target != value {
+        changes.push(FieldChange {
+            field,
+            old_value: display(target),
+            new_value: display(&value),
+        });
+        *target = value;
+    }
+}
+
+/// Ranks the groups, sorted by value, and folds those below `top_n` together.
+fn pareto_response(
+    dimension: ParetoDimension,
+    metric: ParetoMetric,
+    groups: &[(String, String, Decimal)],
+    top_n: Option<i64>,
+) -> ParetoResponse {
+    let total: Decimal = groups.iter().map(|(_, _, value)| *value).sum();
+    let share = |value: Decimal| -> f64 {
+        if total.is_zero() {
+            0.0
+        } else {
+            (value * Decimal::ONE_HUNDRED / total).round_dp(2).to_f64().unwrap_or_default()
+        }
+    };
+
+    let top_n = top_n.filter(|n| *n > 0).map(|n| n as usize).unwrap_or(groups.len());
+    let mut entries = Vec::new();
+    let mut cumulative = Decimal::ZERO;
+
+    for (index, (key, label, value)) in groups.iter().take(top_n).enumerate() {
+        cumulative += *value;
+        entries.push(ParetoEntry {
+            rank: index as i64 + 1,
+            key: key.clone(),
+            label: label.clone(),
+            value: *value,
+            percentage: share(*value),
+            cumulative_percentage: share(cumulative),
+        });
+    }
+
+    // Everything below the top N is folded into a single "Autres" bar
+    let others = if groups.len() > top_n {
+        let rest: Decimal = groups.iter().skip(top_n).map(|(_, _, value)| *value).sum();
+        Some(ParetoEntry {
+            rank: top_n as i64 + 1,
+            key: String::new(),
+            label: "Autres".to_string(),
+            value: rest,
+            percentage: share(rest),
+            cumulative_percentage: share(cumulative + rest),
+        })
+    } else {
+        None
+    };
+
+    ParetoResponse {
+        dimension,
+        metric,
+        total,
+        entries,
+        others,
+    }
+}
+
+/// Writes report field changes to `report_field_history`, for edits as well as
+/// merges that move reports to another line, product or client.
+pub(crate) async fn record_field_changes<'e, E>(
+    executor: E,
+    changes: &[(Uuid, FieldChange)],
+    changed_by: Option<Uuid>,
+    changed_at: DateTime<Utc>,
+) -> Result<()>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let ids: Vec<Uuid> = changes.iter().map(|_| Uuid::new_v4()).collect();
+    let report_ids: Vec<Uuid> = changes.iter().map(|(report_id, _)| *report_id).collect();
+    let fields: Vec<&str> = changes.iter().map(|(_, c)| c.field).collect();
+    let old_values: Vec<Option<String>> = changes.iter().map(|(_, c)| c.old_value.clone()).collect();
+    let new_values: Vec<Option<String>> = changes.iter().map(|(_, c)| c.new_value.clone()).collect();
+
+    sqlx::query(
+        r#"
+        INSERT INTO report_field_history (id, report_id, field, old_value, new_value, changed_by, changed_at)
+        SELECT id, report_id, field, old_value, new_value, $6, $7
+        FROM UNNEST($1::UUID[], $2::UUID[], $3::VARCHAR[], $4::TEXT[], $5::TEXT[]) AS c(id, report_id, field, old_value, new_value)
+        "#,
+    )
+    .bind(&ids)
+    .bind(&report_ids)
+    .bind(&fields)
+    .bind(&old_values)
+    .bind(&new_values)
+    .bind(changed_by)
+    .bind(changed_at)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
 pub struct ReportsService {
     pool: PgPool,
 }
@@ -76,37 +507,114 @@ impl ReportsService {
         Self { pool }
     }
 
+    /// Archived or inactive lines stay on old reports but can't be picked for new ones.
+    async fn check_line_selectable(&self, v: &mut Validator, line_id: Uuid) -> Result<()> {
+        let line: Option<(bool, bool)> = sqlx::query_as(
+            "SELECT is_active, archived_at IS NOT NULL FROM production_lines WHERE id = $1"
+        )
+        .bind(line_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match line {
+            None => v.error("line_id", "Production line not found"),
+            Some((_, true)) => v.error("line_id", "Production line is archived"),
+            Some((false, _)) => v.error("line_id", "Production line is inactive"),
+            Some((true, false)) => {}
+        }
+
+        Ok(())
+    }
+
+    /// Products with associated formats only accept those; products without any
+    /// keep accepting every format.
+    async fn check_format_allowed(&self, v: &mut Validator, product_id: Uuid, format_id: Option<i32>) -> Result<()> {
+        let Some(format_id) = format_id else {
+            return Ok(());
+        };
+
+        let (restricted, allowed): (bool, bool) = sqlx::query_as(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM product_formats WHERE product_id = $1),
+                   EXISTS (SELECT 1 FROM product_formats WHERE product_id = $1 AND format_id = $2)
+            "#
+        )
+        .bind(product_id)
+        .bind(format_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        v.check("format_id", !restricted || allowed, "Format is not available for this product");
+
+        Ok(())
+    }
+
+    async fn check_product_selectable(&self, v: &mut Validator, product_id: Uuid) -> Result<()> {
+        let product_archived: Option<bool> = sqlx::query_scalar(
+            "SELECT archived_at IS NOT NULL FROM products WHERE id = $1"
+        )
+        .bind(product_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match product_archived {
+            None => v.error("product_id", "Product not found"),
+            Some(true) => v.error("product_id", "Product is archived"),
+            Some(false) => {}
+        }
+
+        Ok(())
+    }
+
+    async fn check_description_type(&self, v: &mut Validator, description_type: &str) -> Result<()> {
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM nc_des WHERE name = $1)")
+            .bind(description_type)
+            .fetch_one(&self.pool)
+            .await?;
+
+        v.check("description_type", exists, "Unknown description type");
+
+        Ok(())
+    }
+
     pub async fn create_report(&self, request: CreateReportRequest, reported_by: Uuid) -> Result<NonConformityReport> {
         let id = Uuid::new_v4();
         let now = Utc::now();
         
+        let mut v = Validator::new();
+
+        let line_id = v.uuid("line_id", &request.line_id);
+        let product_id = v.uuid("product_id", &request.product_id);
+        let report_date = v.date("report_date", &request.report_date);
+        let production_date = v.date("production_date", &request.production_date);
+        let time = v.time("time", &request.time);
+        let team = v.parse::<Team>("team", &request.team);
+        let claim_origin = v.parse::<ClaimOrigin>("claim_origin", &request.claim_origin);
+
+        v.check("quantity", request.quantity > 0, "Quantity must be greater than zero");
+        v.check("valuation", request.valuation >= Decimal::ZERO, "Valuation cannot be negative");
+        if let (Some(production_date), Some(report_date)) = (production_date, report_date) {
+            v.check("production_date", production_date <= report_date, "Production date cannot be after the report date");
+        }
+
+        if let Some(line_id) = line_id {
+            self.check_line_selectable(&mut v, line_id).await?;
+        }
+        if let Some(product_id) = product_id {
+            self.check_product_selectable(&mut v, product_id).await?;
+            self.check_format_allowed(&mut v, product_id, request.format_id).await?;
+        }
+        self.check_description_type(&mut v, &request.description_type).await?;
+
+        v.finish()?;
+        let (Some(line_id), Some(product_id), Some(report_date), Some(production_date), Some(time), Some(team), Some(claim_origin)) =
+            (line_id, product_id, report_date, production_date, time, team, claim_origin)
+        else {
+            unreachable!("every field parsed once validation passed");
+        };
+
         // Generate report number (format: NC-YYYYMMDD-XXXX)
         let report_number = self.generate_report_number().await?;
-        
-        // Parse dates and times
-        let report_date = NaiveDate::parse_from_str(&request.report_date, "%Y-%m-%d")
-            .map_err(|e| anyhow::anyhow!("Invalid report date format: {}", e))?;
-            
-        let production_date = NaiveDate::parse_from_str(&request.production_date, "%Y-%m-%d")
-            .map_err(|e| anyhow::anyhow!("Invalid production date format: {}", e))?;
-        
-        let time_input = request.time.trim();
-        let time = if time_input == "--:--" || time_input.is_empty() {
-            // Default time when not specified
-            NaiveTime::from_hms_opt(0, 0, 0).unwrap()
-        } else {
-            let time_clean: String = time_input.chars().filter(|c| c.is_ascii_digit() || *c == ':').collect();
-            NaiveTime::parse_from_str(&time_clean, "%H:%M")
-                .or_else(|_| NaiveTime::parse_from_str(&time_clean, "%H:%M:%S"))
-                .map_err(|e| anyhow::anyhow!("Invalid time format (expected HH:MM or HH:MM:SS): {}", e))?
-        };
-        
-        // Parse UUIDs
-        let line_id = Uuid::parse_str(&request.line_id)
-            .map_err(|e| anyhow::anyhow!("Invalid line ID: {}", e))?;
-        
-        let product_id = Uuid::parse_str(&request.product_id)
-            .map_err(|e| anyhow::anyhow!("Invalid product ID: {}", e))?;
 
         // First insert the report
         sqlx::query(
@@ -126,23 +634,25 @@ impl ReportsService {
         .bind(product_id)
         .bind(request.format_id)
         .bind(production_date)
-        .bind(&request.team)
+        .bind(team.as_str())
         .bind(time)
         .bind(&request.description_type)
         .bind(&request.description_details)
         .bind(request.quantity)
-        .bind(&request.claim_origin)
+        .bind(claim_origin.as_str())
         .bind(&request.claim_origin_detail)
-        .bind(Decimal::from_f64(request.valuation).unwrap_or_default())
+        .bind(request.valuation)
         .bind(&request.performance)
         .bind(&request.picture_data)
-        .bind("open") // Default status
+        .bind(Status::Open.as_str()) // Default status
         .bind(reported_by)
         .bind(now)
         .bind(now)
         .execute(&self.pool)
         .await?;
 
+        self.record_status_change(id, None, Status::Open.as_str(), Some(reported_by)).await?;
+
         // Then fetch the report with product name, line name, and format info via JOIN
         let report = sqlx::query_as::<_, NonConformityReport>(
             r#"
@@ -167,7 +677,8 @@ impl ReportsService {
         Ok(report)
     }
 
-    pub async fn get_all_reports(&self) -> Result<Vec<NonConformityReport>> {
+    /// Reports with one of the given claim origins.
+    pub async fn get_all_reports(&self, origins: &[String]) -> Result<Vec<NonConformityReport>> {
         let reports = sqlx::query_as::<_, NonConformityReport>(
             r#"
             SELECT ncr.*, 
@@ -179,9 +690,11 @@ impl ReportsService {
             FROM non_conformity_reports ncr
             LEFT JOIN products p ON ncr.product_id = p.id
             LEFT JOIN formats f ON ncr.format_id = f.id
+            WHERE ncr.archived_at IS NULL AND ncr.claim_origin = ANY($1)
             ORDER BY ncr.created_at DESC
             "#
         )
+        .bind(origins)
         .fetch_all(&self.pool)
         .await?;
 
@@ -193,8 +706,10 @@ impl ReportsService {
                  params.page, params.limit, params.search, params.product_id, params.line_id, params.start_date, params.end_date, params.claim_origin, params.user_accessible_origins);
         
         let offset = (params.page - 1) * params.limit;
-        
-        let mut query = String::from(
+        let clause = build_filter_clause(&params.filters())?;
+        let limit_index = clause.next_param();
+
+        let final_query = format!(
             r#"
             SELECT ncr.*, 
                    p.designation as product_name,
@@ -207,206 +722,27 @@ impl ReportsService {
             LEFT JOIN products p ON ncr.product_id = p.id
             LEFT JOIN formats f ON ncr.format_id = f.id
             LEFT JOIN production_lines pl ON ncr.line_id = pl.id
-            WHERE 1=1
-            "#
+            WHERE 1=1{}
+            ORDER BY ncr.created_at DESC LIMIT ${} OFFSET ${}
+            "#,
+            clause.conditions,
+            limit_index,
+            limit_index + 1
         );
-        
-        let mut count_query = String::from(
-            "SELECT COUNT(*) FROM non_conformity_reports ncr WHERE 1=1"
+
+        let count_query = format!(
+            "SELECT COUNT(*) FROM non_conformity_reports ncr WHERE 1=1{}",
+            clause.conditions
         );
         
-        let mut conditions = Vec::new();
-        let mut bind_values = Vec::new();
-        
-        // Track parameter types for proper binding
-        let mut param_types = Vec::new();
-        
-        // Collect all filter conditions and their values
-        if let Some(search) = &params.search {
-            println!("[REPORTS_SERVICE] Processing search: '{}'", search);
-            if !search.trim().is_empty() {
-                println!("[REPORTS_SERVICE] Adding search condition");
-                conditions.push("(ncr.report_number ILIKE $PLACEHOLDER OR ncr.description_details ILIKE $PLACEHOLDER)");
-                bind_values.push(format!("%{}%", search));
-                param_types.push("string");
-            } else {
-                println!("[REPORTS_SERVICE] Search is empty, skipping");
-            }
-        } else {
-            println!("[REPORTS_SERVICE] Search is None");
-        }
-
-        if let Some(product_id) = &params.product_id {
-            println!("[REPORTS_SERVICE] Processing product_id: '{}'", product_id);
-            if !product_id.trim().is_empty() {
-                println!("[REPORTS_SERVICE] Adding product_id condition");
-                let product_uuid = uuid::Uuid::parse_str(product_id)
-                    .map_err(|e| anyhow::anyhow!("Invalid product UUID: {}", e))?;
-                conditions.push("ncr.product_id = $PLACEHOLDER");
-                bind_values.push(product_uuid.to_string());
-                param_types.push("uuid");
-            } else {
-                println!("[REPORTS_SERVICE] Product_id is empty, skipping");
-            }
-        } else {
-            println!("[REPORTS_SERVICE] Product_id is None");
-        }
-
-        if let Some(line_id) = &params.line_id {
-            println!("[REPORTS_SERVICE] Processing line_id: '{}'", line_id);
-            if !line_id.trim().is_empty() {
-                println!("[REPORTS_SERVICE] Adding line_id condition");
-                let line_uuid = uuid::Uuid::parse_str(line_id)
-                    .map_err(|e| anyhow::anyhow!("Invalid line UUID: {}", e))?;
-                conditions.push("ncr.line_id = $PLACEHOLDER");
-                bind_values.push(line_uuid.to_string());
-                param_types.push("uuid");
-            } else {
-                println!("[REPORTS_SERVICE] Line_id is empty, skipping");
-            }
-        } else {
-            println!("[REPORTS_SERVICE] Line_id is None");
-        }
-
-        if let Some(start_date) = &params.start_date {
-            println!("[REPORTS_SERVICE] Processing start_date: '{}'", start_date);
-            if !start_date.trim().is_empty() {
-                println!("[REPORTS_SERVICE] Adding start_date condition");
-                let _start_date_parsed = chrono::NaiveDate::parse_from_str(start_date, "%Y-%m-%d")
-                    .map_err(|e| anyhow::anyhow!("Invalid start date format: {}", e))?;
-                conditions.push("ncr.report_date >= $PLACEHOLDER");
-                bind_values.push(start_date.clone());
-                param_types.push("date");
-            } else {
-                println!("[REPORTS_SERVICE] Start_date is empty, skipping");
-            }
-        } else {
-            println!("[REPORTS_SERVICE] Start_date is None");
-        }
-
-        if let Some(end_date) = &params.end_date {
-            println!("[REPORTS_SERVICE] Processing end_date: '{}'", end_date);
-            if !end_date.trim().is_empty() {
-                println!("[REPORTS_SERVICE] Adding end_date condition");
-                let _end_date_parsed = chrono::NaiveDate::parse_from_str(end_date, "%Y-%m-%d")
-                    .map_err(|e| anyhow::anyhow!("Invalid end date format: {}", e))?;
-                conditions.push("ncr.report_date <= $PLACEHOLDER");
-                bind_values.push(end_date.clone());
-                param_types.push("date");
-            } else {
-                println!("[REPORTS_SERVICE] End_date is empty, skipping");
-            }
-        } else {
-            println!("[REPORTS_SERVICE] End_date is None");
-        }
-
-        // Handle claim_origin filtering
-        if let Some(claim_origin) = &params.claim_origin {
-            println!("[REPORTS_SERVICE] Processing claim_origin: '{}'", claim_origin);
-            if !claim_origin.trim().is_empty() {
-                println!("[REPORTS_SERVICE] Adding claim_origin condition");
-                // Use exact string comparison for claim_origin
-                conditions.push("ncr.claim_origin = $PLACEHOLDER");
-                bind_values.push(claim_origin.clone());
-                param_types.push("string");
-            } else {
-                println!("[REPORTS_SERVICE] Claim_origin is empty, skipping");
-            }
-        } else {
-            println!("[REPORTS_SERVICE] Claim_origin is None");
-            
-            // If no specific claim_origin is set, but user_accessible_origins is provided,
-            // filter by the user's accessible origins
-            if let Some(accessible_origins) = &params.user_accessible_origins {
-                if !accessible_origins.is_empty() {
-                    println!("[REPORTS_SERVICE] No specific claim_origin, but user has accessible origins: {:?}", accessible_origins);
-                    // Add a special marker for accessible origins
-                    conditions.push("USER_ACCESSIBLE_ORIGINS");
-                    // Add all accessible origins to bind_values
-                    for origin in accessible_origins {
-                        bind_values.push(origin.clone());
-                        param_types.push("string");
-                    }
-                } else {
-                    println!("[REPORTS_SERVICE] User has empty accessible origins list");
-                }
-            } else {
-                println!("[REPORTS_SERVICE] No user_accessible_origins provided");
-            }
-        }
-        
-        println!("[REPORTS_SERVICE] Found {} conditions to apply", conditions.len());
-        
-        // Build the final queries with proper parameter placeholders
-        let mut param_index = 1;
-        
-        for condition in &conditions {
-            let condition_with_params = if condition.contains("ILIKE $PLACEHOLDER OR") {
-                // For search condition that uses the same parameter twice
-                condition.replace("$PLACEHOLDER", &format!("${}", param_index))
-            } else if *condition == "USER_ACCESSIBLE_ORIGINS" {
-                // Handle the accessible origins IN clause
-                if let Some(accessible_origins) = &params.user_accessible_origins {
-                    let placeholders: Vec<String> = (0..accessible_origins.len())
-                        .map(|i| format!("${}", param_index + i))
-                        .collect();
-                    param_index += accessible_origins.len() - 1; // Adjust for multiple parameters
-                    format!("ncr.claim_origin IN ({})", placeholders.join(", "))
-                } else {
-                    continue; // Skip if no accessible origins
-                }
-            } else {
-                condition.replace("$PLACEHOLDER", &format!("${}", param_index))
-            };
-            query.push_str(&format!(" AND {}", condition_with_params));
-            count_query.push_str(&format!(" AND {}", condition_with_params));
-            param_index += 1;
-        }
-        
-        query.push_str(" ORDER BY ncr.created_at DESC LIMIT $LIMIT OFFSET $OFFSET");
-        
-        // Replace LIMIT and OFFSET placeholders
-        let final_query = query
-            .replace("$LIMIT", &format!("${}", param_index))
-            .replace("$OFFSET", &format!("${}", param_index + 1));
-        
         println!("[REPORTS_SERVICE] Final query: {}", final_query);
         println!("[REPORTS_SERVICE] Count query: {}", count_query);
-        println!("[REPORTS_SERVICE] Bind values: {:?}", bind_values);
-        
-        // Build the queries with proper binding
-        let mut query_builder = sqlx::query_as::<_, NonConformityReport>(&final_query);
-        let mut count_builder = sqlx::query_as::<_, (i64,)>(&count_query);
-        
-        // Bind filter parameters
-        for (i, value) in bind_values.iter().enumerate() {
-            match *param_types.get(i).unwrap_or(&"string") {
-                "uuid" => {
-                    // Bind as UUID
-                    let uuid = uuid::Uuid::parse_str(value).unwrap();
-                    println!("[REPORTS_SERVICE] Binding UUID: {}", uuid);
-                    query_builder = query_builder.bind(uuid);
-                    count_builder = count_builder.bind(uuid);
-                },
-                "date" => {
-                    // Bind as date
-                    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap();
-                    println!("[REPORTS_SERVICE] Binding date: {}", date);
-                    query_builder = query_builder.bind(date);
-                    count_builder = count_builder.bind(date);
-                },
-                _ => {
-                    // Bind as string (search pattern, claim_origin, etc.)
-                    println!("[REPORTS_SERVICE] Binding string: {}", value);
-                    query_builder = query_builder.bind(value);
-                    count_builder = count_builder.bind(value);
-                }
-            }
-        }
+        println!("[REPORTS_SERVICE] Bind values: {:?}", clause.values);
         
-        // Bind limit and offset for main query
-        println!("[REPORTS_SERVICE] Binding limit: {}, offset: {}", params.limit, offset);
-        query_builder = query_builder.bind(params.limit).bind(offset);
+        let query_builder = bind_filters(sqlx::query_as::<_, NonConformityReport>(&final_query), &clause.values)
+            .bind(params.limit)
+            .bind(offset);
+        let count_builder = bind_filters(sqlx::query_as::<_, (i64,)>(&count_query), &clause.values);
         
         let reports = query_builder.fetch_all(&self.pool).await?;
         let total: (i64,) = count_builder.fetch_one(&self.pool).await?;
@@ -424,24 +760,37 @@ impl ReportsService {
         })
     }
 
-    pub async fn get_description_types(&self) -> Result<Vec<NcDes>> {
-        let types = sqlx::query_as::<_, NcDes>(
-            "SELECT * FROM nc_des ORDER BY name"
-        )
-        .fetch_all(&self.pool)
-        .await?;
+    /// Ranks products, lines, description types or formats by NC count,
+    /// quantity or valuation, with each group's cumulative share of the total.
+    pub async fn get_pareto(&self, params: ParetoParams) -> Result<ParetoResponse> {
+        let dimension = ParetoDimension::from_str(&params.dimension).map_err(|e| anyhow::anyhow!(e))?;
+        let metric = ParetoMetric::from_str(&params.metric).map_err(|e| anyhow::anyhow!(e))?;
+        let clause = build_filter_clause(&params.filters)?;
+        let (key_expr, label_expr) = dimension.group_columns();
 
-        Ok(types)
-    }
+        let query = format!(
+            r#"
+            SELECT {} AS key, {} AS label, {} AS value
+            FROM non_conformity_reports ncr
+            LEFT JOIN products p ON ncr.product_id = p.id
+            LEFT JOIN production_lines pl ON ncr.line_id = pl.id
+            LEFT JOIN formats f ON ncr.format_id = f.id
+            LEFT JOIN product_families fam ON p.family_id = fam.id
+            WHERE 1=1{}
+            GROUP BY 1, 2
+            ORDER BY value DESC, label ASC
+            "#,
+            key_expr,
+            label_expr,
+            metric.aggregate(),
+            clause.conditions
+        );
 
-    pub async fn get_formats(&self) -> Result<Vec<Format>> {
-        let formats = sqlx::query_as::<_, Format>(
-            "SELECT * FROM formats ORDER BY format_index"
-        )
-        .fetch_all(&self.pool)
-        .await?;
+        let groups = bind_filters(sqlx::query_as::<_, (String, String, Decimal)>(&query), &clause.values)
+            .fetch_all(&self.pool)
+            .await?;
 
-        Ok(formats)
+        Ok(pareto_response(dimension, metric, &groups, params.top_n))
     }
 
     async fn generate_report_number(&self) -> Result<String> {
@@ -458,61 +807,292 @@ impl ReportsService {
         Ok(format!("NC-{}-{:04}", today, sequence))
     }
 
-    pub async fn update_report_status(&self, report_id: Uuid, status: String) -> Result<NonConformityReport> {
+    pub async fn update_report_status(&self, report_id: Uuid, version: i32, status: String, changed_by: Option<Uuid>) -> Result<NonConformityReport> {
         let now = Utc::now();
-        
-        let report = sqlx::query_as::<_, NonConformityReport>(
-            "UPDATE non_conformity_reports SET status = $1, updated_at = $2 WHERE id = $3 RETURNING *"
+
+        let mut v = Validator::new();
+        let status = v.parse::<Status>("status", &status);
+        v.finish()?;
+        let Some(status) = status.map(|s| s.as_str()) else {
+            unreachable!("status parsed once validation passed");
+        };
+
+        let old_status: Option<String> = sqlx::query_scalar(
+            "SELECT status FROM non_conformity_reports WHERE id = $1 AND version = $2"
+        )
+        .bind(report_id)
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(old_status) = old_status else {
+            return Err(self.version_miss(report_id).await?);
+        };
+
+        let result = sqlx::query(
+            "UPDATE non_conformity_reports SET status = $1, updated_at = $2, version = version + 1 WHERE id = $3 AND version = $4"
         )
-        .bind(&status)
+        .bind(status)
         .bind(now)
         .bind(report_id)
-        .fetch_one(&self.pool)
+        .bind(version)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(self.version_miss(report_id).await?);
+        }
+
+        if old_status != status {
+            self.record_status_change(report_id, Some(&old_status), status, changed_by).await?;
+        }
+
+        self.get_report_by_id(report_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Report not found"))
+    }
+
+    pub async fn get_report_by_id(&self, report_id: Uuid) -> Result<Option<NonConformityReport>> {
+        let report = sqlx::query_as::<_, NonConformityReport>(
+            r#"
+            SELECT ncr.*, 
+                   p.designation as product_name,
+                   pl.name as line_name,
+                   CASE 
+                       WHEN f.format_index IS NOT NULL THEN CONCAT(f.format_index, ' ', f.format_unit)
+                       ELSE NULL 
+                   END as format_display
+            FROM non_conformity_reports ncr
+            LEFT JOIN products p ON ncr.product_id = p.id
+            LEFT JOIN production_lines pl ON ncr.line_id = pl.id
+            LEFT JOIN formats f ON ncr.format_id = f.id
+            WHERE ncr.id = $1
+            "#,
+        )
+        .bind(report_id)
+        .fetch_optional(&self.pool)
         .await?;
 
         Ok(report)
     }
 
-    pub async fn update_report_performance(&self, report_id: Uuid, performance: String) -> Result<bool> {
-        let result = sqlx::query(
-            "UPDATE non_conformity_reports SET performance = $1, updated_at = $2 WHERE id = $3"
+    /// Error for a versioned update that matched no row: the report is gone, or
+    /// it changed since the caller loaded it and the current copy is returned.
+    async fn version_miss(&self, report_id: Uuid) -> Result<anyhow::Error> {
+        Ok(match self.get_report_by_id(report_id).await? {
+            Some(current) => Conflict {
+                message: format!("Report {} was modified by someone else", current.report_number),
+                current,
+            }
+            .into(),
+            None => anyhow::anyhow!("Report not found"),
+        })
+    }
+
+    pub async fn get_report_status_history(&self, report_id: Uuid) -> Result<Vec<ReportStatusHistory>> {
+        let history = sqlx::query_as::<_, ReportStatusHistory>(
+            r#"
+            SELECT h.id, h.report_id, h.old_status, h.new_status, h.changed_by,
+                   u.username as changed_by_name, h.changed_at
+            FROM report_status_history h
+            LEFT JOIN users u ON h.changed_by = u.id
+            WHERE h.report_id = $1
+            ORDER BY h.changed_at ASC
+            "#,
         )
-        .bind(&performance)
-        .bind(Utc::now())
         .bind(report_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(history)
+    }
+
+    async fn record_status_change(
+        &self,
+        report_id: Uuid,
+        old_status: Option<&str>,
+        new_status: &str,
+        changed_by: Option<Uuid>,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO report_status_history (id, report_id, old_status, new_status, changed_by, changed_at)
+             VALUES ($1, $2, $3, $4, $5, $6)"
+        )
+        .bind(Uuid::new_v4())
+        .bind(report_id)
+        .bind(old_status)
+        .bind(new_status)
+        .bind(changed_by)
+        .bind(Utc::now())
         .execute(&self.pool)
         .await?;
 
-        Ok(result.rows_affected() > 0)
+        Ok(())
     }
 
-    pub async fn update_report(&self, report_id: Uuid, request: UpdateReportRequest) -> Result<NonConformityReport> {
-        let now = Utc::now();
-        
-        // Parse dates and times
-        let report_date = NaiveDate::parse_from_str(&request.report_date, "%Y-%m-%d")
-            .map_err(|e| anyhow::anyhow!("Invalid report date format: {}", e))?;
-            
-        let production_date = NaiveDate::parse_from_str(&request.production_date, "%Y-%m-%d")
-            .map_err(|e| anyhow::anyhow!("Invalid production date format: {}", e))?;
-        
-        let time = if request.time.trim() == "--:--" || request.time.trim().is_empty() {
-            // Default time when not specified
-            NaiveTime::from_hms_opt(0, 0, 0).unwrap()
-        } else {
-            NaiveTime::parse_from_str(&request.time, "%H:%M")
-                .or_else(|_| NaiveTime::parse_from_str(&request.time, "%H:%M:%S"))
-                .map_err(|e| anyhow::anyhow!("Invalid time format (expected HH:MM or HH:MM:SS): {}", e))?
+    pub async fn update_report_performance(&self, report_id: Uuid, version: i32, performance: String, changed_by: Option<Uuid>) -> Result<NonConformityReport> {
+        let patch = ReportPatch {
+            version,
+            performance: Some(Some(performance)),
+            ..Default::default()
         };
-        
-        // Parse UUIDs
-        let line_id = Uuid::parse_str(&request.line_id)
-            .map_err(|e| anyhow::anyhow!("Invalid line ID: {}", e))?;
-        
-        let product_id = Uuid::parse_str(&request.product_id)
-            .map_err(|e| anyhow::anyhow!("Invalid product ID: {}", e))?;
 
-        // Update the report
+        self.patch_report(report_id, patch, changed_by).await
+    }
+
+    pub async fn update_report(&self, report_id: Uuid, request: UpdateReportRequest, changed_by: Option<Uuid>) -> Result<NonConformityReport> {
+        let patch = ReportPatch {
+            version: request.version,
+            line_id: Some(Some(request.line_id)),
+            product_id: Some(Some(request.product_id)),
+            format_id: Some(request.format_id),
+            report_date: Some(Some(request.report_date)),
+            production_date: Some(Some(request.production_date)),
+            team: Some(Some(request.team)),
+            time: Some(Some(request.time)),
+            description_type: Some(Some(request.description_type)),
+            description_details: Some(Some(request.description_details)),
+            quantity: Some(Some(request.quantity)),
+            claim_origin: Some(Some(request.claim_origin)),
+            claim_origin_detail: Some(request.claim_origin_detail),
+            valuation: Some(Some(request.valuation)),
+            performance: Some(request.performance),
+            picture_data: Some(request.picture_data),
+        };
+
+        self.patch_report(report_id, patch, changed_by).await
+    }
+
+    /// Applies only the fields present in `patch`. Every field that actually
+    /// changes is validated and written to `report_field_history`, a patch that
+    /// changes nothing leaves the report and its version untouched.
+    pub async fn patch_report(&self, report_id: Uuid, patch: ReportPatch, changed_by: Option<Uuid>) -> Result<NonConformityReport> {
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+
+        let current = sqlx::query_as::<_, NonConformityReport>(
+            r#"
+            SELECT ncr.*, 
+                   p.designation as product_name,
+                   pl.name as line_name,
+                   CASE 
+                       WHEN f.format_index IS NOT NULL THEN CONCAT(f.format_index, ' ', f.format_unit)
+                       ELSE NULL 
+                   END as format_display
+            FROM non_conformity_reports ncr
+            LEFT JOIN products p ON ncr.product_id = p.id
+            LEFT JOIN production_lines pl ON ncr.line_id = pl.id
+            LEFT JOIN formats f ON ncr.format_id = f.id
+            WHERE ncr.id = $1
+            FOR UPDATE OF ncr
+            "#,
+        )
+        .bind(report_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Report not found"))?;
+
+        if current.version != patch.version {
+            return Err(Conflict {
+                message: format!("Report {} was modified by someone else", current.report_number),
+                current,
+            }
+            .into());
+        }
+
+        let mut v = Validator::new();
+        let mut report = current.clone();
+        let mut changes = Vec::new();
+        let text = |value: &String| Some(value.clone());
+        let optional_text = |value: &Option<String>| value.clone();
+
+        if let Some(line_id) = v.required("line_id", patch.line_id).and_then(|id| v.uuid("line_id", &id)) {
+            apply_change(&mut changes, "line_id", &mut report.line_id, line_id, |value| Some(value.to_string()));
+        }
+        if let Some(product_id) = v.required("product_id", patch.product_id).and_then(|id| v.uuid("product_id", &id)) {
+            apply_change(&mut changes, "product_id", &mut report.product_id, product_id, |value| Some(value.to_string()));
+        }
+        if let Some(format_id) = patch.format_id {
+            apply_change(&mut changes, "format_id", &mut report.format_id, format_id, |value| value.map(|id| id.to_string()));
+        }
+        if let Some(report_date) = v.required("report_date", patch.report_date).and_then(|date| v.date("report_date", &date)) {
+            // Only the day is edited, the time of day the report was filed is kept
+            let report_date = report_date.and_time(report.report_date.time()).and_utc();
+            apply_change(&mut changes, "report_date", &mut report.report_date, report_date, |value| Some(value.format("%Y-%m-%d").to_string()));
+        }
+        if let Some(production_date) = v.required("production_date", patch.production_date).and_then(|date| v.date("production_date", &date)) {
+            apply_change(&mut changes, "production_date", &mut report.production_date, production_date, |value| Some(value.to_string()));
+        }
+        if let Some(team) = v.required("team", patch.team).and_then(|team| v.parse::<Team>("team", &team)) {
+            apply_change(&mut changes, "team", &mut report.team, team.as_str().to_string(), text);
+        }
+        if let Some(time) = v.required("time", patch.time).and_then(|time| v.time("time", &time)) {
+            apply_change(&mut changes, "time", &mut report.time, time, |value| Some(value.format("%H:%M:%S").to_string()));
+        }
+        if let Some(description_type) = v.required("description_type", patch.description_type) {
+            apply_change(&mut changes, "description_type", &mut report.description_type, description_type, text);
+        }
+        if let Some(description_details) = v.required("description_details", patch.description_details) {
+            apply_change(&mut changes, "description_details", &mut report.description_details, description_details, text);
+        }
+        if let Some(quantity) = v.required("quantity", patch.quantity) {
+            v.check("quantity", quantity > 0, "Quantity must be greater than zero");
+            apply_change(&mut changes, "quantity", &mut report.quantity, quantity, |value| Some(value.to_string()));
+        }
+        if let Some(claim_origin) = v.required("claim_origin", patch.claim_origin).and_then(|origin| v.parse::<ClaimOrigin>("claim_origin", &origin)) {
+            apply_change(&mut changes, "claim_origin", &mut report.claim_origin, claim_origin.as_str().to_string(), text);
+        }
+        if let Some(claim_origin_detail) = patch.claim_origin_detail {
+            apply_change(&mut changes, "claim_origin_detail", &mut report.claim_origin_detail, claim_origin_detail, optional_text);
+        }
+        if let Some(valuation) = v.required("valuation", patch.valuation) {
+            v.check("valuation", valuation >= Decimal::ZERO, "Valuation cannot be negative");
+            apply_change(&mut changes, "valuation", &mut report.valuation, valuation, |value| Some(value.to_string()));
+        }
+        if let Some(performance) = patch.performance {
+            apply_change(&mut changes, "performance", &mut report.performance, performance, optional_text);
+        }
+        if let Some(picture_data) = patch.picture_data {
+            // The image itself is not copied into the history, only its size
+            apply_change(&mut changes, "picture_data", &mut report.picture_data, picture_data, |value| {
+                value.as_ref().map(|data| format!("{} bytes", data.len()))
+            });
+        }
+
+        if report.production_date != current.production_date || report.report_date != current.report_date {
+            v.check(
+                "production_date",
+                report.production_date <= report.report_date.date_naive(),
+                "Production date cannot be after the report date",
+            );
+        }
+
+        // Reports keep their line, product, format and description type even after
+        // those are deactivated, only changes are checked
+        if v.is_valid() {
+            if report.line_id != current.line_id {
+                self.check_line_selectable(&mut v, report.line_id).await?;
+            }
+
+            if report.product_id != current.product_id {
+                self.check_product_selectable(&mut v, report.product_id).await?;
+            }
+
+            if report.product_id != current.product_id || report.format_id != current.format_id {
+                self.check_format_allowed(&mut v, report.product_id, report.format_id).await?;
+            }
+
+            if report.description_type != current.description_type {
+                self.check_description_type(&mut v, &report.description_type).await?;
+            }
+        }
+
+        v.finish()?;
+
+        if changes.is_empty() {
+            return Ok(current);
+        }
+
         sqlx::query(
             r#"
             UPDATE non_conformity_reports SET
@@ -531,60 +1111,144 @@ impl ReportsService {
                 valuation = $13,
                 performance = $14,
                 picture_data = $15,
-                updated_at = $16
+                updated_at = $16,
+                version = version + 1
             WHERE id = $17
             "#,
         )
-        .bind(line_id)
-        .bind(product_id)
-        .bind(request.format_id)
-        .bind(report_date)
-        .bind(production_date)
-        .bind(&request.team)
-        .bind(time)
-        .bind(&request.description_type)
-        .bind(&request.description_details)
-        .bind(request.quantity)
-        .bind(&request.claim_origin)
-        .bind(&request.claim_origin_detail)
-        .bind(Decimal::from_f64(request.valuation).unwrap_or_default())
-        .bind(&request.performance)
-        .bind(&request.picture_data)
+        .bind(report.line_id)
+        .bind(report.product_id)
+        .bind(report.format_id)
+        .bind(report.report_date)
+        .bind(report.production_date)
+        .bind(&report.team)
+        .bind(report.time)
+        .bind(&report.description_type)
+        .bind(&report.description_details)
+        .bind(report.quantity)
+        .bind(&report.claim_origin)
+        .bind(&report.claim_origin_detail)
+        .bind(report.valuation)
+        .bind(&report.performance)
+        .bind(&report.picture_data)
         .bind(now)
         .bind(report_id)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
-        // Fetch the updated report with joins
-        let report = sqlx::query_as::<_, NonConformityReport>(
+        let changes: Vec<(Uuid, FieldChange)> = changes.into_iter().map(|change| (report_id, change)).collect();
+        record_field_changes(&mut *tx, &changes, changed_by, now).await?;
+
+        tx.commit().await?;
+
+        self.get_report_by_id(report_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Report not found"))
+    }
+
+    pub async fn get_report_field_history(&self, report_id: Uuid) -> Result<Vec<ReportFieldChange>> {
+        let history = sqlx::query_as::<_, ReportFieldChange>(
             r#"
-            SELECT ncr.*, 
-                   p.designation as product_name,
-                   pl.name as line_name,
-                   CASE 
-                       WHEN f.format_index IS NOT NULL THEN CONCAT(f.format_index, ' ', f.format_unit)
-                       ELSE NULL 
-                   END as format_display
-            FROM non_conformity_reports ncr
-            LEFT JOIN products p ON ncr.product_id = p.id
-            LEFT JOIN production_lines pl ON ncr.line_id = pl.id
-            LEFT JOIN formats f ON ncr.format_id = f.id
-            WHERE ncr.id = $1
+            SELECT h.id, h.report_id, h.field, h.old_value, h.new_value, h.changed_by,
+                   u.username as changed_by_name, h.changed_at
+            FROM report_field_history h
+            LEFT JOIN users u ON h.changed_by = u.id
+            WHERE h.report_id = $1
+            ORDER BY h.changed_at ASC, h.field ASC
             "#,
         )
         .bind(report_id)
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
 
-        Ok(report)
+        Ok(history)
     }
 
     pub async fn delete_report(&self, report_id: Uuid) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM non_conformity_reports WHERE id = $1")
+        let result = sqlx::query("UPDATE non_conformity_reports SET archived_at = NOW() WHERE id = $1 AND archived_at IS NULL")
+            .bind(report_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn restore_report(&self, report_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("UPDATE non_conformity_reports SET archived_at = NULL WHERE id = $1 AND archived_at IS NOT NULL")
             .bind(report_id)
             .execute(&self.pool)
             .await?;
 
         Ok(result.rows_affected() > 0)
     }
-}
\ No newline at end of file
+
+    /// Distinct claim origins of the given reports, archived ones included.
+    pub async fn claim_origins(&self, ids: &[Uuid]) -> Result<Vec<String>> {
+        let origins = sqlx::query_scalar("SELECT DISTINCT claim_origin FROM non_conformity_reports WHERE id = ANY($1)")
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(origins)
+    }
+
+    /// Archives several reports in one transaction.
+    pub async fn delete_multiple_reports(&self, ids: &[Uuid]) -> Result<BulkDeleteResponse> {
+        let mut tx = self.pool.begin().await?;
+
+        let deleted: Vec<Uuid> = sqlx::query_scalar("UPDATE non_conformity_reports SET archived_at = NOW() WHERE id = ANY($1) AND archived_at IS NULL RETURNING id")
+            .bind(ids)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(delete_response(ids, &HashMap::new(), &deleted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups(values: &[i64]) -> Vec<(String, String, Decimal)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (index.to_string(), format!("Produit {}", index), Decimal::from(*value)))
+            .collect()
+    }
+
+    fn cumulative(response: &ParetoResponse) -> Vec<f64> {
+        response.entries.iter().map(|entry| entry.cumulative_percentage).collect()
+    }
+
+    #[test]
+    fn pareto_cumulative_percentages_add_up_to_the_total() {
+        let response = pareto_response(ParetoDimension::Product, ParetoMetric::Count, &groups(&[50, 30, 15, 5]), None);
+
+        assert_eq!(response.total, Decimal::from(100));
+        assert_eq!(cumulative(&response), [50.0, 80.0, 95.0, 100.0]);
+        assert_eq!(response.entries[2].percentage, 15.0);
+        assert!(response.others.is_none());
+    }
+
+    #[test]
+    fn pareto_folds_groups_below_top_n() {
+        let response = pareto_response(ParetoDimension::Line, ParetoMetric::Quantity, &groups(&[4, 3, 2, 1]), Some(2));
+
+        assert_eq!(cumulative(&response), [40.0, 70.0]);
+        let others = response.others.unwrap();
+        assert_eq!((others.rank, others.label.as_str(), others.value), (3, "Autres", Decimal::from(3)));
+        assert_eq!((others.percentage, others.cumulative_percentage), (30.0, 100.0));
+    }
+
+    #[test]
+    fn pareto_shares_are_rounded_and_zero_without_a_total() {
+        let response = pareto_response(ParetoDimension::Format, ParetoMetric::Count, &groups(&[1, 1, 1]), None);
+        assert_eq!(cumulative(&response), [33.33, 66.67, 100.0]);
+
+        let empty = pareto_response(ParetoDimension::Format, ParetoMetric::Valuation, &groups(&[0, 0]), Some(0));
+        assert_eq!(cumulative(&empty), [0.0, 0.0]);
+    }
+}
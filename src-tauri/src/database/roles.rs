@@ -0,0 +1,309 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::database::models::{ClaimOrigin, Permission, Role};
+use super::import::clean_text;
+use super::validation::Validator;
+
+/// Keeps every permission and origin, so there is always a role able to manage users.
+pub const ADMIN_ROLE: &str = "admin";
+
+const ROLE_COLUMNS: &str = r#"
+    r.id, r.name, r.description, r.is_system, r.created_at, r.updated_at,
+    ARRAY(SELECT permission FROM role_permissions WHERE role_id = r.id ORDER BY permission)::TEXT[] AS permissions,
+    ARRAY(SELECT claim_origin FROM role_origins WHERE role_id = r.id ORDER BY claim_origin)::TEXT[] AS origins,
+    (SELECT COUNT(*) FROM user_roles WHERE role_id = r.id) AS user_count
+"#;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRoleRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+    pub origins: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+    pub origins: Vec<String>,
+}
+
+/// Roles, permissions and claim origins a user gets from all of their roles.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserAccess {
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+    pub origins: Vec<String>,
+}
+
+pub struct RolesService {
+    pool: PgPool,
+}
+
+impl RolesService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_all(&self) -> Result<Vec<Role>> {
+        let roles = sqlx::query_as::<_, Role>(&format!("SELECT {} FROM roles r ORDER BY r.is_system DESC, r.name", ROLE_COLUMNS))
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(roles)
+    }
+
+    async fn get_by_id(&self, id: Uuid) -> Result<Option<Role>> {
+        let role = sqlx::query_as::<_, Role>(&format!("SELECT {} FROM roles r WHERE r.id = $1", ROLE_COLUMNS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(role)
+    }
+
+    pub async fn create(&self, request: CreateRoleRequest) -> Result<Role> {
+        let role = self.validate(None, &request.name, &request.permissions, &request.origins).await?;
+        let id = Uuid::new_v4();
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("INSERT INTO roles (id, name, description, is_system, created_at, updated_at) VALUES ($1, $2, $3, FALSE, $4, $4)")
+            .bind(id)
+            .bind(&role.name)
+            .bind(clean_description(request.description))
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+
+        set_role_grants(&mut tx, id, &role.permissions, &role.origins).await?;
+
+        tx.commit().await?;
+
+        self.get_by_id(id).await?.ok_or_else(|| anyhow!("Role not found"))
+    }
+
+    /// Seeded roles keep their name, and the admin role keeps every permission and origin.
+    pub async fn update(&self, request: UpdateRoleRequest) -> Result<Role> {
+        let id = Uuid::parse_str(&request.id).map_err(|e| anyhow!("Invalid UUID: {}", e))?;
+        let current = self.get_by_id(id).await?.ok_or_else(|| anyhow!("Role not found"))?;
+        let role = self.validate(Some(&current), &request.name, &request.permissions, &request.origins).await?;
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE roles SET name = $1, description = $2, updated_at = $3 WHERE id = $4")
+            .bind(&role.name)
+            .bind(clean_description(request.description))
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        set_role_grants(&mut tx, id, &role.permissions, &role.origins).await?;
+        ensure_user_manager_remains(&mut tx).await?;
+
+        tx.commit().await?;
+
+        self.get_by_id(id).await?.ok_or_else(|| anyhow!("Role not found"))
+    }
+
+    /// Seeded roles and roles still given to users cannot be deleted.
+    pub async fn delete(&self, id: Uuid) -> Result<bool> {
+        let Some(role) = self.get_by_id(id).await? else {
+            return Ok(false);
+        };
+
+        if role.is_system {
+            return Err(anyhow!("The {} role is built in and cannot be deleted", role.name));
+        }
+        if role.user_count > 0 {
+            return Err(anyhow!("Role is given to {} user(s) and cannot be deleted", role.user_count));
+        }
+
+        let result = sqlx::query("DELETE FROM roles WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Parses a role request, reporting every invalid field at once.
+    async fn validate(&self, current: Option<&Role>, name: &str, permissions: &[String], origins: &[String]) -> Result<ValidRole> {
+        let mut v = Validator::new();
+        let name = clean_text(name);
+
+        if name.is_empty() {
+            v.error("name", "Cannot be empty");
+        } else if name.chars().count() > 50 {
+            v.error("name", "Cannot exceed 50 characters");
+        } else {
+            let taken: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM roles WHERE LOWER(name) = LOWER($1) AND id IS DISTINCT FROM $2)")
+                .bind(&name)
+                .bind(current.map(|role| role.id))
+                .fetch_one(&self.pool)
+                .await?;
+            v.check("name", !taken, "A role with this name already exists");
+        }
+
+        let permissions: Vec<Permission> = permissions.iter().filter_map(|p| v.parse("permissions", p)).collect();
+        let origins: Vec<ClaimOrigin> = origins.iter().filter_map(|o| v.parse("origins", o)).collect();
+
+        if let Some(current) = current.filter(|role| role.is_system) {
+            v.check("name", name == current.name, "Built in roles cannot be renamed");
+
+            if current.name == ADMIN_ROLE {
+                let complete = Permission::ALL.iter().all(|p| permissions.contains(p))
+                    && ClaimOrigin::ALL.iter().all(|o| origins.contains(o));
+                v.check("permissions", complete, "The admin role keeps every permission and origin");
+            }
+        }
+
+        v.finish()?;
+
+        Ok(ValidRole { name, permissions, origins })
+    }
+}
+
+struct ValidRole {
+    name: String,
+    permissions: Vec<Permission>,
+    origins: Vec<ClaimOrigin>,
+}
+
+fn clean_description(description: Option<String>) -> Option<String> {
+    description.map(|d| clean_text(&d)).filter(|d| !d.is_empty())
+}
+
+async fn set_role_grants(
+    tx: &mut Transaction<'_, Postgres>,
+    role_id: Uuid,
+    permissions: &[Permission],
+    origins: &[ClaimOrigin],
+) -> Result<()> {
+    let permissions: Vec<&str> = permissions.iter().map(Permission::as_str).collect();
+    let origins: Vec<&str> = origins.iter().map(ClaimOrigin::as_str).collect();
+
+    sqlx::query("DELETE FROM role_permissions WHERE role_id = $1")
+        .bind(role_id)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query("INSERT INTO role_permissions (role_id, permission) SELECT $1, UNNEST($2::TEXT[]) ON CONFLICT DO NOTHING")
+        .bind(role_id)
+        .bind(&permissions)
+        .execute(&mut **tx)
+        .await?;
+
+    sqlx::query("DELETE FROM role_origins WHERE role_id = $1")
+        .bind(role_id)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query("INSERT INTO role_origins (role_id, claim_origin) SELECT $1, UNNEST($2::TEXT[]) ON CONFLICT DO NOTHING")
+        .bind(role_id)
+        .bind(&origins)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+/// Replaces a user's roles, refusing names that are not defined.
+pub(crate) async fn set_user_roles(tx: &mut Transaction<'_, Postgres>, user_id: Uuid, roles: &[String]) -> Result<()> {
+    if roles.is_empty() {
+        return Err(anyhow!("At least one role must be specified"));
+    }
+
+    let role_ids: Vec<(Uuid, String)> = sqlx::query_as("SELECT id, name FROM roles WHERE name = ANY($1)")
+        .bind(roles)
+        .fetch_all(&mut **tx)
+        .await?;
+
+    if let Some(unknown) = roles.iter().find(|name| !role_ids.iter().any(|(_, known)| known == *name)) {
+        return Err(anyhow!("Invalid role specified: {}", unknown));
+    }
+
+    let role_ids: Vec<Uuid> = role_ids.into_iter().map(|(id, _)| id).collect();
+
+    sqlx::query("DELETE FROM user_roles WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query("INSERT INTO user_roles (user_id, role_id) SELECT $1, UNNEST($2::UUID[])")
+        .bind(user_id)
+        .bind(&role_ids)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn user_access<'e, E>(executor: E, user_id: Uuid) -> Result<UserAccess>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let access = sqlx::query_as::<_, UserAccess>(
+        r#"
+        SELECT
+            ARRAY(
+                SELECT r.name FROM user_roles ur JOIN roles r ON r.id = ur.role_id
+                WHERE ur.user_id = $1 ORDER BY r.name
+            )::TEXT[] AS roles,
+            ARRAY(
+                SELECT DISTINCT rp.permission FROM user_roles ur JOIN role_permissions rp ON rp.role_id = ur.role_id
+                WHERE ur.user_id = $1 ORDER BY rp.permission
+            )::TEXT[] AS permissions,
+            ARRAY(
+                SELECT DISTINCT ro.claim_origin FROM user_roles ur JOIN role_origins ro ON ro.role_id = ur.role_id
+                WHERE ur.user_id = $1 ORDER BY ro.claim_origin
+            )::TEXT[] AS origins
+        "#
+    )
+    .bind(user_id)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(access)
+}
+
+/// Access of a user who may still use the app, `None` once disabled or archived.
+pub(crate) async fn active_user_access(pool: &PgPool, user_id: Uuid) -> Result<Option<UserAccess>> {
+    let active: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = $1 AND is_active AND archived_at IS NULL)")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+    if !active {
+        return Ok(None);
+    }
+
+    user_access(pool, user_id).await.map(Some)
+}
+
+/// Refuses a change that would leave no active user able to manage users.
+pub(crate) async fn ensure_user_manager_remains(tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+    let remains: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM users u
+            JOIN user_roles ur ON ur.user_id = u.id
+            JOIN role_permissions rp ON rp.role_id = ur.role_id
+            WHERE rp.permission = 'manage_users' AND u.is_active AND u.archived_at IS NULL
+        )
+        "#
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    if !remains {
+        return Err(anyhow!("At least one active user must keep the manage_users permission"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::env;
+use std::str::FromStr;
+
+use super::validation::Validator;
+
+/// Rules new passwords must follow. Defaults can be tightened per site through
+/// `PASSWORD_MIN_LENGTH`, `PASSWORD_REQUIRE_UPPERCASE`, `PASSWORD_REQUIRE_LOWERCASE`,
+/// `PASSWORD_REQUIRE_DIGIT`, `PASSWORD_REQUIRE_SYMBOL`, `PASSWORD_HISTORY` and
+/// `PASSWORD_MAX_AGE_DAYS`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    pub history: usize, // How many previous passwords cannot be reused
+    pub max_age_days: i64, // Passwords older than this must be changed at login, 0 disables expiry
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digit: true,
+            require_symbol: false,
+            history: 5,
+            max_age_days: 0,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            min_length: env_or("PASSWORD_MIN_LENGTH", default.min_length),
+            require_uppercase: env_or("PASSWORD_REQUIRE_UPPERCASE", default.require_uppercase),
+            require_lowercase: env_or("PASSWORD_REQUIRE_LOWERCASE", default.require_lowercase),
+            require_digit: env_or("PASSWORD_REQUIRE_DIGIT", default.require_digit),
+            require_symbol: env_or("PASSWORD_REQUIRE_SYMBOL", default.require_symbol),
+            history: env_or("PASSWORD_HISTORY", default.history),
+            max_age_days: env_or("PASSWORD_MAX_AGE_DAYS", default.max_age_days).max(0),
+        }
+    }
+
+    pub fn is_expired(&self, changed_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.max_age_days > 0 && changed_at + Duration::days(self.max_age_days) <= now
+    }
+
+    /// Adds a `password` error for every rule the password breaks. Reuse of
+    /// previous passwords needs their hashes and is checked by the caller.
+    pub fn check(&self, v: &mut Validator, username: &str, password: &str) {
+        if password.chars().count() < self.min_length {
+            v.error("password", format!("Must be at least {} characters long", self.min_length));
+        }
+        if self.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+            v.error("password", "Must contain an uppercase letter");
+        }
+        if self.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+            v.error("password", "Must contain a lowercase letter");
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            v.error("password", "Must contain a digit");
+        }
+        if self.require_symbol && !password.chars().any(|c| !c.is_alphanumeric() && !c.is_whitespace()) {
+            v.error("password", "Must contain a symbol");
+        }
+        if !username.trim().is_empty() && password.to_lowercase().contains(&username.trim().to_lowercase()) {
+            v.error("password", "Must not contain the username");
+        }
+    }
+}
+
+/// Temporary lockout after repeated failed logins, configured through
+/// `LOGIN_MAX_FAILED_ATTEMPTS` and `LOGIN_LOCKOUT_MINUTES`.
+#[derive(Debug, Clone, Serialize)]
+pub struct LockoutPolicy {
+    pub max_failed_attempts: i32,
+    pub lockout_minutes: i64,
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        Self {
+            max_failed_attempts: 5,
+            lockout_minutes: 15,
+        }
+    }
+}
+
+impl LockoutPolicy {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            max_failed_attempts: env_or("LOGIN_MAX_FAILED_ATTEMPTS", default.max_failed_attempts).max(1),
+            lockout_minutes: env_or("LOGIN_LOCKOUT_MINUTES", default.lockout_minutes).max(1),
+        }
+    }
+}
+
+pub(crate) fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
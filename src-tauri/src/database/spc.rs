@@ -0,0 +1,248 @@
+use sqlx::PgPool;
+use anyhow::{Result, anyhow};
+use uuid::Uuid;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// Weeks shown on a control chart when the caller doesn't ask for a window.
+const DEFAULT_WEEKS: i32 = 26;
+/// Most recent weeks that are checked against the limits. They are left out
+/// of the baseline so a drifting line doesn't widen its own limits.
+const RECENT_WEEKS: usize = 4;
+/// Consecutive increasing points that count as a trend (Nelson rule 3).
+const INCREASING_RUN: usize = 6;
+/// Lowest upper control limit. A line without any NC in its baseline would
+/// otherwise get a limit of 0 and alert on its next single report.
+const MIN_UCL: f64 = 1.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlChartPoint {
+    pub week_start: NaiveDate,
+    pub count: i64,
+    pub above_ucl: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    AboveUcl,
+    IncreasingRun,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlAlert {
+    pub line_id: Uuid,
+    pub line_name: String,
+    pub kind: AlertKind,
+    pub week_start: NaiveDate,
+    pub count: i64,
+    pub message: String,
+}
+
+/// c-chart of weekly NC counts for one production line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineControlChart {
+    pub line_id: Uuid,
+    pub line_name: String,
+    pub center_line: f64,
+    pub upper_control_limit: f64,
+    pub lower_control_limit: f64,
+    pub points: Vec<ControlChartPoint>,
+    pub alerts: Vec<ControlAlert>,
+}
+
+pub struct SpcService {
+    pool: PgPool,
+}
+
+impl SpcService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Only reports from `origins`, the claim origins the user can see, are counted.
+    pub async fn get_line_control_chart(&self, line_id: Uuid, weeks: Option<i32>, origins: &[String]) -> Result<LineControlChart> {
+        let rows = self.fetch_weekly_counts(Some(line_id), weeks, origins).await?;
+
+        if rows.is_empty() {
+            return Err(anyhow!("Production line not found"));
+        }
+
+        let line_name = rows[0].1.clone();
+        let counts = rows.into_iter().map(|(_, _, week, count)| (week, count)).collect();
+
+        Ok(build_chart(line_id, line_name, counts))
+    }
+
+    /// Alerts raised in the recent weeks across all active lines, from reports of `origins`.
+    pub async fn get_active_alerts(&self, weeks: Option<i32>, origins: &[String]) -> Result<Vec<ControlAlert>> {
+        let rows = self.fetch_weekly_counts(None, weeks, origins).await?;
+
+        let mut alerts = Vec::new();
+        let mut current: Option<(Uuid, String)> = None;
+        let mut counts = Vec::new();
+
+        for (line_id, line_name, week, count) in rows {
+            if current.as_ref().map(|(id, _)| *id) != Some(line_id) {
+                if let Some((id, name)) = current.take() {
+                    alerts.extend(build_chart(id, name, std::mem::take(&mut counts)).alerts);
+                }
+                current = Some((line_id, line_name));
+            }
+            counts.push((week, count));
+        }
+
+        if let Some((id, name)) = current {
+            alerts.extend(build_chart(id, name, counts).alerts);
+        }
+
+        Ok(alerts)
+    }
+
+    /// Weekly NC counts per line, including weeks without any report,
+    /// ordered by line then week.
+    async fn fetch_weekly_counts(
+        &self,
+        line_id: Option<Uuid>,
+        weeks: Option<i32>,
+        origins: &[String],
+    ) -> Result<Vec<(Uuid, String, NaiveDate, i64)>> {
+        let weeks = weeks.filter(|w| *w > 0).unwrap_or(DEFAULT_WEEKS);
+
+        let rows = sqlx::query_as::<_, (Uuid, String, NaiveDate, i64)>(
+            r#"
+            SELECT pl.id, pl.name, w.week_start::DATE, COUNT(ncr.id)
+            FROM production_lines pl
+            CROSS JOIN generate_series(
+                date_trunc('week', NOW()) - ($1::INT - 1) * INTERVAL '1 week',
+                date_trunc('week', NOW()),
+                INTERVAL '1 week'
+            ) AS w(week_start)
+            LEFT JOIN non_conformity_reports ncr
+                ON ncr.line_id = pl.id
+                AND ncr.report_date >= w.week_start
+                AND ncr.report_date < w.week_start + INTERVAL '1 week'
+                AND ncr.archived_at IS NULL
+                AND ncr.claim_origin = ANY($3)
+            WHERE ($2::UUID IS NULL AND pl.is_active AND pl.archived_at IS NULL) OR pl.id = $2
+            GROUP BY pl.id, pl.name, w.week_start
+            ORDER BY pl.name, pl.id, w.week_start
+            "#,
+        )
+        .bind(weeks)
+        .bind(line_id)
+        .bind(origins)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+}
+
+fn build_chart(line_id: Uuid, line_name: String, counts: Vec<(NaiveDate, i64)>) -> LineControlChart {
+    let recent_start = counts.len().saturating_sub(RECENT_WEEKS);
+    let baseline = if recent_start > 0 { &counts[..recent_start] } else { &counts[..] };
+
+    let center_line = if baseline.is_empty() {
+        0.0
+    } else {
+        baseline.iter().map(|(_, count)| *count as f64).sum::<f64>() / baseline.len() as f64
+    };
+    let sigma = center_line.sqrt();
+    let upper_control_limit = (center_line + 3.0 * sigma).max(MIN_UCL);
+    let lower_control_limit = (center_line - 3.0 * sigma).max(0.0);
+
+    let points: Vec<ControlChartPoint> = counts
+        .iter()
+        .map(|(week_start, count)| ControlChartPoint {
+            week_start: *week_start,
+            count: *count,
+            above_ucl: *count as f64 > upper_control_limit,
+        })
+        .collect();
+
+    let mut alerts = Vec::new();
+
+    for point in points.iter().skip(recent_start).filter(|p| p.above_ucl) {
+        alerts.push(ControlAlert {
+            line_id,
+            line_name: line_name.clone(),
+            kind: AlertKind::AboveUcl,
+            week_start: point.week_start,
+            count: point.count,
+            message: format!(
+                "{} NC la semaine du {} (limite supérieure {:.1})",
+                point.count, point.week_start, upper_control_limit
+            ),
+        });
+    }
+
+    // A trend only matters if it is still going on in the latest week
+    let mut run = 1;
+    for pair in points.windows(2).rev() {
+        if pair[1].count > pair[0].count {
+            run += 1;
+        } else {
+            break;
+        }
+    }
+
+    if run >= INCREASING_RUN {
+        if let Some(last) = points.last() {
+            alerts.push(ControlAlert {
+                line_id,
+                line_name: line_name.clone(),
+                kind: AlertKind::IncreasingRun,
+                week_start: last.week_start,
+                count: last.count,
+                message: format!("{} semaines consécutives en hausse", run),
+            });
+        }
+    }
+
+    LineControlChart {
+        line_id,
+        line_name,
+        center_line,
+        upper_control_limit,
+        lower_control_limit,
+        points,
+        alerts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chart(counts: &[i64]) -> LineControlChart {
+        let start = NaiveDate::from_ymd_opt(2026, 1, 5).unwrap();
+        let counts = counts
+            .iter()
+            .enumerate()
+            .map(|(week, count)| (start + chrono::Duration::weeks(week as i64), *count))
+            .collect();
+
+        build_chart(Uuid::nil(), "Ligne 1".to_string(), counts)
+    }
+
+    #[test]
+    fn zero_baseline_keeps_a_minimum_upper_limit() {
+        let quiet = chart(&[0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(quiet.center_line, 0.0);
+        assert_eq!(quiet.upper_control_limit, MIN_UCL);
+        assert!(quiet.alerts.is_empty());
+
+        let drifting = chart(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(drifting.alerts.len(), 1);
+        assert_eq!(drifting.alerts[0].kind, AlertKind::AboveUcl);
+    }
+
+    #[test]
+    fn recent_weeks_are_left_out_of_the_baseline() {
+        let chart = chart(&[4, 4, 4, 4, 4, 4, 4, 4, 20, 20, 20, 20]);
+        assert_eq!(chart.center_line, 4.0);
+        assert_eq!(chart.upper_control_limit, 10.0);
+        assert_eq!(chart.alerts.iter().filter(|a| a.kind == AlertKind::AboveUcl).count(), RECENT_WEEKS);
+    }
+}
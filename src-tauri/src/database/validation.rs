@@ -0,0 +1,184 @@
+use chrono::{NaiveDate, NaiveTime};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Every invalid field of a request, so a form can flag them all at once.
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<String> = self
+            .errors
+            .iter()
+            .map(|e| format!("{}: {}", e.field, e.message))
+            .collect();
+        f.write_str(&messages.join("; "))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// Parses the `HH:MM` / `HH:MM:SS` time of a report, `--:--` or an empty value mean midnight.
+pub fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    let value = value.trim();
+    if value == "--:--" || value.is_empty() {
+        return Ok(NaiveTime::from_hms_opt(0, 0, 0).unwrap());
+    }
+
+    let clean: String = value.chars().filter(|c| c.is_ascii_digit() || *c == ':').collect();
+    NaiveTime::parse_from_str(&clean, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(&clean, "%H:%M:%S"))
+        .map_err(|_| format!("Invalid time {} (expected HH:MM or HH:MM:SS)", value))
+}
+
+/// Collects field errors while a request is parsed into typed values. Each
+/// parser returns `None` for an invalid field and keeps going, `finish` then
+/// reports everything that was wrong.
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn error(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn check(&mut self, field: &str, valid: bool, message: &str) {
+        if !valid {
+            self.error(field, message);
+        }
+    }
+
+    /// Unwraps a patch value for a column that cannot be null.
+    pub fn required<T>(&mut self, field: &str, value: Option<Option<T>>) -> Option<T> {
+        match value {
+            Some(None) => {
+                self.error(field, "Cannot be null");
+                None
+            }
+            Some(Some(value)) => Some(value),
+            None => None,
+        }
+    }
+
+    pub fn uuid(&mut self, field: &str, value: &str) -> Option<Uuid> {
+        Uuid::parse_str(value.trim())
+            .map_err(|_| self.error(field, format!("Invalid identifier {}", value)))
+            .ok()
+    }
+
+    pub fn date(&mut self, field: &str, value: &str) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+            .map_err(|_| self.error(field, format!("Invalid date {} (expected YYYY-MM-DD)", value)))
+            .ok()
+    }
+
+    pub fn time(&mut self, field: &str, value: &str) -> Option<NaiveTime> {
+        parse_time(value).map_err(|e| self.error(field, e)).ok()
+    }
+
+    pub fn parse<T: FromStr<Err = String>>(&mut self, field: &str, value: &str) -> Option<T> {
+        T::from_str(value.trim()).map_err(|e| self.error(field, e)).ok()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors { errors: self.errors }.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::Team;
+
+    fn time(hour: u32, minute: u32, second: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, second).unwrap()
+    }
+
+    #[test]
+    fn parse_time_accepts_minutes_and_seconds() {
+        assert_eq!(parse_time("08:30"), Ok(time(8, 30, 0)));
+        assert_eq!(parse_time(" 23:59:58 "), Ok(time(23, 59, 58)));
+        assert_eq!(parse_time("8:05"), Ok(time(8, 5, 0)));
+    }
+
+    #[test]
+    fn parse_time_reads_blank_values_as_midnight() {
+        assert_eq!(parse_time(""), Ok(time(0, 0, 0)));
+        assert_eq!(parse_time("--:--"), Ok(time(0, 0, 0)));
+    }
+
+    #[test]
+    fn parse_time_rejects_invalid_times() {
+        assert_eq!(parse_time("25:00"), Err("Invalid time 25:00 (expected HH:MM or HH:MM:SS)".to_string()));
+        assert!(parse_time("midi").is_err());
+        assert!(parse_time("12:60").is_err());
+    }
+
+    #[test]
+    fn validator_reports_every_invalid_field() {
+        let mut v = Validator::new();
+
+        assert_eq!(v.date("report_date", "2026-02-30"), None);
+        assert_eq!(v.parse::<Team>("team", " B "), Some(Team::B));
+        assert_eq!(v.parse::<Team>("team", "D"), None);
+        assert_eq!(v.uuid("product_id", "product"), None);
+        assert_eq!(v.required::<i32>("quantity", Some(None)), None);
+        assert_eq!(v.required("line_id", Some(Some(3))), Some(3));
+        assert_eq!(v.required::<i32>("format_id", None), None);
+        v.check("quantity", false, "Must be positive");
+        assert!(!v.is_valid());
+
+        let error = v.finish().unwrap_err();
+        let fields: Vec<&str> = error
+            .downcast_ref::<ValidationErrors>()
+            .unwrap()
+            .errors
+            .iter()
+            .map(|e| e.field.as_str())
+            .collect();
+        assert_eq!(fields, ["report_date", "team", "product_id", "quantity", "quantity"]);
+        assert_eq!(
+            error.to_string(),
+            "report_date: Invalid date 2026-02-30 (expected YYYY-MM-DD); team: Invalid team: D; \
+             product_id: Invalid identifier product; quantity: Cannot be null; quantity: Must be positive"
+        );
+    }
+
+    #[test]
+    fn validator_without_errors_finishes() {
+        let mut v = Validator::new();
+        assert_eq!(v.time("report_time", "14:45"), Some(time(14, 45, 0)));
+        v.check("quantity", true, "Must be positive");
+
+        assert!(v.is_valid());
+        assert!(v.finish().is_ok());
+    }
+}
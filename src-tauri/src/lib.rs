@@ -1,19 +1,188 @@
 mod database;
 
-use database::auth::{AuthService, LoginRequest, LoginResponse, UserInfo, PaginationParams as AuthPaginationParams, PaginatedResponse as AuthPaginatedResponse};
-use database::models::{CreateUser, CreateClient, NcDes, Format, NonConformityReport};
+use database::auth::{AuthService, LoginRequest, LoginResponse, PaginationParams as AuthPaginationParams, PaginatedResponse as AuthPaginatedResponse};
+use database::models::{CreateUser, CreateClient, NcDes, Format, LineActivation, LoginAttempt, MergeAudit, NonConformityReport, Permission, ProductFamily, ReportFieldChange, ReportStatusHistory, UserActivity, UserSummary};
 use database::clients::{ClientsService, CreateClientRequest, BulkCreateClientsRequest, UpdateClientRequest, PaginationParams as ClientsPaginationParams, PaginatedResponse as ClientsPaginatedResponse};
-use database::products::{ProductsService, CreateProductRequest, BulkCreateProductsRequest, UpdateProductRequest, PaginationParams as ProductsPaginationParams, PaginatedResponse as ProductsPaginatedResponse};
-use database::lines::{LinesService, CreateLineRequest, BulkCreateLinesRequest, UpdateLineRequest, PaginationParams as LinesPaginationParams, PaginatedResponse as LinesPaginatedResponse};
-use database::reports::{ReportsService, CreateReportRequest, UpdateReportRequest, PaginationParams as ReportsPaginationParams, PaginatedResponse as ReportsPaginatedResponse};
+use database::families::{FamiliesService, CreateFamilyRequest, UpdateFamilyRequest};
+use database::formats::{FormatsService, CreateFormatRequest, UpdateFormatRequest};
+use database::description_types::{DescriptionTypesService, CreateDescriptionTypeRequest, UpdateDescriptionTypeRequest};
+use database::products::{ProductsService, CreateProductRequest, BulkCreateProductsRequest, DeleteMultipleProductsRequest, DuplicateProductPair, UpdateProductRequest, PaginationParams as ProductsPaginationParams, PaginatedResponse as ProductsPaginatedResponse};
+use database::lines::{LinesService, LineActivity, CreateLineRequest, BulkCreateLinesRequest, UpdateLineRequest, PaginationParams as LinesPaginationParams, PaginatedResponse as LinesPaginatedResponse};
+use database::reports::{ReportsService, CreateReportRequest, UpdateReportRequest, ReportPatch, ParetoParams, ParetoResponse, ReportFilters, PaginationParams as ReportsPaginationParams, PaginatedResponse as ReportsPaginatedResponse};
+use database::spc::{SpcService, LineControlChart, ControlAlert};
+use database::costs::{CostsService, CostReportParams, CostOfNonQuality};
+use database::export::{ExportService, ExportSummary};
+use database::pdf::{PdfService, PdfSummary};
+use database::import::{ImportService, ImportEntity, ImportReport};
+use database::bulk::{BulkCreateResponse, BulkDeleteResponse};
+use database::audit::{AuditService, MergeResponse};
+use database::roles::{active_user_access, RolesService, CreateRoleRequest, UpdateRoleRequest, UserAccess};
+use database::concurrency::Conflict;
+use database::validation::{FieldError, ValidationErrors};
+use database::security::PasswordPolicy;
 use database::{Database};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
 use std::sync::Arc;
-use tauri::State;
+use tauri::ipc::Invoke;
+use tauri::{Manager, State};
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
 type DatabaseState = Arc<Mutex<Database>>;
 
+/// The user logged in through the app window.
+#[derive(Debug, Default)]
+struct Session {
+    user_id: Option<Uuid>,
+    must_change_password: bool,
+}
+
+type SessionState = std::sync::Mutex<Session>;
+
+/// Commands available before anyone logs in.
+const PUBLIC_COMMANDS: &[&str] = &[
+    "greet",
+    "minimize_window",
+    "maximize_window",
+    "close_window",
+    "login",
+    "logout",
+    "get_password_policy",
+];
+
+/// Commands that stay available while the logged in user has a password change pending.
+const PASSWORD_CHANGE_COMMANDS: &[&str] = &["change_password"];
+
+/// Wraps the command handler so that every other command is refused without a
+/// logged in user, or until the session's pending password change is done.
+/// Commands check their own permission with [`authorize`].
+fn require_session<R: tauri::Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let command = invoke.message.command();
+        if !PUBLIC_COMMANDS.contains(&command) {
+            let webview = invoke.message.webview();
+            let session = webview.state::<SessionState>();
+            let session = session.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+
+            if session.user_id.is_none() {
+                invoke.resolver.reject("Login required");
+                return true;
+            }
+            if session.must_change_password && !PASSWORD_CHANGE_COMMANDS.contains(&command) {
+                invoke.resolver.reject("Password change required");
+                return true;
+            }
+        }
+
+        handler(invoke)
+    }
+}
+
+/// The logged in user with what their roles allow. It is read again on every
+/// call, so role changes and disabled accounts apply without a new login.
+struct Actor {
+    id: Uuid,
+    access: UserAccess,
+}
+
+impl Actor {
+    fn can(&self, permission: Permission) -> bool {
+        self.access.permissions.iter().any(|p| p == permission.as_str())
+    }
+
+    fn require(&self, permission: Permission) -> Result<(), String> {
+        if self.can(permission) {
+            Ok(())
+        } else {
+            Err(format!("Permission denied: {}", permission.as_str()))
+        }
+    }
+
+    fn require_origin(&self, origin: &str) -> Result<(), String> {
+        if self.access.origins.iter().any(|o| o == origin) {
+            Ok(())
+        } else {
+            Err(format!("Access denied to claim origin {}", origin))
+        }
+    }
+}
+
+async fn current_actor(session: &SessionState, pool: &PgPool) -> Result<Actor, String> {
+    let user_id = session
+        .lock()
+        .map_err(|e| e.to_string())?
+        .user_id
+        .ok_or("Login required")?;
+
+    let access = active_user_access(pool, user_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("Account is disabled")?;
+
+    Ok(Actor { id: user_id, access })
+}
+
+/// Refuses the call unless the logged in user has the permission.
+async fn authorize(session: &SessionState, pool: &PgPool, permission: Permission) -> Result<Actor, String> {
+    let actor = current_actor(session, pool).await?;
+    actor.require(permission)?;
+    Ok(actor)
+}
+
+/// Refuses the call when one of the reports has a claim origin the user may not see.
+async fn require_report_access(actor: &Actor, reports_service: &ReportsService, ids: &[Uuid]) -> Result<(), String> {
+    let origins = reports_service.claim_origins(ids).await.map_err(|e| e.to_string())?;
+    origins.iter().try_for_each(|origin| actor.require_origin(origin))
+}
+
+fn parse_uuids(ids: &[String]) -> Result<Vec<Uuid>, String> {
+    ids.iter()
+        .map(|id| Uuid::parse_str(id).map_err(|e| format!("Invalid UUID {}: {}", id, e)))
+        .collect()
+}
+
+/// Error of the report update commands. A conflict carries the current server
+/// copy of the report so the UI can show a merge dialog instead of a message,
+/// invalid lists every rejected field.
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum ReportUpdateError {
+    Error { message: String },
+    Invalid { message: String, errors: Vec<FieldError> },
+    Conflict { message: String, current: Box<NonConformityReport> },
+}
+
+impl From<String> for ReportUpdateError {
+    fn from(message: String) -> Self {
+        ReportUpdateError::Error { message }
+    }
+}
+
+impl From<anyhow::Error> for ReportUpdateError {
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<Conflict<NonConformityReport>>() {
+            Ok(conflict) => {
+                return ReportUpdateError::Conflict {
+                    message: conflict.message,
+                    current: Box::new(conflict.current),
+                }
+            }
+            Err(error) => error,
+        };
+
+        match error.downcast::<ValidationErrors>() {
+            Ok(invalid) => ReportUpdateError::Invalid {
+                message: invalid.to_string(),
+                errors: invalid.errors,
+            },
+            Err(error) => ReportUpdateError::Error { message: error.to_string() },
+        }
+    }
+}
+
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -38,19 +207,37 @@ async fn close_window(window: tauri::Window) -> Result<(), String> {
 #[tauri::command]
 async fn login(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     request: LoginRequest,
 ) -> Result<LoginResponse, String> {
     let db = db_state.lock().await;
     let auth_service = AuthService::new(db.pool.clone());
 
-    auth_service.login(request).await.map_err(|e| e.to_string())
+    let response = auth_service.login(request).await.map_err(|e| e.to_string())?;
+
+    if let Some(user) = &response.user {
+        *session.lock().map_err(|e| e.to_string())? = Session {
+            user_id: Some(user.id),
+            must_change_password: response.must_change_password,
+        };
+    }
+
+    Ok(response)
+}
+
+#[tauri::command]
+fn logout(session: State<'_, SessionState>) -> Result<(), String> {
+    *session.lock().map_err(|e| e.to_string())? = Session::default();
+    Ok(())
 }
 
 #[tauri::command]
 async fn get_users(
     db_state: State<'_, DatabaseState>,
-) -> Result<Vec<database::models::User>, String> {
+    session: State<'_, SessionState>,
+) -> Result<Vec<UserSummary>, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
     let auth_service = AuthService::new(db.pool.clone());
 
     auth_service
@@ -62,14 +249,17 @@ async fn get_users(
 #[tauri::command]
 async fn get_users_paginated(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     page: i64,
     limit: i64,
     search: Option<String>,
-) -> Result<AuthPaginatedResponse<database::models::User>, String> {
+    include_archived: Option<bool>,
+) -> Result<AuthPaginatedResponse<UserSummary>, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
     let auth_service = AuthService::new(db.pool.clone());
     
-    let params = AuthPaginationParams { page, limit, search };
+    let params = AuthPaginationParams { page, limit, search, include_archived: include_archived.unwrap_or(false) };
     auth_service
         .get_paginated_users(params)
         .await
@@ -77,56 +267,107 @@ async fn get_users_paginated(
 }
 
 #[tauri::command]
-async fn change_password(
+async fn get_login_attempts(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    username: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<LoginAttempt>, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
+    let auth_service = AuthService::new(db.pool.clone());
+
+    auth_service
+        .get_login_attempts(username, limit.unwrap_or(100))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_user_activity(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     user_id: String,
-    current_password: String,
-    new_password: String,
-) -> Result<(), String> {
+    limit: Option<i64>,
+) -> Result<Vec<UserActivity>, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
     let auth_service = AuthService::new(db.pool.clone());
 
     let user_uuid =
         uuid::Uuid::parse_str(&user_id).map_err(|e| format!("Invalid user ID: {}", e))?;
 
     auth_service
-        .change_password(&user_uuid, &current_password, &new_password)
+        .get_user_activity(&user_uuid, limit.unwrap_or(100))
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn update_user_role(
-    db_state: State<'_, DatabaseState>,
-    user_id: String,
-    new_role: String,
-) -> Result<(), String> {
+async fn unlock_user(db_state: State<'_, DatabaseState>, session: State<'_, SessionState>, user_id: String) -> Result<(), String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
     let auth_service = AuthService::new(db.pool.clone());
 
     let user_uuid =
         uuid::Uuid::parse_str(&user_id).map_err(|e| format!("Invalid user ID: {}", e))?;
 
     auth_service
-        .update_user_role(&user_uuid, &new_role)
+        .unlock_user(&user_uuid)
         .await
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn get_password_policy() -> PasswordPolicy {
+    PasswordPolicy::from_env()
+}
+
+#[tauri::command]
+async fn change_password(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    user_id: String,
+    current_password: String,
+    new_password: String,
+) -> Result<UserSummary, String> {
+    let db = db_state.lock().await;
+    let auth_service = AuthService::new(db.pool.clone());
+
+    let user_uuid =
+        uuid::Uuid::parse_str(&user_id).map_err(|e| format!("Invalid user ID: {}", e))?;
+
+    if session.lock().map_err(|e| e.to_string())?.user_id != Some(user_uuid) {
+        return Err("Users can only change their own password".to_string());
+    }
+
+    let user = auth_service
+        .change_password(&user_uuid, &current_password, &new_password)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    session.lock().map_err(|e| e.to_string())?.must_change_password = false;
+
+    Ok(user)
+}
+
 #[tauri::command]
 async fn update_user_roles(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     user_id: String,
     new_roles: Vec<String>,
-) -> Result<(), String> {
+    expected_updated_at: DateTime<Utc>,
+) -> Result<UserSummary, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
     let auth_service = AuthService::new(db.pool.clone());
 
     let user_uuid =
         uuid::Uuid::parse_str(&user_id).map_err(|e| format!("Invalid user ID: {}", e))?;
 
     auth_service
-        .update_user_roles(&user_uuid, new_roles)
+        .update_user_roles(&user_uuid, new_roles, expected_updated_at)
         .await
         .map_err(|e| e.to_string())
 }
@@ -134,57 +375,57 @@ async fn update_user_roles(
 #[tauri::command]
 async fn create_user(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     username: String,
     password: String,
-    role: String,
-    roles: Option<Vec<String>>,
-) -> Result<UserInfo, String> {
+    roles: Vec<String>,
+) -> Result<UserSummary, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
     let auth_service = AuthService::new(db.pool.clone());
 
     let create_user = CreateUser {
         username,
         password,
-        role,
         roles,
     };
 
     auth_service
         .create_user(create_user)
         .await
-        .map(|user| {
-            let role_clone = user.role.clone();
-            UserInfo {
-                id: user.id,
-                username: user.username,
-                role: user.role,
-                roles: user.roles.unwrap_or_else(|| vec![role_clone]),
-            }
-        })
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn update_username(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     user_id: String,
     new_username: String,
-) -> Result<(), String> {
+    expected_updated_at: DateTime<Utc>,
+) -> Result<UserSummary, String> {
     let db = db_state.lock().await;
     let auth_service = AuthService::new(db.pool.clone());
 
     let user_uuid =
         uuid::Uuid::parse_str(&user_id).map_err(|e| format!("Invalid user ID: {}", e))?;
 
+    // Anyone may rename themselves from the profile page
+    let actor = current_actor(&session, &db.pool).await?;
+    if actor.id != user_uuid {
+        actor.require(Permission::ManageUsers)?;
+    }
+
     auth_service
-        .update_username(&user_uuid, &new_username)
+        .update_username(&user_uuid, &new_username, expected_updated_at)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn delete_user(db_state: State<'_, DatabaseState>, user_id: String) -> Result<(), String> {
+async fn delete_user(db_state: State<'_, DatabaseState>, session: State<'_, SessionState>, user_id: String) -> Result<(), String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
     let auth_service = AuthService::new(db.pool.clone());
 
     let user_uuid =
@@ -196,20 +437,146 @@ async fn delete_user(db_state: State<'_, DatabaseState>, user_id: String) -> Res
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn disable_user(db_state: State<'_, DatabaseState>, session: State<'_, SessionState>, user_id: String) -> Result<(), String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
+    let auth_service = AuthService::new(db.pool.clone());
+
+    let user_uuid =
+        uuid::Uuid::parse_str(&user_id).map_err(|e| format!("Invalid user ID: {}", e))?;
+
+    auth_service
+        .disable_user(&user_uuid)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn enable_user(db_state: State<'_, DatabaseState>, session: State<'_, SessionState>, user_id: String) -> Result<(), String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
+    let auth_service = AuthService::new(db.pool.clone());
+
+    let user_uuid =
+        uuid::Uuid::parse_str(&user_id).map_err(|e| format!("Invalid user ID: {}", e))?;
+
+    auth_service
+        .enable_user(&user_uuid)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn restore_user(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    user_id: String,
+) -> Result<bool, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
+    let auth_service = AuthService::new(db.pool.clone());
+
+    let uuid = uuid::Uuid::parse_str(&user_id).map_err(|e| format!("Invalid user ID: {}", e))?;
+
+    auth_service
+        .restore_user(&uuid)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_multiple_users(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    user_ids: Vec<String>,
+    force: Option<bool>,
+) -> Result<BulkDeleteResponse, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
+    let auth_service = AuthService::new(db.pool.clone());
+
+    let uuids = parse_uuids(&user_ids)?;
+
+    auth_service
+        .delete_multiple_users(&uuids, force.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn update_user_password(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     user_id: String,
     new_password: String,
-) -> Result<(), String> {
+    expected_updated_at: DateTime<Utc>,
+) -> Result<UserSummary, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
     let auth_service = AuthService::new(db.pool.clone());
 
     let user_uuid =
         uuid::Uuid::parse_str(&user_id).map_err(|e| format!("Invalid user ID: {}", e))?;
 
     auth_service
-        .update_user_password(&user_uuid, &new_password)
+        .update_user_password(&user_uuid, &new_password, expected_updated_at)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Roles management commands
+#[tauri::command]
+async fn get_roles(db_state: State<'_, DatabaseState>, session: State<'_, SessionState>) -> Result<Vec<database::models::Role>, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
+    let roles_service = RolesService::new(db.pool.clone());
+
+    roles_service.get_all().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_role(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    request: CreateRoleRequest,
+) -> Result<database::models::Role, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
+    let roles_service = RolesService::new(db.pool.clone());
+
+    roles_service
+        .create(request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_role(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    request: UpdateRoleRequest,
+) -> Result<database::models::Role, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
+    let roles_service = RolesService::new(db.pool.clone());
+
+    roles_service
+        .update(request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_role(db_state: State<'_, DatabaseState>, session: State<'_, SessionState>, role_id: String) -> Result<bool, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageUsers).await?;
+    let roles_service = RolesService::new(db.pool.clone());
+
+    let uuid = uuid::Uuid::parse_str(&role_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+
+    roles_service
+        .delete(uuid)
         .await
         .map_err(|e| e.to_string())
 }
@@ -218,12 +585,13 @@ async fn update_user_password(
 #[tauri::command]
 async fn get_lines(
     db_state: State<'_, DatabaseState>,
+    active_only: Option<bool>,
 ) -> Result<Vec<database::models::ProductionLine>, String> {
     let db = db_state.lock().await;
     let lines_service = LinesService::new(db.pool.clone());
 
     lines_service
-        .get_all_lines()
+        .get_all_lines(active_only.unwrap_or(false))
         .await
         .map_err(|e| e.to_string())
 }
@@ -234,11 +602,12 @@ async fn get_lines_paginated(
     page: i64,
     limit: i64,
     search: Option<String>,
+    include_archived: Option<bool>,
 ) -> Result<LinesPaginatedResponse<database::models::ProductionLine>, String> {
     let db = db_state.lock().await;
     let lines_service = LinesService::new(db.pool.clone());
     
-    let params = LinesPaginationParams { page, limit, search };
+    let params = LinesPaginationParams { page, limit, search, include_archived: include_archived.unwrap_or(false) };
     lines_service
         .get_paginated_lines(params)
         .await
@@ -248,9 +617,11 @@ async fn get_lines_paginated(
 #[tauri::command]
 async fn create_line(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     request: CreateLineRequest,
 ) -> Result<database::models::ProductionLine, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
     let lines_service = LinesService::new(db.pool.clone());
 
     lines_service
@@ -262,9 +633,11 @@ async fn create_line(
 #[tauri::command]
 async fn bulk_create_lines(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     request: BulkCreateLinesRequest,
-) -> Result<Vec<database::models::ProductionLine>, String> {
+) -> Result<BulkCreateResponse<database::models::ProductionLine>, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
     let lines_service = LinesService::new(db.pool.clone());
 
     lines_service
@@ -276,9 +649,11 @@ async fn bulk_create_lines(
 #[tauri::command]
 async fn update_line(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     request: UpdateLineRequest,
 ) -> Result<database::models::ProductionLine, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
     let lines_service = LinesService::new(db.pool.clone());
 
     lines_service
@@ -288,8 +663,9 @@ async fn update_line(
 }
 
 #[tauri::command]
-async fn delete_line(db_state: State<'_, DatabaseState>, line_id: String) -> Result<bool, String> {
+async fn delete_line(db_state: State<'_, DatabaseState>, session: State<'_, SessionState>, line_id: String) -> Result<bool, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
     let lines_service = LinesService::new(db.pool.clone());
 
     let uuid = uuid::Uuid::parse_str(&line_id).map_err(|e| format!("Invalid UUID: {}", e))?;
@@ -300,6 +676,99 @@ async fn delete_line(db_state: State<'_, DatabaseState>, line_id: String) -> Res
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn restore_line(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    line_id: String,
+) -> Result<bool, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let lines_service = LinesService::new(db.pool.clone());
+
+    let uuid = uuid::Uuid::parse_str(&line_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+
+    lines_service
+        .restore_line(&uuid)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_line_activation_history(
+    db_state: State<'_, DatabaseState>,
+    line_id: String,
+) -> Result<Vec<LineActivation>, String> {
+    let db = db_state.lock().await;
+    let lines_service = LinesService::new(db.pool.clone());
+
+    let uuid = uuid::Uuid::parse_str(&line_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+
+    lines_service
+        .get_line_activation_history(&uuid)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_line_activity(
+    db_state: State<'_, DatabaseState>,
+    start_date: String,
+    end_date: String,
+) -> Result<Vec<LineActivity>, String> {
+    let db = db_state.lock().await;
+    let lines_service = LinesService::new(db.pool.clone());
+
+    let start = chrono::NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid start date: {}", e))?;
+    let end = chrono::NaiveDate::parse_from_str(&end_date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid end date: {}", e))?;
+
+    lines_service
+        .get_line_activity(start, end)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_multiple_lines(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    line_ids: Vec<String>,
+    force: Option<bool>,
+) -> Result<BulkDeleteResponse, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let lines_service = LinesService::new(db.pool.clone());
+
+    let uuids = parse_uuids(&line_ids)?;
+
+    lines_service
+        .delete_multiple_lines(&uuids, force.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn merge_lines(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    source_id: String,
+    target_id: String,
+) -> Result<MergeResponse<database::models::ProductionLine>, String> {
+    let db = db_state.lock().await;
+    let actor = authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let lines_service = LinesService::new(db.pool.clone());
+
+    let source_uuid = uuid::Uuid::parse_str(&source_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+    let target_uuid = uuid::Uuid::parse_str(&target_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+
+    lines_service
+        .merge_lines(&source_uuid, &target_uuid, Some(actor.id))
+        .await
+        .map_err(|e| e.to_string())
+}
+
 
 // Products management commands
 #[tauri::command]
@@ -321,11 +790,12 @@ async fn get_products_paginated(
     page: i64,
     limit: i64,
     search: Option<String>,
+    include_archived: Option<bool>,
 ) -> Result<ProductsPaginatedResponse<database::models::Product>, String> {
     let db = db_state.lock().await;
     let products_service = ProductsService::new(db.pool.clone());
     
-    let params = ProductsPaginationParams { page, limit, search };
+    let params = ProductsPaginationParams { page, limit, search, include_archived: include_archived.unwrap_or(false) };
     products_service
         .get_paginated_products(params)
         .await
@@ -335,9 +805,11 @@ async fn get_products_paginated(
 #[tauri::command]
 async fn create_product(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     request: CreateProductRequest,
 ) -> Result<database::models::Product, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
     let products_service = ProductsService::new(db.pool.clone());
 
     products_service
@@ -349,9 +821,11 @@ async fn create_product(
 #[tauri::command]
 async fn bulk_create_products(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     request: BulkCreateProductsRequest,
-) -> Result<Vec<database::models::Product>, String> {
+) -> Result<BulkCreateResponse<database::models::Product>, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
     let products_service = ProductsService::new(db.pool.clone());
 
     products_service
@@ -363,9 +837,11 @@ async fn bulk_create_products(
 #[tauri::command]
 async fn update_product(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     request: UpdateProductRequest,
 ) -> Result<database::models::Product, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
     let products_service = ProductsService::new(db.pool.clone());
 
     products_service
@@ -377,9 +853,11 @@ async fn update_product(
 #[tauri::command]
 async fn delete_product(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     product_id: String,
 ) -> Result<bool, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
     let products_service = ProductsService::new(db.pool.clone());
 
     let uuid = uuid::Uuid::parse_str(&product_id).map_err(|e| format!("Invalid UUID: {}", e))?;
@@ -390,47 +868,221 @@ async fn delete_product(
         .map_err(|e| e.to_string())
 }
 
-
-// Clients management commands
 #[tauri::command]
-async fn get_clients(
+async fn restore_product(
     db_state: State<'_, DatabaseState>,
-) -> Result<Vec<database::models::Client>, String> {
+    session: State<'_, SessionState>,
+    product_id: String,
+) -> Result<bool, String> {
     let db = db_state.lock().await;
-    let clients_service = ClientsService::new(db.pool.clone());
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let products_service = ProductsService::new(db.pool.clone());
 
-    clients_service
-        .get_all()
+    let uuid = uuid::Uuid::parse_str(&product_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+
+    products_service
+        .restore_product(&uuid)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn get_clients_paginated(
+async fn delete_multiple_products(
     db_state: State<'_, DatabaseState>,
-    page: i64,
-    limit: i64,
-    search: Option<String>,
-) -> Result<ClientsPaginatedResponse<database::models::Client>, String> {
+    session: State<'_, SessionState>,
+    data: DeleteMultipleProductsRequest,
+) -> Result<BulkDeleteResponse, String> {
     let db = db_state.lock().await;
-    let clients_service = ClientsService::new(db.pool.clone());
-    
-    let params = ClientsPaginationParams { page, limit, search };
-    clients_service
-        .get_paginated(params)
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let products_service = ProductsService::new(db.pool.clone());
+
+    let uuids = parse_uuids(&data.product_ids)?;
+
+    products_service
+        .delete_multiple_products(&uuids, data.force)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn create_client(
+async fn get_product_formats(
     db_state: State<'_, DatabaseState>,
-    request: CreateClientRequest,
-) -> Result<database::models::Client, String> {
+    product_id: String,
+) -> Result<Vec<Format>, String> {
     let db = db_state.lock().await;
-    let clients_service = ClientsService::new(db.pool.clone());
+    let products_service = ProductsService::new(db.pool.clone());
 
-    clients_service
+    let uuid = uuid::Uuid::parse_str(&product_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+
+    products_service
+        .get_product_formats(&uuid)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_product_formats(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    product_id: String,
+    format_ids: Vec<i32>,
+) -> Result<Vec<Format>, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let products_service = ProductsService::new(db.pool.clone());
+
+    let uuid = uuid::Uuid::parse_str(&product_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+
+    products_service
+        .set_product_formats(&uuid, &format_ids)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn find_duplicate_products(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+) -> Result<Vec<DuplicateProductPair>, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let products_service = ProductsService::new(db.pool.clone());
+
+    products_service
+        .find_duplicate_products()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn merge_products(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    source_id: String,
+    target_id: String,
+) -> Result<MergeResponse<database::models::Product>, String> {
+    let db = db_state.lock().await;
+    let actor = authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let products_service = ProductsService::new(db.pool.clone());
+
+    let source_uuid = uuid::Uuid::parse_str(&source_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+    let target_uuid = uuid::Uuid::parse_str(&target_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+
+    products_service
+        .merge_products(&source_uuid, &target_uuid, Some(actor.id))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+
+// Product families management commands
+#[tauri::command]
+async fn get_product_families(
+    db_state: State<'_, DatabaseState>,
+) -> Result<Vec<ProductFamily>, String> {
+    let db = db_state.lock().await;
+    let families_service = FamiliesService::new(db.pool.clone());
+
+    families_service
+        .get_all()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_product_family(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    request: CreateFamilyRequest,
+) -> Result<ProductFamily, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let families_service = FamiliesService::new(db.pool.clone());
+
+    families_service
+        .create(request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_product_family(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    request: UpdateFamilyRequest,
+) -> Result<ProductFamily, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let families_service = FamiliesService::new(db.pool.clone());
+
+    families_service
+        .update(request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_product_family(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    family_id: String,
+) -> Result<bool, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let families_service = FamiliesService::new(db.pool.clone());
+
+    let uuid = uuid::Uuid::parse_str(&family_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+
+    families_service
+        .delete(uuid)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+
+// Clients management commands
+#[tauri::command]
+async fn get_clients(
+    db_state: State<'_, DatabaseState>,
+) -> Result<Vec<database::models::Client>, String> {
+    let db = db_state.lock().await;
+    let clients_service = ClientsService::new(db.pool.clone());
+
+    clients_service
+        .get_all()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_clients_paginated(
+    db_state: State<'_, DatabaseState>,
+    page: i64,
+    limit: i64,
+    search: Option<String>,
+    include_archived: Option<bool>,
+) -> Result<ClientsPaginatedResponse<database::models::Client>, String> {
+    let db = db_state.lock().await;
+    let clients_service = ClientsService::new(db.pool.clone());
+    
+    let params = ClientsPaginationParams { page, limit, search, include_archived: include_archived.unwrap_or(false) };
+    clients_service
+        .get_paginated(params)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_client(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    request: CreateClientRequest,
+) -> Result<database::models::Client, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let clients_service = ClientsService::new(db.pool.clone());
+
+    clients_service
         .create(CreateClient { name: request.name })
         .await
         .map_err(|e| e.to_string())
@@ -439,9 +1091,11 @@ async fn create_client(
 #[tauri::command]
 async fn bulk_create_clients(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     request: BulkCreateClientsRequest,
-) -> Result<Vec<database::models::Client>, String> {
+) -> Result<BulkCreateResponse<database::models::Client>, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
     let clients_service = ClientsService::new(db.pool.clone());
 
     clients_service
@@ -453,13 +1107,15 @@ async fn bulk_create_clients(
 #[tauri::command]
 async fn update_client(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     request: UpdateClientRequest,
 ) -> Result<database::models::Client, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
     let clients_service = ClientsService::new(db.pool.clone());
 
     clients_service
-        .update(request.id, request.name)
+        .update(request.id, request.name, request.expected_updated_at)
         .await
         .map_err(|e| e.to_string())
 }
@@ -467,9 +1123,11 @@ async fn update_client(
 #[tauri::command]
 async fn delete_client(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     client_id: String,
 ) -> Result<bool, String> {
     let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
     let clients_service = ClientsService::new(db.pool.clone());
 
     let uuid = uuid::Uuid::parse_str(&client_id).map_err(|e| format!("Invalid UUID: {}", e))?;
@@ -480,22 +1138,119 @@ async fn delete_client(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn restore_client(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    client_id: String,
+) -> Result<bool, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let clients_service = ClientsService::new(db.pool.clone());
+
+    let uuid = uuid::Uuid::parse_str(&client_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+
+    clients_service
+        .restore(uuid)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_multiple_clients(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    client_ids: Vec<String>,
+    force: Option<bool>,
+) -> Result<BulkDeleteResponse, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let clients_service = ClientsService::new(db.pool.clone());
+
+    let uuids = parse_uuids(&client_ids)?;
+
+    clients_service
+        .delete_multiple(&uuids, force.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn merge_clients(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    source_id: String,
+    target_id: String,
+) -> Result<MergeResponse<database::models::Client>, String> {
+    let db = db_state.lock().await;
+    let actor = authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let clients_service = ClientsService::new(db.pool.clone());
+
+    let source_uuid = uuid::Uuid::parse_str(&source_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+    let target_uuid = uuid::Uuid::parse_str(&target_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+
+    clients_service
+        .merge(source_uuid, target_uuid, Some(actor.id))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_merge_history(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    entity: Option<String>,
+) -> Result<Vec<MergeAudit>, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let audit_service = AuditService::new(db.pool.clone());
+
+    audit_service
+        .get_merge_history(entity)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+
+// Master data import commands
+#[tauri::command]
+async fn import_master_data(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    entity: String,
+    path: String,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let import_service = ImportService::new(db.pool.clone());
+
+    let entity: ImportEntity = entity.parse()?;
+
+    import_service
+        .import_file(entity, &path, dry_run)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 
 // Reports management commands
 #[tauri::command]
 async fn create_report(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     request: CreateReportRequest,
-    reported_by: String,
 ) -> Result<database::models::NonConformityReport, String> {
     let db = db_state.lock().await;
+    let actor = authorize(&session, &db.pool, Permission::CreateReport).await?;
+    actor.require_origin(&request.claim_origin)?;
+    if request.performance.is_some() {
+        actor.require(Permission::EditPerformance)?;
+    }
     let reports_service = ReportsService::new(db.pool.clone());
 
-    let user_uuid = uuid::Uuid::parse_str(&reported_by)
-        .map_err(|e| format!("Invalid user ID: {}", e))?;
-
     reports_service
-        .create_report(request, user_uuid)
+        .create_report(request, actor.id)
         .await
         .map_err(|e| e.to_string())
 }
@@ -503,12 +1258,14 @@ async fn create_report(
 #[tauri::command]
 async fn get_reports(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
 ) -> Result<Vec<database::models::NonConformityReport>, String> {
     let db = db_state.lock().await;
+    let actor = current_actor(&session, &db.pool).await?;
     let reports_service = ReportsService::new(db.pool.clone());
 
     reports_service
-        .get_all_reports()
+        .get_all_reports(&actor.access.origins)
         .await
         .map_err(|e| e.to_string())
 }
@@ -516,6 +1273,7 @@ async fn get_reports(
 #[tauri::command]
 async fn get_reports_paginated(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     page: i64,
     limit: i64,
     search: Option<String>,
@@ -524,14 +1282,15 @@ async fn get_reports_paginated(
     start_date: Option<String>,
     end_date: Option<String>,
     claim_origin: Option<String>,
-    user_accessible_origins: Option<Vec<String>>,
+    family_id: Option<String>,
 ) -> Result<ReportsPaginatedResponse<database::models::NonConformityReport>, String> {
     let db = db_state.lock().await;
+    let actor = current_actor(&session, &db.pool).await?;
     let reports_service = ReportsService::new(db.pool.clone());
     
     println!(
-        "[TAURI] get_reports_paginated received - page={}, limit={}, search={:?}, product_id={:?}, line_id={:?}, start_date={:?}, end_date={:?}, claim_origin={:?}, user_accessible_origins={:?}",
-        page, limit, search, product_id, line_id, start_date, end_date, claim_origin, user_accessible_origins
+        "[TAURI] get_reports_paginated received - page={}, limit={}, search={:?}, product_id={:?}, line_id={:?}, start_date={:?}, end_date={:?}, claim_origin={:?}",
+        page, limit, search, product_id, line_id, start_date, end_date, claim_origin
     );
     
     // Additional debug to check for empty strings vs None
@@ -560,7 +1319,8 @@ async fn get_reports_paginated(
         start_date,
         end_date,
         claim_origin,
-        user_accessible_origins,
+        user_accessible_origins: Some(actor.access.origins),
+        family_id,
     };
     reports_service
         .get_paginated_reports(params)
@@ -569,12 +1329,204 @@ async fn get_reports_paginated(
 }
 
 #[tauri::command]
-async fn get_description_types(db_state: tauri::State<'_, DatabaseState>) -> Result<Vec<NcDes>, String> {
+async fn get_pareto(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    dimension: String,
+    metric: String,
+    top_n: Option<i64>,
+    search: Option<String>,
+    product_id: Option<String>,
+    line_id: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    claim_origin: Option<String>,
+    family_id: Option<String>,
+) -> Result<ParetoResponse, String> {
     let db = db_state.lock().await;
+    let actor = current_actor(&session, &db.pool).await?;
     let reports_service = ReportsService::new(db.pool.clone());
 
+    let params = ParetoParams {
+        dimension,
+        metric,
+        top_n,
+        filters: ReportFilters {
+            search,
+            product_id,
+            line_id,
+            start_date,
+            end_date,
+            claim_origin,
+            user_accessible_origins: Some(actor.access.origins),
+            family_id,
+        },
+    };
     reports_service
-        .get_description_types()
+        .get_pareto(params)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_line_control_chart(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    line_id: String,
+    weeks: Option<i32>,
+) -> Result<LineControlChart, String> {
+    let db = db_state.lock().await;
+    let actor = current_actor(&session, &db.pool).await?;
+    let spc_service = SpcService::new(db.pool.clone());
+
+    let uuid = Uuid::parse_str(&line_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+
+    spc_service
+        .get_line_control_chart(uuid, weeks, &actor.access.origins)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_active_alerts(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    weeks: Option<i32>,
+) -> Result<Vec<ControlAlert>, String> {
+    let db = db_state.lock().await;
+    let actor = current_actor(&session, &db.pool).await?;
+    let spc_service = SpcService::new(db.pool.clone());
+
+    spc_service
+        .get_active_alerts(weeks, &actor.access.origins)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_cost_of_non_quality(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    year: Option<i32>,
+    search: Option<String>,
+    product_id: Option<String>,
+    line_id: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    claim_origin: Option<String>,
+    family_id: Option<String>,
+) -> Result<CostOfNonQuality, String> {
+    let db = db_state.lock().await;
+    let actor = current_actor(&session, &db.pool).await?;
+    let costs_service = CostsService::new(db.pool.clone());
+
+    let params = CostReportParams {
+        year,
+        filters: ReportFilters {
+            search,
+            product_id,
+            line_id,
+            start_date,
+            end_date,
+            claim_origin,
+            user_accessible_origins: Some(actor.access.origins),
+            family_id,
+        },
+    };
+    costs_service
+        .get_cost_of_non_quality(params)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn export_reports_xlsx(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    path: String,
+    search: Option<String>,
+    product_id: Option<String>,
+    line_id: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    claim_origin: Option<String>,
+    family_id: Option<String>,
+) -> Result<ExportSummary, String> {
+    let db = db_state.lock().await;
+    let actor = current_actor(&session, &db.pool).await?;
+    let export_service = ExportService::new(db.pool.clone());
+
+    let filters = ReportFilters {
+        search,
+        product_id,
+        line_id,
+        start_date,
+        end_date,
+        claim_origin,
+        user_accessible_origins: Some(actor.access.origins),
+        family_id,
+    };
+    export_service
+        .export_reports_xlsx(filters, &path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_description_types(db_state: tauri::State<'_, DatabaseState>) -> Result<Vec<NcDes>, String> {
+    let db = db_state.lock().await;
+    let description_types_service = DescriptionTypesService::new(db.pool.clone());
+
+    description_types_service
+        .get_all()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_description_type(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    request: CreateDescriptionTypeRequest,
+) -> Result<NcDes, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let description_types_service = DescriptionTypesService::new(db.pool.clone());
+
+    description_types_service
+        .create(request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_description_type(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    request: UpdateDescriptionTypeRequest,
+) -> Result<NcDes, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let description_types_service = DescriptionTypesService::new(db.pool.clone());
+
+    description_types_service
+        .update(request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_description_type(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    description_type_id: i32,
+) -> Result<bool, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let description_types_service = DescriptionTypesService::new(db.pool.clone());
+
+    description_types_service
+        .delete(description_type_id)
         .await
         .map_err(|e| e.to_string())
 }
@@ -582,10 +1534,58 @@ async fn get_description_types(db_state: tauri::State<'_, DatabaseState>) -> Res
 #[tauri::command]
 async fn get_formats(db_state: tauri::State<'_, DatabaseState>) -> Result<Vec<Format>, String> {
     let db = db_state.lock().await;
-    let reports_service = ReportsService::new(db.pool.clone());
+    let formats_service = FormatsService::new(db.pool.clone());
 
-    reports_service
-        .get_formats()
+    formats_service
+        .get_all()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_format(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    request: CreateFormatRequest,
+) -> Result<Format, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let formats_service = FormatsService::new(db.pool.clone());
+
+    formats_service
+        .create(request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_format(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    request: UpdateFormatRequest,
+) -> Result<Format, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let formats_service = FormatsService::new(db.pool.clone());
+
+    formats_service
+        .update(request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_format(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    format_id: i32,
+) -> Result<bool, String> {
+    let db = db_state.lock().await;
+    authorize(&session, &db.pool, Permission::ManageMasterData).await?;
+    let formats_service = FormatsService::new(db.pool.clone());
+
+    formats_service
+        .delete(format_id)
         .await
         .map_err(|e| e.to_string())
 }
@@ -593,17 +1593,61 @@ async fn get_formats(db_state: tauri::State<'_, DatabaseState>) -> Result<Vec<Fo
 #[tauri::command]
 async fn update_report_status(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     report_id: String,
+    version: i32,
     status: String,
-) -> Result<database::models::NonConformityReport, String> {
+) -> Result<database::models::NonConformityReport, ReportUpdateError> {
     let db = db_state.lock().await;
+    let actor = authorize(&session, &db.pool, Permission::EditPerformance).await?;
     let reports_service = ReportsService::new(db.pool.clone());
 
     let uuid = uuid::Uuid::parse_str(&report_id)
         .map_err(|e| format!("Invalid UUID: {}", e))?;
+    require_report_access(&actor, &reports_service, &[uuid]).await?;
+
+    Ok(reports_service
+        .update_report_status(uuid, version, status, Some(actor.id))
+        .await?)
+}
+
+#[tauri::command]
+async fn get_report_status_history(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    report_id: String,
+) -> Result<Vec<ReportStatusHistory>, String> {
+    let db = db_state.lock().await;
+    let actor = current_actor(&session, &db.pool).await?;
+    let reports_service = ReportsService::new(db.pool.clone());
+
+    let uuid = Uuid::parse_str(&report_id)
+        .map_err(|e| format!("Invalid UUID: {}", e))?;
+    require_report_access(&actor, &reports_service, &[uuid]).await?;
 
     reports_service
-        .update_report_status(uuid, status)
+        .get_report_status_history(uuid)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn generate_report_pdf(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    report_id: String,
+    path: String,
+) -> Result<PdfSummary, String> {
+    let db = db_state.lock().await;
+    let actor = current_actor(&session, &db.pool).await?;
+    let pdf_service = PdfService::new(db.pool.clone());
+
+    let uuid = Uuid::parse_str(&report_id)
+        .map_err(|e| format!("Invalid UUID: {}", e))?;
+    require_report_access(&actor, &ReportsService::new(db.pool.clone()), &[uuid]).await?;
+
+    pdf_service
+        .generate_report_pdf(uuid, &path)
         .await
         .map_err(|e| e.to_string())
 }
@@ -611,28 +1655,33 @@ async fn update_report_status(
 #[tauri::command]
 async fn update_report_performance(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     report_id: String,
+    version: i32,
     performance: String,
-) -> Result<bool, String> {
+) -> Result<NonConformityReport, ReportUpdateError> {
     let db = db_state.lock().await;
+    let actor = authorize(&session, &db.pool, Permission::EditPerformance).await?;
     let reports_service = ReportsService::new(db.pool.clone());
     
     let uuid = Uuid::parse_str(&report_id)
         .map_err(|e| format!("Invalid UUID: {}", e))?;
+    require_report_access(&actor, &reports_service, &[uuid]).await?;
 
-    reports_service
-        .update_report_performance(uuid, performance)
-        .await
-        .map_err(|e| e.to_string())
+    Ok(reports_service
+        .update_report_performance(uuid, version, performance, Some(actor.id))
+        .await?)
 }
 
 #[tauri::command]
 async fn update_report(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     report_id: String,
     request: UpdateReportRequest,
-) -> Result<NonConformityReport, String> {
+) -> Result<NonConformityReport, ReportUpdateError> {
     let db = db_state.lock().await;
+    let actor = authorize(&session, &db.pool, Permission::EditPerformance).await?;
     let reports_service = ReportsService::new(db.pool.clone());
 
     println!(
@@ -649,9 +1698,53 @@ async fn update_report(
 
     let uuid = Uuid::parse_str(&report_id)
         .map_err(|e| format!("Invalid UUID: {}", e))?;
+    require_report_access(&actor, &reports_service, &[uuid]).await?;
+    actor.require_origin(&request.claim_origin)?;
+
+    Ok(reports_service
+        .update_report(uuid, request, Some(actor.id))
+        .await?)
+}
+
+#[tauri::command]
+async fn patch_report(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    report_id: String,
+    patch: ReportPatch,
+) -> Result<NonConformityReport, ReportUpdateError> {
+    let db = db_state.lock().await;
+    let actor = authorize(&session, &db.pool, Permission::EditPerformance).await?;
+    let reports_service = ReportsService::new(db.pool.clone());
+
+    let uuid = Uuid::parse_str(&report_id)
+        .map_err(|e| format!("Invalid UUID: {}", e))?;
+    require_report_access(&actor, &reports_service, &[uuid]).await?;
+    if let Some(Some(origin)) = &patch.claim_origin {
+        actor.require_origin(origin)?;
+    }
+
+    Ok(reports_service
+        .patch_report(uuid, patch, Some(actor.id))
+        .await?)
+}
+
+#[tauri::command]
+async fn get_report_field_history(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    report_id: String,
+) -> Result<Vec<ReportFieldChange>, String> {
+    let db = db_state.lock().await;
+    let actor = current_actor(&session, &db.pool).await?;
+    let reports_service = ReportsService::new(db.pool.clone());
+
+    let uuid = Uuid::parse_str(&report_id)
+        .map_err(|e| format!("Invalid UUID: {}", e))?;
+    require_report_access(&actor, &reports_service, &[uuid]).await?;
 
     reports_service
-        .update_report(uuid, request)
+        .get_report_field_history(uuid)
         .await
         .map_err(|e| e.to_string())
 }
@@ -659,13 +1752,16 @@ async fn update_report(
 #[tauri::command]
 async fn delete_report(
     db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
     report_id: String,
 ) -> Result<bool, String> {
     let db = db_state.lock().await;
+    let actor = authorize(&session, &db.pool, Permission::EditPerformance).await?;
     let reports_service = ReportsService::new(db.pool.clone());
 
     let uuid = uuid::Uuid::parse_str(&report_id)
         .map_err(|e| format!("Invalid UUID: {}", e))?;
+    require_report_access(&actor, &reports_service, &[uuid]).await?;
 
     reports_service
         .delete_report(uuid)
@@ -673,6 +1769,44 @@ async fn delete_report(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn restore_report(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    report_id: String,
+) -> Result<bool, String> {
+    let db = db_state.lock().await;
+    let actor = authorize(&session, &db.pool, Permission::EditPerformance).await?;
+    let reports_service = ReportsService::new(db.pool.clone());
+
+    let uuid = uuid::Uuid::parse_str(&report_id).map_err(|e| format!("Invalid UUID: {}", e))?;
+    require_report_access(&actor, &reports_service, &[uuid]).await?;
+
+    reports_service
+        .restore_report(uuid)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_multiple_reports(
+    db_state: State<'_, DatabaseState>,
+    session: State<'_, SessionState>,
+    report_ids: Vec<String>,
+) -> Result<BulkDeleteResponse, String> {
+    let db = db_state.lock().await;
+    let actor = authorize(&session, &db.pool, Permission::EditPerformance).await?;
+    let reports_service = ReportsService::new(db.pool.clone());
+
+    let uuids = parse_uuids(&report_ids)?;
+    require_report_access(&actor, &reports_service, &uuids).await?;
+
+    reports_service
+        .delete_multiple_reports(&uuids)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -690,49 +1824,99 @@ pub fn run() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
         .manage(db_state)
-        .invoke_handler(tauri::generate_handler![
+        .manage(SessionState::default())
+        .invoke_handler(require_session(tauri::generate_handler![
             greet,
             minimize_window,
             maximize_window,
             close_window,
             login,
+            logout,
             get_users,
             get_users_paginated,
+            get_login_attempts,
+            get_user_activity,
+            unlock_user,
+            get_password_policy,
             change_password,
-            update_user_role,
             update_user_roles,
             create_user,
             update_username,
             delete_user,
+            disable_user,
+            enable_user,
+            restore_user,
+            delete_multiple_users,
             update_user_password,
+            get_roles,
+            create_role,
+            update_role,
+            delete_role,
             get_lines,
             get_lines_paginated,
             create_line,
             update_line,
             delete_line,
+            restore_line,
+            get_line_activation_history,
+            get_line_activity,
+            delete_multiple_lines,
+            merge_lines,
             bulk_create_lines,
             get_clients,
             get_clients_paginated,
             create_client,
             update_client,
             delete_client,
+            restore_client,
+            delete_multiple_clients,
+            merge_clients,
+            get_merge_history,
             bulk_create_clients,
             get_products,
             get_products_paginated,
             create_product,
             update_product,
             delete_product,
+            restore_product,
+            delete_multiple_products,
+            get_product_formats,
+            set_product_formats,
+            find_duplicate_products,
+            merge_products,
+            get_product_families,
+            create_product_family,
+            update_product_family,
+            delete_product_family,
             bulk_create_products,
+            import_master_data,
             create_report,
             get_reports,
             get_reports_paginated,
+            get_pareto,
+            get_line_control_chart,
+            get_active_alerts,
+            get_cost_of_non_quality,
+            export_reports_xlsx,
             get_description_types,
+            create_description_type,
+            update_description_type,
+            delete_description_type,
             get_formats,
+            create_format,
+            update_format,
+            delete_format,
             update_report_status,
+            get_report_status_history,
+            generate_report_pdf,
             update_report_performance,
+            patch_report,
+            get_report_field_history,
             update_report,
-            delete_report
-        ])
+            delete_report,
+            restore_report,
+            delete_multiple_reports
+        ]))
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -24,12 +24,12 @@ function App() {
         <Route path="/" element={<Navigate to="/reports" replace />} />
 
         {/* Dynamically generate protected routes */}
-        {APP_ROUTES.map(({ path, element: Element, allowedRoles }) => (
+        {APP_ROUTES.map(({ path, element: Element, permission }) => (
           <Route
             key={path}
             path={path}
             element={
-              <ProtectedRoute allowedRoles={allowedRoles}>
+              <ProtectedRoute permission={permission}>
                 <Element />
               </ProtectedRoute>
             }
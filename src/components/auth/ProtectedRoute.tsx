@@ -1,14 +1,14 @@
 import React from 'react';
 import { Navigate, useLocation } from 'react-router-dom';
 import { useAuth } from '../../contexts/AuthContext';
-import { UserRole, hasAnyRole } from '../../types/auth';
+import { Permission, hasPermission } from '../../types/auth';
 
 interface ProtectedRouteProps {
-  allowedRoles: UserRole[];
+  permission?: Permission;
   children: React.ReactNode;
 }
 
-const ProtectedRoute: React.FC<ProtectedRouteProps> = ({ allowedRoles, children }) => {
+const ProtectedRoute: React.FC<ProtectedRouteProps> = ({ permission, children }) => {
   const { user, isAuthenticated } = useAuth();
   const location = useLocation();
 
@@ -17,10 +17,12 @@ const ProtectedRoute: React.FC<ProtectedRouteProps> = ({ allowedRoles, children
     return <Navigate to="/login" state={{ from: location }} replace />;
   }
 
-  // Check if user has any of the allowed roles (supports multiple roles)
-  const hasPermission = hasAnyRole(user, allowedRoles);
+  // Nothing but the password form until an admin-set or expired password is replaced
+  if (user?.mustChangePassword && location.pathname !== '/profile') {
+    return <Navigate to="/profile" state={{ from: location }} replace />;
+  }
 
-  if (!hasPermission) {
+  if (permission && !hasPermission(user, permission)) {
     // Redirect to dashboard or unauthorized page
     return <Navigate to="/unauthorized" state={{ from: location }} replace />;
   }
@@ -3,13 +3,13 @@ import {
   faChevronLeft,
   faChevronRight,
 } from "@fortawesome/free-solid-svg-icons";
-import { User, hasAnyRole } from '../types/auth';
+import { User, hasPermission } from '../types/auth';
 import { APP_ROUTES } from "./routes";
 
-// Helper function to filter navigation items based on user roles (supports multiple roles)
+// Helper function to filter navigation items based on the permissions the user's roles give
 export const getFilteredNavItems = (user: User) => {
   return APP_ROUTES.filter(
-    (route) => route.showInNav && route.label && route.icon && hasAnyRole(user, route.allowedRoles)
+    (route) => route.showInNav && route.label && route.icon && (!route.permission || hasPermission(user, route.permission))
   );
 };
 
@@ -1,11 +1,12 @@
-import { faUser, faTachometerAlt, faIndustry, faBoxes, faUsers, faBuilding, faClipboardList, faExclamationTriangle, faCog } from "@fortawesome/free-solid-svg-icons";
-import { ROLES, UserRole } from '../types/auth';
+import { faUser, faTachometerAlt, faIndustry, faBoxes, faUsers, faUserShield, faBuilding, faClipboardList, faExclamationTriangle, faCog } from "@fortawesome/free-solid-svg-icons";
+import { PERMISSIONS, Permission } from '../types/auth';
 import DashboardPage from '../pages/DashboardPage';
 import ProfilePage from '../pages/ProfilePage';
 import LinesPage from '../pages/LinesPage';
 import ProductsPage from '../pages/ProductsPage';
 import ClientsPage from '../pages/ClientsPage';
 import UsersPage from '../pages/UsersPage';
+import RolesPage from '../pages/RolesPage';
 import { NewReportPage } from '../pages/NewReportPage';
 import { ReportsPage } from '../pages/ReportsPage';
 import SettingsPage from "../pages/SettingsPage";
@@ -13,7 +14,7 @@ import SettingsPage from "../pages/SettingsPage";
 export interface AppRoute {
   path: string;
   element: React.ComponentType;
-  allowedRoles: UserRole[];
+  permission?: Permission; // Every signed in user can open routes without one
   label?: string;
   icon?: any;
   showInNav?: boolean;
@@ -25,7 +26,7 @@ export const APP_ROUTES: AppRoute[] = [
     element: DashboardPage,
     label: "Tableau de bord",
     icon: faTachometerAlt,
-    allowedRoles: [ROLES.ADMIN],
+    permission: PERMISSIONS.MANAGE_MASTER_DATA,
     showInNav: true,
   },
   {
@@ -33,7 +34,6 @@ export const APP_ROUTES: AppRoute[] = [
     element: ProfilePage,
     label: "Profil",
     icon: faUser,
-    allowedRoles: Object.values(ROLES),
     showInNav: true,
   },
   {
@@ -41,7 +41,7 @@ export const APP_ROUTES: AppRoute[] = [
     element: LinesPage,
     label: "Lignes",
     icon: faIndustry,
-    allowedRoles: [ROLES.ADMIN],
+    permission: PERMISSIONS.MANAGE_MASTER_DATA,
     showInNav: true,
   },
   {
@@ -49,7 +49,7 @@ export const APP_ROUTES: AppRoute[] = [
     element: ProductsPage,
     label: "Produits",
     icon: faBoxes,
-    allowedRoles: [ROLES.ADMIN],
+    permission: PERMISSIONS.MANAGE_MASTER_DATA,
     showInNav: true,
   },
   {
@@ -57,7 +57,6 @@ export const APP_ROUTES: AppRoute[] = [
     element: ReportsPage,
     label: "Rapports",
     icon: faClipboardList,
-    allowedRoles: Object.values(ROLES),
     showInNav: true,
   },
   {
@@ -65,7 +64,7 @@ export const APP_ROUTES: AppRoute[] = [
     element: NewReportPage,
     label: "Nouveau rapport",
     icon: faExclamationTriangle,
-    allowedRoles: Object.values(ROLES),
+    permission: PERMISSIONS.CREATE_REPORT,
     showInNav: true,
   },
   {
@@ -73,7 +72,7 @@ export const APP_ROUTES: AppRoute[] = [
     element: ClientsPage,
     label: "Clients",
     icon: faBuilding,
-    allowedRoles: [ROLES.ADMIN],
+    permission: PERMISSIONS.MANAGE_MASTER_DATA,
     showInNav: true,
   },
   {
@@ -81,7 +80,15 @@ export const APP_ROUTES: AppRoute[] = [
     element: UsersPage,
     label: "Utilisateurs",
     icon: faUsers,
-    allowedRoles: [ROLES.ADMIN],
+    permission: PERMISSIONS.MANAGE_USERS,
+    showInNav: true,
+  },
+  {
+    path: "/roles",
+    element: RolesPage,
+    label: "Rôles",
+    icon: faUserShield,
+    permission: PERMISSIONS.MANAGE_USERS,
     showInNav: true,
   },
   {
@@ -89,7 +96,7 @@ export const APP_ROUTES: AppRoute[] = [
     element: SettingsPage,
     label: "Paramètres",
     icon: faCog,
-    allowedRoles: [ROLES.ADMIN],
+    permission: PERMISSIONS.MANAGE_MASTER_DATA,
     showInNav: false,
   },
 ];
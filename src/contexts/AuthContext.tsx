@@ -21,25 +21,30 @@ export const AuthProvider: React.FC<AuthProviderProps> = ({ children }) => {
   const [user, setUser] = useState<User | null>(null);
   const [isLoading, setIsLoading] = useState(true);
 
-  // Check authentication status on app load
+  // Check authentication status on app load. The backend forgets the login when
+  // the app closes, so it is only kept for the window's session, across reloads.
   useEffect(() => {
     const checkAuth = () => {
-      const isAuth = localStorage.getItem('isAuthenticated');
-      const userData = localStorage.getItem('userData');
+      // Logins kept by earlier versions would outlive the backend session
+      localStorage.removeItem('isAuthenticated');
+      localStorage.removeItem('userData');
+      const isAuth = sessionStorage.getItem('isAuthenticated');
+      const userData = sessionStorage.getItem('userData');
       
       if (isAuth === 'true' && userData) {
         try {
           const user = JSON.parse(userData);
-          // Ensure roles array exists for backward compatibility
-          const userWithRoles = {
-            ...user,
-            roles: user.roles || [user.role]
-          };
-          setUser(userWithRoles);
+          // Sessions saved by older versions have to sign in again
+          if (Array.isArray(user.permissions) && Array.isArray(user.origins) && user.updated_at) {
+            setUser(user);
+          } else {
+            sessionStorage.removeItem('isAuthenticated');
+            sessionStorage.removeItem('userData');
+          }
         } catch (error) {
           console.error('Error parsing user data:', error);
-          localStorage.removeItem('isAuthenticated');
-          localStorage.removeItem('userData');
+          sessionStorage.removeItem('isAuthenticated');
+          sessionStorage.removeItem('userData');
         }
       }
       setIsLoading(false);
@@ -60,21 +65,24 @@ export const AuthProvider: React.FC<AuthProviderProps> = ({ children }) => {
 
       const response = await invoke<{
         success: boolean;
-        user?: { id: string; username: string; role: string; roles: string[] };
+        user?: Omit<User, 'mustChangePassword'>;
         message: string;
+        must_change_password: boolean;
       }>('login', {
         request: { username, password }
       });
 
       if (response.success && response.user) {
-        // Ensure roles array exists for backward compatibility
-        const userWithRoles = {
+        const signedIn: User = {
           ...response.user,
-          roles: response.user.roles || [response.user.role]
+          mustChangePassword: response.must_change_password
         };
-        setUser(userWithRoles);
-        localStorage.setItem('isAuthenticated', 'true');
-        localStorage.setItem('userData', JSON.stringify(userWithRoles));
+        setUser(signedIn);
+        // A pending password change is not remembered, reloading asks for a new login
+        if (!signedIn.mustChangePassword) {
+          sessionStorage.setItem('isAuthenticated', 'true');
+          sessionStorage.setItem('userData', JSON.stringify(signedIn));
+        }
         setIsLoading(false);
         return { success: true };
       } else {
@@ -92,15 +100,19 @@ export const AuthProvider: React.FC<AuthProviderProps> = ({ children }) => {
     setUser(prev => {
       if (!prev) return prev;
       const updatedUser = { ...prev, ...userData };
-      localStorage.setItem('userData', JSON.stringify(updatedUser));
+      if (!updatedUser.mustChangePassword) {
+        sessionStorage.setItem('isAuthenticated', 'true');
+        sessionStorage.setItem('userData', JSON.stringify(updatedUser));
+      }
       return updatedUser;
     });
   };
 
   const logout = () => {
+    invoke('logout').catch(error => console.error('Logout error:', error));
     setUser(null);
-    localStorage.removeItem('isAuthenticated');
-    localStorage.removeItem('userData');
+    sessionStorage.removeItem('isAuthenticated');
+    sessionStorage.removeItem('userData');
     // Clean up any legacy keys
     localStorage.removeItem('username');
   };
@@ -92,6 +92,7 @@ const ClientsPage: React.FC = () => {
         await invoke("update_client", {
           request: {
             id: editingClient.id,
+            expected_updated_at: editingClient.updated_at,
             name: formData.name,
           },
         });
@@ -90,6 +90,7 @@ const LinesPage: React.FC = () => {
         await invoke('update_line', {
           request: {
             id: editingLine.id,
+            expected_updated_at: editingLine.updated_at,
             ...formData,
           },
         });
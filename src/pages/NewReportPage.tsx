@@ -9,7 +9,7 @@ import DatePicker from '../components/ui/DatePicker';
 import IntuitiveTimePicker from '../components/ui/IntuitiveTimePicker';
 import Dialog from '../components/ui/Dialog';
 import PictureUpload from '../components/ui/PictureUpload';
-import { ROLES } from '../types/auth';
+import { CLAIM_ORIGINS, CLAIM_ORIGIN_LABELS, PERMISSIONS, hasPermission } from '../types/auth';
 import wilayas from '../data/algerianWilayas.json';
 
 interface Client {
@@ -111,42 +111,20 @@ export const NewReportPage: React.FC = () => {
     loadInitialData();
   }, []);
 
-  // Initialize claim origin based on user role (for single role users)
+  // Initialize claim origin for users limited to a single origin
   useEffect(() => {
-    if (user) {
-      const userRoles = user.roles && user.roles.length > 0 ? user.roles : [user.role];
-      
-      // Only auto-set for single role users with non-admin/non-performance roles
-      if (userRoles.length === 1) {
-        const role = userRoles[0];
-        let initialClaimOrigin = '';
-        
-        if (role !== ROLES.PERFORMANCE && role !== ROLES.ADMIN) {
-          switch (role) {
-            case ROLES.SITE01:
-            case ROLES.SITE02:
-            case ROLES.RECLAMATION_CLIENT:
-            case ROLES.RETOUR_CLIENT:
-            case ROLES.CONSOMMATEUR:
-              initialClaimOrigin = role;
-              break;
-          }
-          
-          if (initialClaimOrigin) {
-            setFormData(prev => ({
-              ...prev,
-              claim_origin: initialClaimOrigin
-            }));
-          }
-        }
-      }
+    if (user && user.origins.length === 1) {
+      setFormData(prev => ({
+        ...prev,
+        claim_origin: user.origins[0]
+      }));
     }
   }, [user]);
 
   const loadInitialData = async () => {
     try {
       const [linesData, productsData, formatsData, typesData, clientsData] = await Promise.all([
-        invoke<ProductionLine[]>('get_lines'),
+        invoke<ProductionLine[]>('get_lines', { activeOnly: true }),
         invoke<Product[]>('get_products'),
         invoke<Format[]>('get_formats'),
         invoke<DescriptionType[]>('get_description_types'),
@@ -209,8 +187,9 @@ export const NewReportPage: React.FC = () => {
     }
     if (!formData.team) newErrors.team = "L'équipe est requise";
     if (!formData.description_type) newErrors.description_type = 'Le type de description est requis';
-    // Skip description_details validation for site01/site02 users or when site01/site02 is selected
-    const isSiteUser = ['site01', 'site02'].includes(user?.role || '');
+    // Skip description_details validation for users limited to site01/site02 or when site01/site02 is selected
+    const isSiteUser = !!user && user.origins.length > 0
+      && user.origins.every(origin => origin === CLAIM_ORIGINS.SITE01 || origin === CLAIM_ORIGINS.SITE02);
     const isSiteSelected = ['site01', 'site02'].includes(formData.claim_origin);
     if (!isSiteUser && !isSiteSelected && !formData.description_details.trim()) {
       newErrors.description_details = 'Veuillez fournir des détails de description';
@@ -275,13 +254,12 @@ export const NewReportPage: React.FC = () => {
         })(), // "Détail de la réclamation" maps to claim_origin_detail
         valuation: 0,
         // Only include performance field if user has permission
-        performance: (user?.role === 'performance' || user?.role === 'admin') ? formData.performance : undefined,
+        performance: hasPermission(user, PERMISSIONS.EDIT_PERFORMANCE) ? formData.performance : undefined,
         picture_data: formData.picture_data
       };
 
       await invoke('create_report', {
-        request: reportData,
-        reportedBy: user?.id
+        request: reportData
       });
 
       // Reset form
@@ -315,47 +293,20 @@ export const NewReportPage: React.FC = () => {
   };
 
 
-  // Render claim origin field based on user roles
+  // Render claim origin field based on the origins the user's roles give
   const renderClaimOriginField = () => {
     if (!user) return null;
     
-    const userRoles = user.roles && user.roles.length > 0 ? user.roles : [user.role];
-    const hasAdminOrPerformance = userRoles.includes(ROLES.ADMIN) || userRoles.includes(ROLES.PERFORMANCE);
-    
-    // Get available claim origin options based on user roles
-    const getAvailableOptions = () => {
-      if (hasAdminOrPerformance) {
-        // Admin/Performance can select any origin
-        return [
-          { value: ROLES.RECLAMATION_CLIENT, label: ROLES.RECLAMATION_CLIENT },
-          { value: ROLES.RETOUR_CLIENT, label: ROLES.RETOUR_CLIENT },
-          { value: 'site01', label: 'Site 01' },
-          { value: 'site02', label: 'Site 02' },
-          { value: ROLES.CONSOMMATEUR, label: ROLES.CONSOMMATEUR },
-        ];
-      } else {
-        // Regular users can only select from their assigned roles
-        return userRoles
-          .filter(role => [
-            ROLES.RECLAMATION_CLIENT,
-            ROLES.RETOUR_CLIENT,
-            ROLES.SITE01,
-            ROLES.SITE02,
-            ROLES.CONSOMMATEUR
-          ].includes(role as any))
-          .map(role => ({
-            value: role,
-            label: role === ROLES.SITE01 ? 'Site 01' :
-                   role === ROLES.SITE02 ? 'Site 02' :
-                   role
-          }));
-      }
-    };
+    // Only the origins the user has access to can be selected
+    const getAvailableOptions = () =>
+      Object.values(CLAIM_ORIGINS)
+        .filter(origin => user.origins.includes(origin))
+        .map(origin => ({ value: origin, label: CLAIM_ORIGIN_LABELS[origin] }));
     
     const availableOptions = getAvailableOptions();
     
-    // If user has only one valid role, show it as disabled
-    if (availableOptions.length === 1 && !hasAdminOrPerformance) {
+    // If user has only one origin, show it as disabled
+    if (availableOptions.length === 1) {
       return (
         <Select
           label="Origine de la réclamation *"
@@ -368,7 +319,7 @@ export const NewReportPage: React.FC = () => {
         />
       );
     }
-    // Show selectable dropdown for users with multiple roles or admin/performance
+    // Show selectable dropdown for users with several origins
     return (
       <Select
         label="Origine de la réclamation *"
@@ -578,7 +529,7 @@ export const NewReportPage: React.FC = () => {
                   disabled
                   className="w-full bg-gray-100"
                 />
-              ) : ((formData.claim_origin === ROLES.RECLAMATION_CLIENT || formData.claim_origin === ROLES.RETOUR_CLIENT
+              ) : ((formData.claim_origin === CLAIM_ORIGINS.RECLAMATION_CLIENT || formData.claim_origin === CLAIM_ORIGINS.RETOUR_CLIENT
                 ) && clients.length > 0) ? (
                 <>
                   <SearchableSelect
@@ -606,7 +557,7 @@ export const NewReportPage: React.FC = () => {
                     <p className="mt-1 text-sm text-red-500">{errors.description_details}</p>
                   )}
                 </>
-              ) : formData.claim_origin === ROLES.CONSOMMATEUR ? (
+              ) : formData.claim_origin === CLAIM_ORIGINS.CONSOMMATEUR ? (
                 <Select
                   value={formData.claim_origin_detail || ''}
                   onChange={(value) => handleInputChange('claim_origin_detail', value)}
@@ -97,6 +97,7 @@ const ProductsPage: React.FC = () => {
         await invoke("update_product", {
           request: {
             id: editingProduct.id,
+            expected_updated_at: editingProduct.updated_at,
             ...formData,
           },
         });
@@ -22,7 +22,7 @@ export const ProfilePage: React.FC = () => {
   const [passwordError, setPasswordError] = useState('');
   
   // Active tab state
-  const [activeTab, setActiveTab] = useState<'profile' | 'password'>('profile');
+  const [activeTab, setActiveTab] = useState<'profile' | 'password'>(user?.mustChangePassword ? 'password' : 'profile');
 
   useEffect(() => {
     if (user?.username) {
@@ -47,13 +47,14 @@ export const ProfilePage: React.FC = () => {
     try {
       setIsUpdatingProfile(true);
       
-      await invoke('update_username', {
+      const updated = await invoke<{ username: string; updated_at: string }>('update_username', {
         userId: user?.id,
         newUsername: username.trim(),
+        expectedUpdatedAt: user?.updated_at,
       });
       
-      // Update the user context and localStorage
-      updateUser({ username: username.trim() });
+      // Update the user context and the stored login
+      updateUser({ username: updated.username, updated_at: updated.updated_at });
       
       addToast('Nom d\'utilisateur mis à jour avec succès', 'success');
     } catch (error) {
@@ -87,13 +88,14 @@ export const ProfilePage: React.FC = () => {
     try {
       setIsUpdatingPassword(true);
       
-      await invoke('change_password', {
+      const updated = await invoke<{ updated_at: string }>('change_password', {
         userId: user?.id,
         currentPassword,
         newPassword,
       });
       
       addToast('Mot de passe mis à jour avec succès', 'success');
+      updateUser({ mustChangePassword: false, updated_at: updated.updated_at });
       setCurrentPassword('');
       setNewPassword('');
       setConfirmPassword('');
@@ -133,7 +135,7 @@ export const ProfilePage: React.FC = () => {
                   {username || user?.username || 'Utilisateur'}
                 </h2>
                 <p className="text-muted-foreground text-sm">
-                  {user?.role || 'Membre'}
+                  {user?.roles.join(', ') || 'Membre'}
                 </p>
               </div>
             </div>
@@ -208,11 +210,11 @@ export const ProfilePage: React.FC = () => {
                       />
                       
                       <Input
-                        label="Rôle"
+                        label="Rôles"
                         type="text"
-                        value={user?.role || 'Membre'}
+                        value={user?.roles.join(', ') || 'Membre'}
                         disabled
-                        helperText="Contactez un administrateur pour modifier votre rôle"
+                        helperText="Contactez un administrateur pour modifier vos rôles"
                       />
                     </div>
                     
@@ -248,6 +250,14 @@ export const ProfilePage: React.FC = () => {
                   <p className="text-muted-foreground text-sm mb-6">
                     Assurez-vous que votre compte utilise un mot de passe long et aléatoire pour rester sécurisé.
                   </p>
+
+                  {user?.mustChangePassword && (
+                    <div className="mb-4 p-4 bg-yellow-500/10 text-yellow-700 border border-yellow-500/20 rounded-lg">
+                      <span className="text-sm">
+                        Votre mot de passe a été défini par un administrateur ou a expiré. Choisissez-en un nouveau pour continuer.
+                      </span>
+                    </div>
+                  )}
                   
                   {passwordError && (
                     <div className="mb-4 p-4 bg-destructive/10 text-destructive border border-destructive/20 rounded-lg">
@@ -260,6 +270,11 @@ export const ProfilePage: React.FC = () => {
                     </div>
                   )}
                   
+                  {user?.auth_source === 'ldap' ? (
+                    <p className="text-sm text-muted-foreground">
+                      Ce compte se connecte avec l'annuaire de l'entreprise, son mot de passe se change depuis l'annuaire.
+                    </p>
+                  ) : (
                   <form onSubmit={handlePasswordSubmit} className="space-y-4">
                     <Input
                       label="Mot de passe actuel"
@@ -315,6 +330,7 @@ export const ProfilePage: React.FC = () => {
                       </Button>
                     </div>
                   </form>
+                  )}
                 </div>
               </div>
             )}
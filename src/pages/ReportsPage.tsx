@@ -14,7 +14,12 @@ import Dialog from "../components/ui/Dialog";
 import ActionButtons from "../components/ui/ActionButtons";
 import { useToast } from "../components/ui/Toast";
 import * as ExcelJS from "exceljs";
-import { ROLES, hasRole } from "../types/auth";
+import {
+  CLAIM_ORIGINS,
+  CLAIM_ORIGIN_LABELS,
+  PERMISSIONS,
+  hasPermission,
+} from "../types/auth";
 
 interface NonConformityReport {
   id: string;
@@ -41,6 +46,7 @@ interface NonConformityReport {
   reported_by: string;
   created_at: string;
   updated_at: string;
+  version: number;
   product_name?: string;
   format_display?: string;
 }
@@ -218,10 +224,8 @@ export const ReportsPage: React.FC = () => {
   const [pictureViewModalOpen, setPictureViewModalOpen] = useState(false);
   const [viewingPicture, setViewingPicture] = useState<string | null>(null);
 
-  const canViewPerformance =
-    hasRole(user, ROLES.PERFORMANCE) || hasRole(user, ROLES.ADMIN);
-  const canFullEdit =
-    hasRole(user, ROLES.PERFORMANCE) || hasRole(user, ROLES.ADMIN);
+  const canViewPerformance = hasPermission(user, PERMISSIONS.EDIT_PERFORMANCE);
+  const canFullEdit = hasPermission(user, PERMISSIONS.EDIT_PERFORMANCE);
 
   const storageKey = React.useMemo(
     () =>
@@ -320,72 +324,14 @@ export const ReportsPage: React.FC = () => {
     setVisibleColumnKeys(computedDefaultColumnKeys);
   }, [computedDefaultColumnKeys]);
 
-  // Determine user's accessible claim origins based on their roles
-  const getUserAccessibleClaimOrigins = () => {
-    if (!user) return [];
-
-    const accessibleOrigins = [];
-
-    // Check each role and add corresponding claim origins
-    if (hasRole(user, ROLES.SITE01)) {
-      accessibleOrigins.push("site01");
-    }
-    if (hasRole(user, ROLES.SITE02)) {
-      accessibleOrigins.push("site02");
-    }
-    if (hasRole(user, ROLES.RECLAMATION_CLIENT)) {
-      accessibleOrigins.push("Réclamation client");
-    }
-    if (hasRole(user, ROLES.RETOUR_CLIENT)) {
-      accessibleOrigins.push("Retour client");
-    }
-    if (hasRole(user, ROLES.CONSOMMATEUR)) {
-      accessibleOrigins.push("consommateur");
-    }
-
-    // Admin and performance roles can see all origins
-    if (hasRole(user, ROLES.ADMIN) || hasRole(user, ROLES.PERFORMANCE)) {
-      return [
-        "site01",
-        "site02",
-        "Réclamation client",
-        "Retour client",
-        "consommateur",
-      ];
-    }
-
-    return accessibleOrigins;
-  };
-
-  const userAccessibleOrigins = getUserAccessibleClaimOrigins();
+  // Claim origins the user's roles give access to
+  const userAccessibleOrigins: string[] = user?.origins ?? [];
+  const seesAllOrigins = Object.values(CLAIM_ORIGINS).every((origin) =>
+    userAccessibleOrigins.includes(origin)
+  );
   const hasOnlyOneRole = userAccessibleOrigins.length === 1;
   const shouldPreselect = hasOnlyOneRole && !selectedClaimOrigin;
 
-  // Debug user and accessible origins
-  console.error("[Reports] ROLES constants:", ROLES);
-  console.error("[Reports] User object:", user);
-  console.error("[Reports] User roles array:", user?.roles);
-  console.error("[Reports] User primary role:", user?.role);
-  console.error("[Reports] User accessible origins:", userAccessibleOrigins);
-  console.error("[Reports] Has only one role:", hasOnlyOneRole);
-  console.error("[Reports] Should preselect:", shouldPreselect);
-  console.error(
-    "[Reports] hasRole(user, ROLES.ADMIN):",
-    hasRole(user, ROLES.ADMIN)
-  );
-  console.error(
-    "[Reports] hasRole(user, ROLES.PERFORMANCE):",
-    hasRole(user, ROLES.PERFORMANCE)
-  );
-  console.error(
-    "[Reports] hasRole(user, ROLES.SITE01):",
-    hasRole(user, ROLES.SITE01)
-  );
-  console.error(
-    "[Reports] hasRole(user, ROLES.SITE02):",
-    hasRole(user, ROLES.SITE02)
-  );
-
   useEffect(() => {
     // Debug current filters
     console.debug("[Reports] loadReports triggered with filters:", {
@@ -518,13 +464,13 @@ export const ReportsPage: React.FC = () => {
       // Determine the claim origin filter to apply
       let claimOriginFilter = selectedClaimOrigin || null;
 
-      // For non-admin/performance users, always apply role-based filtering
+      // For users limited to some origins, always apply origin-based filtering
       // When selectedClaimOrigin is empty ("Toutes les origines"), show all accessible origins
       // When selectedClaimOrigin has a value, show only that specific origin (if user has access)
-      if (!hasRole(user, ROLES.ADMIN) && !hasRole(user, ROLES.PERFORMANCE)) {
+      if (!seesAllOrigins) {
         if (!selectedClaimOrigin) {
           // "Toutes les origines" selected - filter by all accessible origins
-          claimOriginFilter = null; // Let backend handle filtering by user_accessible_origins
+          claimOriginFilter = null; // The backend restricts to the user's origins
         } else {
           // Specific origin selected - verify user has access to it
           if (userAccessibleOrigins.includes(selectedClaimOrigin)) {
@@ -536,18 +482,11 @@ export const ReportsPage: React.FC = () => {
         }
       }
 
-      const userAccessibleOriginsToSend =
-        !hasRole(user, ROLES.ADMIN) && !hasRole(user, ROLES.PERFORMANCE)
-          ? userAccessibleOrigins
-          : null;
-
       const payload = {
         page,
         limit: itemsPerPage,
         claim_origin: claimOriginFilter,
         claimOrigin: claimOriginFilter, // Add camelCase version for consistency
-        user_accessible_origins: userAccessibleOriginsToSend,
-        userAccessibleOrigins: userAccessibleOriginsToSend, // Try camelCase version
         // Send both casings to diagnose mapping behavior
         product_id: selectedProduct || null,
         productId: selectedProduct || null,
@@ -601,11 +540,11 @@ export const ReportsPage: React.FC = () => {
       // Determine the claim origin filter for export (same logic as loadReports)
       let exportClaimOriginFilter = selectedClaimOrigin || null;
 
-      // For non-admin/performance users, always apply role-based filtering
-      if (!hasRole(user, ROLES.ADMIN) && !hasRole(user, ROLES.PERFORMANCE)) {
+      // For users limited to some origins, always apply origin-based filtering
+      if (!seesAllOrigins) {
         if (!selectedClaimOrigin) {
           // "Toutes les origines" selected - filter by all accessible origins
-          exportClaimOriginFilter = null; // Let backend handle filtering by user_accessible_origins
+          exportClaimOriginFilter = null; // The backend restricts to the user's origins
         } else {
           // Specific origin selected - verify user has access to it
           if (userAccessibleOrigins.includes(selectedClaimOrigin)) {
@@ -623,10 +562,6 @@ export const ReportsPage: React.FC = () => {
         limit: 999999, // Large number to get all results
         claim_origin: exportClaimOriginFilter,
         claimOrigin: exportClaimOriginFilter, // Add camelCase version for consistency
-        user_accessible_origins:
-          !hasRole(user, ROLES.ADMIN) && !hasRole(user, ROLES.PERFORMANCE)
-            ? userAccessibleOrigins
-            : null,
         product_id: selectedProduct || null,
         productId: selectedProduct || null,
         line_id: selectedLine || null,
@@ -1065,6 +1000,7 @@ export const ReportsPage: React.FC = () => {
           performance: editFormData.performance,
           // Preserve existing picture_data - don't overwrite with null
           picture_data: editingReport.picture_data,
+          version: editingReport.version,
         },
       });
 
@@ -1077,6 +1013,24 @@ export const ReportsPage: React.FC = () => {
       addToast("Rapport mis à jour avec succès", "success");
     } catch (error) {
       console.error("Échec de la mise à jour du rapport :", error);
+      const updateError = error as {
+        kind?: string;
+        message?: string;
+        current?: NonConformityReport;
+      };
+      if (updateError?.kind === "conflict" && updateError.current) {
+        // Keep the user's edits, saving again applies them on top of the newer copy
+        setEditingReport(updateError.current);
+        addToast(
+          "Ce rapport a été modifié par un autre utilisateur. Vérifiez vos changements puis enregistrez à nouveau.",
+          "warning"
+        );
+        return;
+      }
+      if (updateError?.kind === "invalid" && updateError.message) {
+        addToast(`Rapport invalide : ${updateError.message}`, "error");
+        return;
+      }
       addToast("Échec de la mise à jour du rapport", "error");
     } finally {
       setLoading(false);
@@ -1347,30 +1301,11 @@ export const ReportsPage: React.FC = () => {
                   ];
                 }
 
-                // For multiple roles or admin/performance, show accessible origins
+                // For several origins, show only the accessible ones
                 const baseOptions = [
                   { value: "", label: "Toutes les origines" },
                 ];
 
-                if (
-                  hasRole(user, ROLES.ADMIN) ||
-                  hasRole(user, ROLES.PERFORMANCE)
-                ) {
-                  // Admin and performance can see all origins
-                  return [
-                    ...baseOptions,
-                    { value: "site01", label: "Site 01" },
-                    { value: "site02", label: "Site 02" },
-                    {
-                      value: "Réclamation client",
-                      label: "Réclamation client",
-                    },
-                    { value: "Retour client", label: "Retour client" },
-                    { value: "consommateur", label: "Consommateur" },
-                  ];
-                }
-
-                // For users with multiple roles, show only their accessible origins
                 const accessibleOptions = userAccessibleOrigins.map(
                   (origin) => ({
                     value: origin,
@@ -1782,16 +1717,10 @@ export const ReportsPage: React.FC = () => {
                 onChange={(value) =>
                   handleEditInputChange("claim_origin", value)
                 }
-                options={[
-                  {
-                    value: ROLES.RECLAMATION_CLIENT,
-                    label: ROLES.RECLAMATION_CLIENT,
-                  },
-                  { value: ROLES.RETOUR_CLIENT, label: ROLES.RETOUR_CLIENT },
-                  { value: "site01", label: "Site 01" },
-                  { value: "site02", label: "Site 02" },
-                  { value: ROLES.CONSOMMATEUR, label: ROLES.CONSOMMATEUR },
-                ]}
+                options={Object.values(CLAIM_ORIGINS).map((origin) => ({
+                  value: origin,
+                  label: CLAIM_ORIGIN_LABELS[origin],
+                }))}
                 error={editErrors.claim_origin}
                 disabled={true}
               />
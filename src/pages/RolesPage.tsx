@@ -0,0 +1,413 @@
+import React, { useState, useEffect, useCallback } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { toast } from 'react-hot-toast';
+import Button from '../components/ui/Button';
+import Input from '../components/ui/Input';
+import Table from '../components/ui/Table';
+import Dialog from '../components/ui/Dialog';
+import {
+  CLAIM_ORIGINS,
+  CLAIM_ORIGIN_LABELS,
+  ClaimOrigin,
+  PERMISSIONS,
+  PERMISSION_LABELS,
+  Permission,
+  Role,
+} from '../types/auth';
+import { FontAwesomeIcon } from '@fortawesome/react-fontawesome';
+import { faEdit, faTrash } from '@fortawesome/free-solid-svg-icons';
+
+interface RoleForm {
+  id: string; // Empty while creating
+  name: string;
+  description: string;
+  permissions: Permission[];
+  origins: ClaimOrigin[];
+}
+
+const EMPTY_FORM: RoleForm = {
+  id: '',
+  name: '',
+  description: '',
+  permissions: [],
+  origins: [],
+};
+
+const toggle = <T,>(values: T[], value: T, checked: boolean): T[] =>
+  checked ? [...values, value] : values.filter(v => v !== value);
+
+export const RolesPage: React.FC = () => {
+  const [roles, setRoles] = useState<Role[]>([]);
+  const [isLoading, setIsLoading] = useState(true);
+  const [isFormOpen, setIsFormOpen] = useState(false);
+  const [form, setForm] = useState<RoleForm>(EMPTY_FORM);
+  const [roleToDelete, setRoleToDelete] = useState<Role | null>(null);
+  const [isSaving, setIsSaving] = useState(false);
+  const [isDeleting, setIsDeleting] = useState(false);
+
+  const loadRoles = useCallback(async () => {
+    try {
+      setIsLoading(true);
+      setRoles(await invoke<Role[]>('get_roles'));
+    } catch (error) {
+      console.error('Erreur lors du chargement des rôles :', error);
+      toast.error('Échec du chargement des rôles');
+    } finally {
+      setIsLoading(false);
+    }
+  }, []);
+
+  useEffect(() => {
+    loadRoles();
+  }, [loadRoles]);
+
+  const openCreateModal = () => {
+    setForm(EMPTY_FORM);
+    setIsFormOpen(true);
+  };
+
+  const openEditModal = (role: Role) => {
+    setForm({
+      id: role.id,
+      name: role.name,
+      description: role.description || '',
+      permissions: role.permissions,
+      origins: role.origins,
+    });
+    setIsFormOpen(true);
+  };
+
+  const handleSave = async (e: React.FormEvent) => {
+    e.preventDefault();
+
+    if (!form.name.trim()) {
+      toast.error('Le nom du rôle est requis');
+      return;
+    }
+
+    try {
+      setIsSaving(true);
+      const request = {
+        name: form.name,
+        description: form.description || null,
+        permissions: form.permissions,
+        origins: form.origins,
+      };
+      if (form.id) {
+        await invoke('update_role', { request: { id: form.id, ...request } });
+        toast.success('Rôle mis à jour avec succès');
+      } else {
+        await invoke('create_role', { request });
+        toast.success('Rôle créé avec succès');
+      }
+      setIsFormOpen(false);
+      loadRoles();
+    } catch (error) {
+      console.error('Erreur lors de l\'enregistrement du rôle :', error);
+      toast.error(typeof error === 'string' ? error : 'Échec de l\'enregistrement du rôle');
+    } finally {
+      setIsSaving(false);
+    }
+  };
+
+  const handleDelete = async () => {
+    if (!roleToDelete) return;
+
+    try {
+      setIsDeleting(true);
+      await invoke('delete_role', { roleId: roleToDelete.id });
+      toast.success('Rôle supprimé avec succès');
+      setRoleToDelete(null);
+      loadRoles();
+    } catch (error) {
+      console.error('Erreur lors de la suppression du rôle :', error);
+      toast.error(typeof error === 'string' ? error : 'Échec de la suppression du rôle');
+    } finally {
+      setIsDeleting(false);
+    }
+  };
+
+  const editedRole = roles.find(role => role.id === form.id);
+
+  if (isLoading && roles.length === 0) {
+    return (
+      <div className="min-h-screen bg-background p-6">
+        <div className="max-w-7xl mx-auto">
+          <div className="flex items-center justify-center h-64">
+            <div className="animate-spin rounded-full h-8 w-8 border-b-2 border-primary"></div>
+            <span className="ml-3 text-primary">Chargement des rôles...</span>
+          </div>
+        </div>
+      </div>
+    );
+  }
+
+  return (
+    <div className="p-4 lg:p-6 w-full">
+      <div className="w-full">
+        {/* Header */}
+        <div className="mb-8">
+          <div className="flex items-center justify-between">
+            <div>
+              <h1 className="text-2xl font-bold text-foreground mb-2">
+                Gestion des rôles
+              </h1>
+              <p className="text-muted-foreground mb-6">
+                Définissez ce que chaque rôle permet et les origines de réclamation qu'il voit
+              </p>
+            </div>
+            <Button
+              variant="primary"
+              onClick={openCreateModal}
+              className="flex items-center space-x-2"
+            >
+              <svg className="w-4 h-4" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M12 4v16m8-8H4" />
+              </svg>
+              <span>Ajouter un rôle</span>
+            </Button>
+          </div>
+        </div>
+
+        {/* Roles Table */}
+        <Table
+          columns={[
+            {
+              key: 'name',
+              header: 'Rôle',
+              render: (value, role) => {
+                void value;
+                return (
+                  <div>
+                    <div className="text-sm font-medium text-foreground">
+                      {role.name}
+                      {role.is_system && (
+                        <span className="ml-2 inline-flex px-2 py-0.5 text-xs font-semibold rounded-full border bg-muted text-muted-foreground border-muted/20">
+                          Intégré
+                        </span>
+                      )}
+                    </div>
+                    {role.description && (
+                      <div className="text-xs text-muted-foreground">{role.description}</div>
+                    )}
+                  </div>
+                );
+              }
+            },
+            {
+              key: 'permissions',
+              header: 'Permissions',
+              render: (value, role) => {
+                void value;
+                return (
+                  <div className="flex flex-wrap gap-1">
+                    {role.permissions.map((permission: Permission) => (
+                      <span
+                        key={permission}
+                        className="inline-flex px-2 py-1 text-xs font-semibold rounded-full border bg-primary text-primary-foreground border-primary/20"
+                      >
+                        {PERMISSION_LABELS[permission] || permission}
+                      </span>
+                    ))}
+                  </div>
+                );
+              }
+            },
+            {
+              key: 'origins',
+              header: 'Origines',
+              render: (value, role) => {
+                void value;
+                return (
+                  <div className="flex flex-wrap gap-1">
+                    {role.origins.map((origin: ClaimOrigin) => (
+                      <span
+                        key={origin}
+                        className="inline-flex px-2 py-1 text-xs font-semibold rounded-full border bg-secondary text-secondary-foreground border-secondary/20"
+                      >
+                        {CLAIM_ORIGIN_LABELS[origin] || origin}
+                      </span>
+                    ))}
+                  </div>
+                );
+              }
+            },
+            {
+              key: 'user_count',
+              header: 'Utilisateurs',
+            },
+            {
+              key: 'actions',
+              header: 'Actions',
+              render: (value, role) => {
+                void value;
+                return (
+                  <div className="flex items-center justify-end space-x-1">
+                    <button
+                      type="button"
+                      onClick={(e) => {
+                        e.stopPropagation();
+                        openEditModal(role);
+                      }}
+                      className="p-2 text-muted-foreground hover:text-primary hover:bg-primary/10 rounded-full transition-colors duration-200"
+                      title="Modifier le rôle"
+                    >
+                      <FontAwesomeIcon icon={faEdit} className="w-4 h-4" />
+                    </button>
+                    {!role.is_system && (
+                      <button
+                        type="button"
+                        onClick={(e) => {
+                          e.stopPropagation();
+                          setRoleToDelete(role);
+                        }}
+                        className="p-2 text-muted-foreground hover:text-destructive hover:bg-destructive/10 rounded-full transition-colors duration-200"
+                        title="Supprimer le rôle"
+                      >
+                        <FontAwesomeIcon icon={faTrash} className="w-4 h-4" />
+                      </button>
+                    )}
+                  </div>
+                );
+              }
+            }
+          ]}
+          data={roles}
+          hoverable={true}
+        />
+
+        {/* Create / Edit Role Dialog */}
+        <Dialog
+          isOpen={isFormOpen}
+          onClose={() => setIsFormOpen(false)}
+          title={form.id ? 'Modifier le rôle' : 'Nouveau rôle'}
+          maxWidth="lg"
+        >
+          <form onSubmit={handleSave} className="space-y-4">
+            <Input
+              label="Nom"
+              type="text"
+              value={form.name}
+              onChange={(e) => setForm({ ...form, name: e.target.value })}
+              placeholder="Saisissez un nom de rôle"
+              disabled={editedRole?.is_system}
+              helperText={editedRole?.is_system ? 'Les rôles intégrés ne peuvent pas être renommés' : undefined}
+              required
+            />
+
+            <Input
+              label="Description"
+              type="text"
+              value={form.description}
+              onChange={(e) => setForm({ ...form, description: e.target.value })}
+              placeholder="Description optionnelle du rôle"
+            />
+
+            <fieldset>
+              <legend className="block text-sm font-medium text-foreground mb-2">Permissions</legend>
+              <div className="space-y-2">
+                {Object.values(PERMISSIONS).map(permission => (
+                  <label key={permission} className="flex items-center text-sm text-foreground">
+                    <input
+                      type="checkbox"
+                      checked={form.permissions.includes(permission)}
+                      onChange={(e: React.ChangeEvent<HTMLInputElement>) =>
+                        setForm({ ...form, permissions: toggle(form.permissions, permission, e.target.checked) })
+                      }
+                      className="h-4 w-4 rounded border-border/50 dark:border-border/30 focus:ring-primary/50 text-primary"
+                    />
+                    <span className="ml-2">{PERMISSION_LABELS[permission]}</span>
+                  </label>
+                ))}
+              </div>
+            </fieldset>
+
+            <fieldset>
+              <legend className="block text-sm font-medium text-foreground mb-2">Origines de réclamation visibles</legend>
+              <div className="grid grid-cols-2 gap-2">
+                {Object.values(CLAIM_ORIGINS).map(origin => (
+                  <label key={origin} className="flex items-center text-sm text-foreground">
+                    <input
+                      type="checkbox"
+                      checked={form.origins.includes(origin)}
+                      onChange={(e: React.ChangeEvent<HTMLInputElement>) =>
+                        setForm({ ...form, origins: toggle(form.origins, origin, e.target.checked) })
+                      }
+                      className="h-4 w-4 rounded border-border/50 dark:border-border/30 focus:ring-primary/50 text-primary"
+                    />
+                    <span className="ml-2">{CLAIM_ORIGIN_LABELS[origin]}</span>
+                  </label>
+                ))}
+              </div>
+            </fieldset>
+
+            <div className="flex items-center justify-end space-x-3 pt-4">
+              <Button
+                type="button"
+                variant="secondary"
+                onClick={() => setIsFormOpen(false)}
+                disabled={isSaving}
+              >
+                Annuler
+              </Button>
+              <Button
+                type="submit"
+                variant="primary"
+                disabled={isSaving}
+                isLoading={isSaving}
+              >
+                {form.id ? 'Mettre à jour' : 'Créer le rôle'}
+              </Button>
+            </div>
+          </form>
+        </Dialog>
+
+        {/* Delete Role Dialog */}
+        <Dialog
+          isOpen={!!roleToDelete}
+          onClose={() => setRoleToDelete(null)}
+          title="Supprimer le rôle"
+          maxWidth="md"
+        >
+          <div className="mb-6">
+            <div className="flex items-start space-x-3 p-4 bg-destructive/10 dark:bg-destructive/20 rounded-lg border border-destructive/20">
+              <div>
+                <h3 className="text-sm font-medium text-destructive">
+                  Êtes-vous sûr de vouloir supprimer ce rôle ?
+                </h3>
+                <p className="text-sm text-destructive/80 mt-1">
+                  Rôle : <strong>{roleToDelete?.name}</strong>
+                </p>
+                <p className="text-sm text-destructive/80">
+                  Un rôle encore attribué à des utilisateurs ne peut pas être supprimé.
+                </p>
+              </div>
+            </div>
+          </div>
+
+          <div className="flex items-center justify-end space-x-3">
+            <Button
+              type="button"
+              variant="secondary"
+              onClick={() => setRoleToDelete(null)}
+              disabled={isDeleting}
+            >
+              Annuler
+            </Button>
+            <Button
+              type="button"
+              variant="danger"
+              onClick={handleDelete}
+              disabled={isDeleting}
+              isLoading={isDeleting}
+            >
+              Supprimer
+            </Button>
+          </div>
+        </Dialog>
+      </div>
+    </div>
+  );
+};
+
+export default RolesPage;
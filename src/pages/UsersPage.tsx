@@ -6,20 +6,36 @@ import Input from '../components/ui/Input';
 import Table from '../components/ui/Table';
 import MultiSelect from '../components/ui/MultiSelect';
 import Dialog from '../components/ui/Dialog';
-import { ROLES } from '../types/auth';
+import { PERMISSIONS, Role } from '../types/auth';
 import { FontAwesomeIcon } from '@fortawesome/react-fontawesome';
-import { faUserEdit, faUserTimes } from '@fortawesome/free-solid-svg-icons';
+import { faHistory, faUserCheck, faUserEdit, faUserSlash, faUserTimes } from '@fortawesome/free-solid-svg-icons';
 import { PaginatedResponse } from '../types/pagination';
 
 interface User {
   id: string;
   username: string;
-  role: string;
-  roles?: string[];
+  roles: string[];
   created_at: string;
   updated_at: string;
+  last_login_at: string | null;
+  login_count: number;
+  status: 'active' | 'disabled' | 'archived';
+}
+
+interface UserActivity {
+  kind: 'report_created' | 'report_edited' | 'status_changed';
+  report_id: string;
+  report_number: string;
+  detail: string | null;
+  occurred_at: string;
 }
 
+const ACTIVITY_LABELS: Record<UserActivity['kind'], string> = {
+  report_created: 'Rapport créé',
+  report_edited: 'Rapport modifié',
+  status_changed: 'Statut modifié',
+};
+
 interface CreateUserForm {
   username: string;
   password: string;
@@ -33,18 +49,9 @@ interface EditUserForm {
   newPassword?: string;
 }
 
-const ROLE_OPTIONS = [
-  { value: ROLES.ADMIN, label: 'Administrateur' },
-  { value: ROLES.RECLAMATION_CLIENT, label: 'Réclamation client' },
-  { value: ROLES.RETOUR_CLIENT, label: 'Retour client' },
-  { value: ROLES.SITE01, label: 'Site 01' },
-  { value: ROLES.SITE02, label: 'Site 02' },
-  { value: ROLES.PERFORMANCE, label: 'Performance' },
-  { value: ROLES.CONSOMMATEUR, label: 'Consommateur' },
-];
-
 export const UsersPage: React.FC = () => {
   const [users, setUsers] = useState<User[]>([]);
+  const [roles, setRoles] = useState<Role[]>([]);
   const [isLoading, setIsLoading] = useState(true);
   const [error, setError] = useState('');
   const [pagination, setPagination] = useState({
@@ -74,6 +81,9 @@ export const UsersPage: React.FC = () => {
     newPassword: ''
   });
   const [userToDelete, setUserToDelete] = useState<User | null>(null);
+  const [activityUser, setActivityUser] = useState<User | null>(null);
+  const [activity, setActivity] = useState<UserActivity[]>([]);
+  const [isLoadingActivity, setIsLoadingActivity] = useState(false);
   
   // Loading states
   const [isCreating, setIsCreating] = useState(false);
@@ -93,6 +103,17 @@ export const UsersPage: React.FC = () => {
     loadUsers();
   }, [pagination.currentPage, pagination.itemsPerPage, debouncedSearchTerm]);
 
+  useEffect(() => {
+    invoke<Role[]>('get_roles')
+      .then(setRoles)
+      .catch(error => {
+        console.error('Erreur lors du chargement des rôles :', error);
+        toast.error('Échec du chargement des rôles');
+      });
+  }, []);
+
+  const roleOptions = roles.map(role => ({ value: role.name, label: role.name }));
+
   const loadUsers = useCallback(async () => {
     try {
       setIsLoading(true);
@@ -137,7 +158,6 @@ export const UsersPage: React.FC = () => {
       await invoke('create_user', {
         username: createForm.username.trim(),
         password: createForm.password,
-        role: createForm.roles[0], // Primary role for backward compatibility
         roles: createForm.roles
       });
       
@@ -164,19 +184,26 @@ export const UsersPage: React.FC = () => {
     try {
       setIsUpdating(true);
       
-      // Update username if changed
+      // Each update is refused if the user changed since the list was loaded
       const originalUser = users.find(u => u.id === editForm.id);
-      if (originalUser && originalUser.username !== editForm.username.trim()) {
-        await invoke('update_username', {
+      if (!originalUser) return;
+      let updatedAt = originalUser.updated_at;
+
+      // Update username if changed
+      if (originalUser.username !== editForm.username.trim()) {
+        const renamed = await invoke<User>('update_username', {
           userId: editForm.id,
-          newUsername: editForm.username.trim()
+          newUsername: editForm.username.trim(),
+          expectedUpdatedAt: updatedAt
         });
+        updatedAt = renamed.updated_at;
       }
       
       // Update roles - always update since roles array might have changed
       await invoke('update_user_roles', {
         userId: editForm.id,
-        newRoles: editForm.roles
+        newRoles: editForm.roles,
+        expectedUpdatedAt: updatedAt
       });
       
       toast.success('Utilisateur mis à jour avec succès');
@@ -204,17 +231,29 @@ export const UsersPage: React.FC = () => {
       await loadUsers();
     } catch (error) {
       console.error('Erreur lors de la suppression de l\'utilisateur :', error);
-      toast.error(error instanceof Error ? error.message : 'Échec de la suppression de l\'utilisateur');
+      toast.error(typeof error === 'string' ? error : 'Échec de la suppression de l\'utilisateur');
     } finally {
       setIsDeleting(false);
     }
   };
 
+  const handleToggleActive = async (user: User) => {
+    try {
+      const isActive = user.status === 'active';
+      await invoke(isActive ? 'disable_user' : 'enable_user', { userId: user.id });
+      toast.success(isActive ? 'Utilisateur désactivé' : 'Utilisateur réactivé');
+      await loadUsers();
+    } catch (error) {
+      console.error('Erreur lors du changement de statut de l\'utilisateur :', error);
+      toast.error(typeof error === 'string' ? error : 'Échec du changement de statut de l\'utilisateur');
+    }
+  };
+
   const openEditModal = (user: User) => {
     setEditForm({
       id: user.id,
       username: user.username,
-      roles: user.roles || [user.role], // Use roles array or fallback to single role
+      roles: user.roles,
       newPassword: ''
     });
     setIsEditModalOpen(true);
@@ -225,6 +264,20 @@ export const UsersPage: React.FC = () => {
     setIsDeleteModalOpen(true);
   };
 
+  const openActivityModal = async (user: User) => {
+    setActivityUser(user);
+    setActivity([]);
+    try {
+      setIsLoadingActivity(true);
+      setActivity(await invoke<UserActivity[]>('get_user_activity', { userId: user.id }));
+    } catch (error) {
+      console.error("Erreur lors du chargement de l'activité :", error);
+      toast.error("Échec du chargement de l'activité");
+    } finally {
+      setIsLoadingActivity(false);
+    }
+  };
+
   const formatDate = (dateString: string) => {
     return new Date(dateString).toLocaleDateString('fr-FR', {
       year: 'numeric',
@@ -235,28 +288,15 @@ export const UsersPage: React.FC = () => {
     });
   };
 
-  const getRoleLabel = (role: string) => {
-    const roleObj = ROLE_OPTIONS.find(r => r.value === role);
-    return roleObj ? roleObj.label : role;
-  };
-
-  const getRoleBadgeColor = (role: string) => {
-    switch (role) {
-      case ROLES.ADMIN:
-        return 'bg-muted text-muted-foreground border-muted/20';
-      case ROLES.PERFORMANCE:
-        return 'bg-primary text-primary-foreground border-primary/20';
-      case ROLES.SITE01:
-      case ROLES.SITE02:
-        return 'bg-secondary text-secondary-foreground border-secondary/20';
-      case ROLES.RECLAMATION_CLIENT:
-      case ROLES.RETOUR_CLIENT:
-        return 'bg-popover text-popover-foreground border-popover/20';
-      case ROLES.CONSOMMATEUR:
-        return 'bg-accent text-accent-foreground border-accent/20';
-      default:
-        return 'bg-ring text-input border-ring/20';
+  const getRoleBadgeColor = (name: string) => {
+    const role = roles.find(r => r.name === name);
+    if (role?.permissions.includes(PERMISSIONS.MANAGE_USERS)) {
+      return 'bg-muted text-muted-foreground border-muted/20';
     }
+    if (role?.permissions.includes(PERMISSIONS.EDIT_PERFORMANCE)) {
+      return 'bg-primary text-primary-foreground border-primary/20';
+    }
+    return role ? 'bg-secondary text-secondary-foreground border-secondary/20' : 'bg-ring text-input border-ring/20';
   };
 
   const handlePageChange = (page: number) => {
@@ -349,25 +389,29 @@ export const UsersPage: React.FC = () => {
                       <div className="text-sm text-muted-foreground">
                         ID: {user.id.slice(0, 8)}...
                       </div>
+                      {user.status === 'disabled' && (
+                        <span className="inline-flex px-2 py-0.5 mt-1 text-xs font-semibold rounded-full border bg-muted text-muted-foreground border-border">
+                          Désactivé
+                        </span>
+                      )}
                     </div>
                   </div>
                 );
               }
             },
             {
-              key: 'role',
+              key: 'roles',
               header: 'Rôles',
               render: (value, user) => {
                 void value; // Acknowledge unused parameter
-                const userRoles = user.roles && user.roles.length > 0 ? user.roles : [user.role];
                 return (
                   <div className="flex flex-wrap gap-1">
-                    {userRoles.map((role: string, index: number) => (
+                    {user.roles.map((role: string, index: number) => (
                       <span 
                         key={index}
                         className={`inline-flex px-2 py-1 text-xs font-semibold rounded-full border ${getRoleBadgeColor(role)}`}
                       >
-                        {getRoleLabel(role)}
+                        {role}
                       </span>
                     ))}
                   </div>
@@ -384,6 +428,18 @@ export const UsersPage: React.FC = () => {
               header: 'Mis à jour le',
               render: (value) => formatDate(value)
             },
+            {
+              key: 'last_login_at',
+              header: 'Dernière connexion',
+              render: (value, user) => (
+                <div>
+                  <div className="text-sm text-foreground">{value ? formatDate(value) : 'Jamais'}</div>
+                  <div className="text-xs text-muted-foreground">
+                    {user.login_count} connexion{user.login_count > 1 ? 's' : ''}
+                  </div>
+                </div>
+              )
+            },
             {
               key: 'actions',
               header: 'Actions',
@@ -391,6 +447,17 @@ export const UsersPage: React.FC = () => {
                 void value;
                 return (
                   <div className="flex items-center justify-end space-x-1">
+                    <button
+                      type="button"
+                      onClick={(e) => {
+                        e.stopPropagation();
+                        openActivityModal(user);
+                      }}
+                      className="p-2 text-muted-foreground hover:text-primary hover:bg-primary/10 rounded-full transition-colors duration-200"
+                      title="Activité de l'utilisateur"
+                    >
+                      <FontAwesomeIcon icon={faHistory} className="w-4 h-4" />
+                    </button>
                     <button
                       type="button"
                       onClick={(e) => {
@@ -402,6 +469,17 @@ export const UsersPage: React.FC = () => {
                     >
                       <FontAwesomeIcon icon={faUserEdit} className="w-4 h-4" />
                     </button>
+                    <button
+                      type="button"
+                      onClick={(e) => {
+                        e.stopPropagation();
+                        handleToggleActive(user);
+                      }}
+                      className="p-2 text-muted-foreground hover:text-primary hover:bg-primary/10 rounded-full transition-colors duration-200"
+                      title={user.status === 'active' ? "Désactiver l'utilisateur" : "Réactiver l'utilisateur"}
+                    >
+                      <FontAwesomeIcon icon={user.status === 'active' ? faUserSlash : faUserCheck} className="w-4 h-4" />
+                    </button>
                     <button
                       type="button"
                       onClick={(e) => {
@@ -461,7 +539,7 @@ export const UsersPage: React.FC = () => {
               label="Rôles"
               value={createForm.roles}
               onChange={(roles) => setCreateForm({ ...createForm, roles })}
-              options={ROLE_OPTIONS}
+              options={roleOptions}
               placeholder="Sélectionnez les rôles..."
             />
             
@@ -507,7 +585,7 @@ export const UsersPage: React.FC = () => {
               label="Rôles"
               value={editForm.roles}
               onChange={(roles) => setEditForm({ ...editForm, roles })}
-              options={ROLE_OPTIONS}
+              options={roleOptions}
               placeholder="Sélectionnez les rôles..."
             />
             
@@ -561,7 +639,7 @@ export const UsersPage: React.FC = () => {
                   Utilisateur : <strong>{userToDelete?.username}</strong>
                 </p>
                 <p className="text-sm text-destructive/80">
-                  Cette action est irréversible.
+                  Cette action est irréversible. Un utilisateur ayant créé des rapports doit être désactivé plutôt que supprimé.
                 </p>
               </div>
             </div>
@@ -587,6 +665,38 @@ export const UsersPage: React.FC = () => {
             </Button>
           </div>
         </Dialog>
+
+        {/* User Activity Dialog */}
+        <Dialog
+          isOpen={!!activityUser}
+          onClose={() => setActivityUser(null)}
+          title={`Activité de ${activityUser?.username ?? ''}`}
+          maxWidth="lg"
+        >
+          {isLoadingActivity ? (
+            <p className="text-sm text-muted-foreground">Chargement...</p>
+          ) : activity.length === 0 ? (
+            <p className="text-sm text-muted-foreground">Aucune activité enregistrée</p>
+          ) : (
+            <ul className="divide-y divide-border max-h-96 overflow-y-auto">
+              {activity.map((entry, index) => (
+                <li key={index} className="py-2 flex items-start justify-between gap-4">
+                  <div>
+                    <div className="text-sm font-medium text-foreground">
+                      {ACTIVITY_LABELS[entry.kind]} · {entry.report_number}
+                    </div>
+                    {entry.detail && (
+                      <div className="text-xs text-muted-foreground">{entry.detail}</div>
+                    )}
+                  </div>
+                  <div className="text-xs text-muted-foreground whitespace-nowrap">
+                    {formatDate(entry.occurred_at)}
+                  </div>
+                </li>
+              ))}
+            </ul>
+          )}
+        </Dialog>
       </div>
     </div>
   );
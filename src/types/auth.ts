@@ -1,48 +1,77 @@
-// These should match the backend's UserRole enum exactly
-export const ROLES = {
+// These should match the backend's Permission enum exactly
+export const PERMISSIONS = {
+  CREATE_REPORT: 'create_report',
+  EDIT_PERFORMANCE: 'edit_performance',
+  MANAGE_MASTER_DATA: 'manage_master_data',
+  MANAGE_USERS: 'manage_users',
+} as const;
+
+export type Permission = typeof PERMISSIONS[keyof typeof PERMISSIONS];
+
+// These should match the backend's ClaimOrigin enum exactly
+export const CLAIM_ORIGINS = {
   RECLAMATION_CLIENT: 'Réclamation client',
   RETOUR_CLIENT: 'Retour client',
   SITE01: 'site01',
   SITE02: 'site02',
-  PERFORMANCE: 'performance',
-  ADMIN: 'admin',
   CONSOMMATEUR: 'consommateur',
 } as const;
 
-export type UserRole = typeof ROLES[keyof typeof ROLES];
+export type ClaimOrigin = typeof CLAIM_ORIGINS[keyof typeof CLAIM_ORIGINS];
 
-export interface ProtectedRouteProps {
-  allowedRoles: UserRole[];
-  children: React.ReactNode;
-}
+export const PERMISSION_LABELS: Record<Permission, string> = {
+  create_report: 'Créer des rapports',
+  edit_performance: 'Modifier la performance',
+  manage_master_data: 'Gérer les données de référence',
+  manage_users: 'Gérer les utilisateurs et les rôles',
+};
+
+export const CLAIM_ORIGIN_LABELS: Record<ClaimOrigin, string> = {
+  'Réclamation client': 'Réclamation client',
+  'Retour client': 'Retour client',
+  site01: 'Site 01',
+  site02: 'Site 02',
+  consommateur: 'Consommateur',
+};
 
 export interface User {
   id: string;
   username: string;
-  role: string; // Primary role for backward compatibility
-  roles: string[]; // Multiple roles array
+  roles: string[]; // Role names, defined on the roles page
+  permissions: Permission[]; // Granted by any of the roles
+  origins: ClaimOrigin[]; // Claim origins the user can see and report on
+  auth_source?: 'local' | 'ldap'; // Directory accounts change their password in the directory
+  mustChangePassword?: boolean; // Set until the user replaces an admin-set or expired password
+  updated_at: string; // Sent back with the user's own updates, which are refused if the account changed since
+}
+
+export interface Role {
+  id: string;
+  name: string;
+  description?: string | null;
+  permissions: Permission[];
+  origins: ClaimOrigin[];
+  is_system: boolean; // Seeded roles cannot be renamed or deleted
+  user_count: number;
+  created_at: string;
+  updated_at: string;
 }
 
 export interface NavItem {
   icon: any; // This will be FontAwesome icon
   label: string;
   path: string;
-  allowedRoles: UserRole[];
+  permission?: Permission;
   showInNav?: boolean;
 }
 
-// Utility functions for role checking
-export const hasRole = (user: User | null, role: UserRole): boolean => {
-  if (!user) return false;
-  return user.roles.includes(role);
-};
-
-export const hasAnyRole = (user: User | null, roles: UserRole[]): boolean => {
+// Utility functions for permission checking
+export const hasPermission = (user: User | null, permission: Permission): boolean => {
   if (!user) return false;
-  return roles.some(role => user.roles.includes(role));
+  return user.permissions.includes(permission);
 };
 
-export const hasAllRoles = (user: User | null, roles: UserRole[]): boolean => {
+export const canAccessOrigin = (user: User | null, origin: string): boolean => {
   if (!user) return false;
-  return roles.every(role => user.roles.includes(role));
+  return (user.origins as string[]).includes(origin);
 };